@@ -0,0 +1,13 @@
+// 打包时可以通过 DDNS_DEFAULT_CRON 环境变量修改未配置 cron 时使用的默认值，不需要修改源码
+const FALLBACK_CRON: &str = "0 */5 * * * *";
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=DDNS_DEFAULT_CRON");
+
+    let cron = std::env::var("DDNS_DEFAULT_CRON").unwrap_or_else(|_| FALLBACK_CRON.to_string());
+    let cron = cron.trim();
+    if cron.is_empty() {
+        panic!("DDNS_DEFAULT_CRON is set but empty");
+    }
+    println!("cargo:rustc-env=DDNS_DEFAULT_CRON={}", cron);
+}
@@ -0,0 +1,101 @@
+// 按前缀（CIDR）过滤检测到的地址（allowed_ipv6_cidrs / denied_ipv6_cidrs）
+// 只允许已知的委派前缀，避免把 VPN 等其他接口的地址发布出去
+use std::fmt;
+use std::net::Ipv6Addr;
+
+use crate::BoxError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ipv6Cidr {
+    network: Ipv6Addr,
+    len: u8,
+}
+
+impl Ipv6Cidr {
+    // 如 2001:db8:1234::/48；不带前缀长度时表示单个地址（/128）
+    pub fn parse(spec: &str) -> Result<Self, BoxError> {
+        let spec = spec.trim();
+        let (address, len) = match spec.split_once('/') {
+            Some((address, len)) => {
+                let len = len.parse::<u8>().ok().filter(|len| *len <= 128);
+                (address, len.ok_or_else(|| format!("Invalid IPv6 prefix length in '{}'", spec))?)
+            }
+            None => (spec, 128),
+        };
+        let address: Ipv6Addr = address
+            .parse()
+            .map_err(|_| format!("Invalid IPv6 CIDR '{}', expected a prefix like 2001:db8::/48", spec))?;
+        Ok(Self {
+            network: Ipv6Addr::from(u128::from(address) & mask(len)),
+            len,
+        })
+    }
+
+    pub fn contains(&self, ip: Ipv6Addr) -> bool {
+        u128::from(ip) & mask(self.len) == u128::from(self.network)
+    }
+}
+
+impl fmt::Display for Ipv6Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.len)
+    }
+}
+
+fn mask(len: u8) -> u128 {
+    u128::MAX.checked_shl(128 - u32::from(len)).unwrap_or(0)
+}
+
+pub fn parse_list(specs: &[String]) -> Result<Vec<Ipv6Cidr>, BoxError> {
+    specs.iter().map(|spec| Ipv6Cidr::parse(spec)).collect()
+}
+
+// 允许列表不为空时地址必须在其中一个前缀内，且不能在任何拒绝的前缀内；拒绝时返回原因
+pub fn check(ip: Ipv6Addr, allowed: &[Ipv6Cidr], denied: &[Ipv6Cidr]) -> Result<(), BoxError> {
+    if let Some(cidr) = denied.iter().find(|cidr| cidr.contains(ip)) {
+        return Err(format!("Detected address {} is inside denied prefix {}, not publishing", ip, cidr).into());
+    }
+    if !allowed.is_empty() && !allowed.iter().any(|cidr| cidr.contains(ip)) {
+        let allowed: Vec<String> = allowed.iter().map(ToString::to_string).collect();
+        return Err(format!(
+            "Detected address {} is outside the allowed prefixes ({}), not publishing",
+            ip,
+            allowed.join(", ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_contains() {
+        let cidr = Ipv6Cidr::parse("2001:db8:1234:ff::1/48").unwrap();
+        assert_eq!(cidr.to_string(), "2001:db8:1234::/48");
+        assert!(cidr.contains("2001:db8:1234:5::1".parse().unwrap()));
+        assert!(!cidr.contains("2001:db8:1235::1".parse().unwrap()));
+
+        assert!(Ipv6Cidr::parse("::/0").unwrap().contains("fd00::1".parse().unwrap()));
+        assert_eq!(Ipv6Cidr::parse("2001:db8::1").unwrap().to_string(), "2001:db8::1/128");
+        assert!(Ipv6Cidr::parse("2001:db8::/129").is_err());
+        assert!(Ipv6Cidr::parse("10.0.0.0/8").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let allowed = parse_list(&["2001:db8::/32".to_string()]).unwrap();
+        let denied = parse_list(&["2001:db8:dead::/48".to_string()]).unwrap();
+
+        assert!(check("2001:db8:1::1".parse().unwrap(), &allowed, &denied).is_ok());
+        let err = check("fd7a:115c:a1e0::1".parse().unwrap(), &allowed, &denied).unwrap_err();
+        assert!(err.to_string().contains("outside the allowed prefixes (2001:db8::/32)"), "{}", err);
+        let err = check("2001:db8:dead::1".parse().unwrap(), &allowed, &denied).unwrap_err();
+        assert!(err.to_string().contains("inside denied prefix 2001:db8:dead::/48"), "{}", err);
+
+        // 都为空时不过滤
+        assert!(check("fd00::1".parse().unwrap(), &[], &[]).is_ok());
+    }
+}
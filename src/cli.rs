@@ -0,0 +1,222 @@
+// 命令行参数解析
+pub const USAGE: &str = "\
+Usage: rs-refresh-ddns [OPTIONS]
+
+Options:
+  --generate-config [PATH]  Write a commented sample config.toml to PATH (or stdout) and exit
+  --test-notify             Send a sample payload to notify_webhook_url, report the result and exit
+  --once                    Run a single update, print the result and exit (non-zero on failure)
+  --fail-fast               With --once, stop at the first provider failure instead of trying
+                            every provider
+  --print-ip                Detect the current IPv6 address, print it and exit
+  --show-url                Detect the current address, print the update URL each provider would
+                            request (token redacted) without sending it, and exit
+  --show-config             Print the effective configuration with secrets redacted and exit
+  --reset-state             Delete status_file and metrics_file so the next run starts fresh, and exit
+  --test-providers          Check every configured provider without changing records, print a
+                            pass/fail table and exit (non-zero if any fails)
+  --daemonize               Detach from the terminal and run in the background (Unix only),
+                            writing pid_file if configured; do not use under systemd
+  --profile <name>          Use only this [profiles.<name>] section of config.toml; required by
+                            the one-shot commands when several profiles are defined
+  --format <text|json>      Output format for --once, --print-ip, --show-url, --show-config and
+                            --test-providers
+                            (default: text)
+  -v, --verbose             Log at debug level (-vv for trace), overrides log_level
+  -q, --quiet               Log only warnings (-qq for errors only), overrides log_level
+  -h, --help                Print this help and exit
+";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Cli {
+    // 生成示例配置，内层为输出路径，None 表示输出到标准输出
+    pub generate_config: Option<Option<String>>,
+    pub help: bool,
+    pub test_notify: bool,
+    pub once: bool,
+    // --once 时第一个提供商失败就停止其余更新
+    pub fail_fast: bool,
+    pub print_ip: bool,
+    pub show_url: bool,
+    pub show_config: bool,
+    pub reset_state: bool,
+    pub test_providers: bool,
+    pub daemonize: bool,
+    // 只运行配置文件中的该配置档案
+    pub profile: Option<String>,
+    pub format: OutputFormat,
+    // -v 每次加一，-q 每次减一，0 表示使用配置中的 log_level
+    pub verbosity: i8,
+}
+
+// --once、--print-ip、--show-url、--show-config、--test-providers 的输出格式
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!("Invalid --format '{}', expected text or json", other)),
+        }
+    }
+}
+
+impl Cli {
+    // 一次性命令的结果输出到标准输出，日志需要改写到标准错误
+    pub fn one_shot(&self) -> bool {
+        self.once || self.print_ip || self.show_url || self.show_config || self.test_providers || self.reset_state
+    }
+
+    // 命令行指定的日志级别，未指定 -v/-q 时返回 None
+    pub fn log_level(&self) -> Option<tracing::Level> {
+        match self.verbosity {
+            0 => None,
+            v if v >= 2 => Some(tracing::Level::TRACE),
+            1 => Some(tracing::Level::DEBUG),
+            -1 => Some(tracing::Level::WARN),
+            _ => Some(tracing::Level::ERROR),
+        }
+    }
+}
+
+impl Cli {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Self::default();
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--generate-config" => {
+                    // 路径参数可选
+                    let path = args.next_if(|next| !next.starts_with('-'));
+                    cli.generate_config = Some(path);
+                }
+                "-h" | "--help" => cli.help = true,
+                "--test-notify" => cli.test_notify = true,
+                "--once" => cli.once = true,
+                "--fail-fast" => cli.fail_fast = true,
+                "--print-ip" => cli.print_ip = true,
+                "--show-url" => cli.show_url = true,
+                "--show-config" => cli.show_config = true,
+                "--reset-state" => cli.reset_state = true,
+                "--test-providers" => cli.test_providers = true,
+                "--daemonize" => cli.daemonize = true,
+                "--profile" => {
+                    let value = args.next().ok_or("--profile requires a profile name")?;
+                    cli.profile = Some(value);
+                }
+                "--format" => {
+                    let value = args.next().ok_or("--format requires a value (text or json)")?;
+                    cli.format = OutputFormat::parse(&value)?;
+                }
+                "--verbose" => cli.verbosity = cli.verbosity.saturating_add(1),
+                "--quiet" => cli.verbosity = cli.verbosity.saturating_sub(1),
+                // 支持叠加写法，如 -vv、-qq
+                _ if repeated_flag(&arg, 'v') > 0 => {
+                    cli.verbosity = cli.verbosity.saturating_add(repeated_flag(&arg, 'v'));
+                }
+                _ if repeated_flag(&arg, 'q') > 0 => {
+                    cli.verbosity = cli.verbosity.saturating_sub(repeated_flag(&arg, 'q'));
+                }
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+
+        // 一次性命令在前台运行完就退出，转入后台没有意义
+        if cli.daemonize && (cli.one_shot() || cli.test_notify) {
+            return Err("--daemonize cannot be combined with --once, --print-ip, --show-url, --show-config, --test-providers, --reset-state or --test-notify".to_string());
+        }
+
+        if cli.fail_fast && !cli.once {
+            return Err("--fail-fast can only be used with --once".to_string());
+        }
+
+        Ok(cli)
+    }
+}
+
+// "-vvv" 这类参数中重复的标志个数，不是这种形式时返回 0
+fn repeated_flag(arg: &str, flag: char) -> i8 {
+    match arg.strip_prefix('-') {
+        Some(flags) if !flags.is_empty() && flags.chars().all(|c| c == flag) => flags.len().min(i8::MAX as usize) as i8,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        Cli::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_generate_config() {
+        assert_eq!(parse(&[]).unwrap(), Cli::default());
+        assert_eq!(parse(&["--generate-config"]).unwrap().generate_config, Some(None));
+        assert_eq!(
+            parse(&["--generate-config", "config.toml"]).unwrap().generate_config,
+            Some(Some("config.toml".to_string()))
+        );
+        assert!(parse(&["--test-notify"]).unwrap().test_notify);
+        assert!(parse(&["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_verbosity() {
+        assert_eq!(parse(&[]).unwrap().log_level(), None);
+        assert_eq!(parse(&["-v"]).unwrap().log_level(), Some(tracing::Level::DEBUG));
+        assert_eq!(parse(&["-vv"]).unwrap().log_level(), Some(tracing::Level::TRACE));
+        assert_eq!(parse(&["-v", "--verbose"]).unwrap().log_level(), Some(tracing::Level::TRACE));
+        assert_eq!(parse(&["-q"]).unwrap().log_level(), Some(tracing::Level::WARN));
+        assert_eq!(parse(&["-qq"]).unwrap().log_level(), Some(tracing::Level::ERROR));
+        assert_eq!(parse(&["-v", "-q"]).unwrap().log_level(), None);
+        assert!(parse(&["-vq"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_format() {
+        let cli = parse(&["--once", "--format", "json"]).unwrap();
+        assert!(cli.once && cli.one_shot());
+        assert_eq!(cli.format, OutputFormat::Json);
+        assert_eq!(parse(&["--print-ip"]).unwrap().format, OutputFormat::Text);
+        assert!(!parse(&["--test-notify"]).unwrap().one_shot());
+        assert!(parse(&["--format"]).is_err());
+        assert!(parse(&["--format", "yaml"]).is_err());
+        assert!(parse(&["--test-providers"]).unwrap().one_shot());
+        assert!(parse(&["--show-url", "--format", "json"]).unwrap().one_shot());
+    }
+
+    #[test]
+    fn test_parse_daemonize() {
+        assert!(parse(&["--daemonize"]).unwrap().daemonize);
+        assert!(parse(&["--daemonize", "--once"]).is_err());
+        assert!(parse(&["--test-notify", "--daemonize"]).is_err());
+        assert!(parse(&["--reset-state", "--daemonize"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_fail_fast() {
+        assert!(parse(&["--once", "--fail-fast"]).unwrap().fail_fast);
+        assert!(!parse(&["--once"]).unwrap().fail_fast);
+        assert!(parse(&["--fail-fast"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        assert_eq!(parse(&["--once", "--profile", "home"]).unwrap().profile.as_deref(), Some("home"));
+        assert_eq!(parse(&[]).unwrap().profile, None);
+        assert!(parse(&["--profile"]).is_err());
+    }
+}
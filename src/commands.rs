@@ -0,0 +1,266 @@
+// 供脚本调用的一次性命令：--print-ip、--show-url、--once、--show-config、--test-providers、--reset-state
+// --format json 时标准输出只有一个JSON对象，字段名保持稳定；失败时 success 为 false 并以非零状态退出
+use futures::future::join_all;
+use tracing::Instrument;
+
+use crate::cli::OutputFormat;
+use crate::local::LocalCache;
+use crate::providers;
+use crate::supervisor::{RunStatus, Supervisor};
+use crate::{build_http_client, get_ipv6_address, normalize_ipv6, BoxError, Config};
+
+#[derive(serde::Serialize)]
+struct IpReport {
+    success: bool,
+    method: String,
+    ip: Option<String>,
+    error: Option<String>,
+}
+
+// --print-ip：只检测地址，不更新任何提供商；返回是否成功
+pub async fn print_ip(config: &Config, format: OutputFormat) -> Result<bool, BoxError> {
+    let client = build_http_client(config)?;
+    let result = match get_ipv6_address(config, &client, &LocalCache::default()).await {
+        Ok(raw) => normalize_ipv6(&raw),
+        Err(e) => Err(e),
+    };
+
+    match format {
+        OutputFormat::Text => {
+            println!("{}", result?);
+            Ok(true)
+        }
+        OutputFormat::Json => {
+            let report = IpReport {
+                success: result.is_ok(),
+                method: config.ipv6_method().to_string(),
+                ip: result.as_ref().ok().map(|ip| ip.to_string()),
+                error: result.as_ref().err().map(|e| e.to_string()),
+            };
+            println!("{}", serde_json::to_string(&report)?);
+            Ok(report.success)
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ProviderUrl {
+    provider: String,
+    url: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct UrlReport {
+    success: bool,
+    ip: Option<String>,
+    urls: Vec<ProviderUrl>,
+    error: Option<String>,
+}
+
+// --show-url：检测地址并输出各提供商将请求的地址（密钥已隐藏），不发送请求；返回是否成功
+pub async fn show_url(config: &Config, format: OutputFormat) -> Result<bool, BoxError> {
+    let client = build_http_client(config)?;
+    let providers = providers::build_providers(config)?;
+    let result = async {
+        let ip = normalize_ipv6(&get_ipv6_address(config, &client, &LocalCache::default()).await?)?.to_string();
+        let urls = providers
+            .iter()
+            .map(|provider| {
+                Ok(ProviderUrl {
+                    provider: provider.name().to_string(),
+                    url: provider.update_url(&ip)?,
+                })
+            })
+            .collect::<Result<Vec<_>, BoxError>>()?;
+        Ok::<_, BoxError>((ip, urls))
+    }
+    .await;
+
+    match format {
+        OutputFormat::Text => {
+            let (_, urls) = result?;
+            for entry in urls {
+                match entry.url {
+                    Some(url) => println!("{}  {}", entry.provider, url),
+                    None => println!("{}  (no single update URL for this provider)", entry.provider),
+                }
+            }
+            Ok(true)
+        }
+        OutputFormat::Json => {
+            let report = match result {
+                Ok((ip, urls)) => UrlReport {
+                    success: true,
+                    ip: Some(ip),
+                    urls,
+                    error: None,
+                },
+                Err(e) => UrlReport {
+                    success: false,
+                    ip: None,
+                    urls: Vec::new(),
+                    error: Some(e.to_string()),
+                },
+            };
+            println!("{}", serde_json::to_string(&report)?);
+            Ok(report.success)
+        }
+    }
+}
+
+// --once：完整执行一次更新（含状态文件、钩子和通知），JSON 字段与 status_file 相同
+// fail_fast 时第一个提供商失败就停止，否则尝试所有提供商后按是否有失败决定退出码
+pub async fn once(config: Config, format: OutputFormat, fail_fast: bool) -> Result<bool, BoxError> {
+    let supervisor = Supervisor::new(config)?.fail_fast(fail_fast);
+    let result = supervisor.force_update().await;
+
+    match format {
+        OutputFormat::Text => {
+            let outcome = result?;
+            let ip = outcome.ip.map(|ip| ip.to_string()).unwrap_or_default();
+            println!("{} {}", if outcome.changed { "updated" } else { "unchanged" }, ip);
+            Ok(true)
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string(&RunStatus::from_result(&result))?);
+            Ok(result.is_ok())
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ProviderCheck {
+    provider: String,
+    success: bool,
+    detail: String,
+}
+
+// --test-providers：逐个检查已配置的提供商，不改变记录；返回是否全部通过
+// 有只读接口的提供商（如 route53）只读取，其余用当前地址做一次空操作更新
+pub async fn test_providers(config: &Config, format: OutputFormat) -> Result<bool, BoxError> {
+    let client = build_http_client(config)?;
+    let mut providers = providers::build_providers(config)?;
+    providers.extend(providers::build_local_providers(config)?);
+    let ip = normalize_ipv6(&get_ipv6_address(config, &client, &LocalCache::default()).await?)?.to_string();
+
+    let results = join_all(
+        providers
+            .iter()
+            .map(|provider| provider.check(&client, &ip).instrument(providers::log_span(provider.as_ref()))),
+    )
+    .await;
+    let checks: Vec<ProviderCheck> = providers
+        .iter()
+        .zip(results)
+        .map(|(provider, result)| ProviderCheck {
+            provider: provider.name().to_string(),
+            success: result.is_ok(),
+            detail: result.unwrap_or_else(|e| e.to_string()),
+        })
+        .collect();
+
+    match format {
+        OutputFormat::Text => print!("{}", format_checks(&checks)),
+        OutputFormat::Json => println!("{}", serde_json::to_string(&checks)?),
+    }
+    Ok(checks.iter().all(|check| check.success))
+}
+
+// 对齐的文本表格
+fn format_checks(checks: &[ProviderCheck]) -> String {
+    let width = checks.iter().map(|check| check.provider.len()).chain([8]).max().unwrap_or_default();
+    let mut table = format!("{:<width$}  RESULT  DETAIL\n", "PROVIDER");
+    for check in checks {
+        let result = if check.success { "PASS" } else { "FAIL" };
+        table.push_str(&format!("{:<width$}  {:<6}  {}\n", check.provider, result, check.detail));
+    }
+    table
+}
+
+// --show-config：输出合并环境变量、配置文件后的实际配置，密钥替换为 "***"
+pub fn show_config(config: &Config, format: OutputFormat) -> Result<(), BoxError> {
+    match format {
+        OutputFormat::Text => print!("{}", toml::to_string(config)?),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(config)?),
+    }
+    Ok(())
+}
+
+// --reset-state：删除上次运行留下的状态文件（status_file、metrics_file 及未完成写入的临时文件），
+// 下一次运行时从头开始，不再从 metrics_file 恢复上次变化时间；返回删除的文件
+pub fn reset_state(config: &Config) -> Result<Vec<String>, BoxError> {
+    let mut removed = Vec::new();
+    for path in [config.status_file.as_deref(), config.metrics_file.as_deref()].into_iter().flatten() {
+        for path in [path.to_string(), format!("{}.tmp", path)] {
+            match std::fs::remove_file(&path) {
+                Ok(()) => removed.push(path),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("Failed to remove '{}': {}", path, e).into()),
+            }
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_checks() {
+        let checks = [
+            ProviderCheck {
+                provider: "duckdns:home".to_string(),
+                success: true,
+                detail: "ok".to_string(),
+            },
+            ProviderCheck {
+                provider: "dyndns2:nas".to_string(),
+                success: false,
+                detail: "badauth".to_string(),
+            },
+        ];
+        assert_eq!(
+            format_checks(&checks),
+            "PROVIDER      RESULT  DETAIL\n\
+             duckdns:home  PASS    ok\n\
+             dyndns2:nas   FAIL    badauth\n"
+        );
+    }
+
+    #[test]
+    fn test_config_output_redacts_secrets() {
+        let config = Config {
+            duckdns_token: Some("super-secret".to_string()),
+            ..crate::tests::test_config()
+        };
+
+        let json: serde_json::Value = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["duckdns_token"], "***");
+        assert_eq!(json["cron"], config.cron.as_str());
+        assert!(json["dyndns2_password"].is_null());
+
+        let text = toml::to_string(&config).unwrap();
+        assert!(text.contains(r#"duckdns_token = "***""#), "{}", text);
+        assert!(!text.contains("super-secret"), "{}", text);
+    }
+
+    #[test]
+    fn test_reset_state() {
+        let dir = std::env::temp_dir();
+        let status = dir.join(format!("rs-refresh-ddns-reset-{}.toml", std::process::id()));
+        let metrics = dir.join(format!("rs-refresh-ddns-reset-{}.prom", std::process::id()));
+        std::fs::write(&status, "").unwrap();
+        std::fs::write(format!("{}.tmp", metrics.display()), "").unwrap();
+        let config = Config {
+            status_file: Some(status.to_string_lossy().to_string()),
+            metrics_file: Some(metrics.to_string_lossy().to_string()),
+            ..crate::tests::test_config()
+        };
+
+        let removed = reset_state(&config).unwrap();
+        assert_eq!(removed, vec![status.to_string_lossy().to_string(), format!("{}.tmp", metrics.display())]);
+        assert!(!status.exists());
+        assert!(reset_state(&config).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+// 发布前的连通性检查：从检测到的地址向 connectivity_check 指定的 host:port 建立TCP连接，
+// 避免在地址抖动期间发布一个实际不可用的地址；
+// 启动时的IPv6出口检查：不指定源地址建立一次IPv6连接，提前发现本机IPv6不可用的情况；
+// 以及发布后的外部可达性验证：请求 post_update_verify_url，由外部检测服务确认新地址可以从公网访问；
+// 重复地址检查：确认主机名解析回新地址，并请求 identity_probe_url 确认回答的是本机
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use reqwest::Client;
+use tokio::net::TcpSocket;
+use tokio::time::timeout;
+use tracing::debug;
+
+use crate::dns;
+use crate::providers::render_template;
+use crate::{read_body_limited, BoxError};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 没有配置 connectivity_check 时启动检查使用的目标
+pub const DEFAULT_STARTUP_CHECK_TARGET: &str = "one.one.one.one:443";
+
+// 外部检测服务响应的大小上限，只用于错误信息
+const MAX_VERIFY_RESPONSE_BYTES: usize = 4096;
+
+// 地址属于本机时以它为源地址连接；通过外部服务或路由器得到的地址通常不在本机上，此时只检查IPv6出口是否可用
+pub async fn check(target: &str, source: Ipv6Addr) -> Result<(), BoxError> {
+    let mut last_error = None;
+    for addr in resolve_ipv6(target).await? {
+        match connect(addr, Some(source)).await {
+            Ok(()) => {
+                debug!("Connectivity check to {} from {} succeeded", addr, source);
+                return Ok(());
+            }
+            Err(e) => last_error = Some(format!("{}: {}", addr, e)),
+        }
+    }
+    Err(format!(
+        "Connectivity check to '{}' failed, not publishing {}: {}",
+        target,
+        source,
+        last_error.unwrap_or_default()
+    )
+    .into())
+}
+
+// 由系统选择源地址，依次尝试目标的各个IPv6地址，任一连接成功即可
+pub async fn check_egress(target: &str) -> Result<(), BoxError> {
+    let mut last_error = None;
+    for addr in resolve_ipv6(target).await? {
+        match connect(addr, None).await {
+            Ok(()) => {
+                debug!("IPv6 connectivity check to {} succeeded", addr);
+                return Ok(());
+            }
+            Err(e) => last_error = Some(format!("{}: {}", addr, e)),
+        }
+    }
+    Err(format!("Could not connect to '{}' over IPv6: {}", target, last_error.unwrap_or_default()).into())
+}
+
+// 把 url_template 中的 {ip}、{ipv6} 替换为发布的地址后发送 GET 请求，2xx 表示外部可以访问该地址
+pub async fn verify_external(client: &Client, url_template: &str, ip: Ipv6Addr) -> Result<(), BoxError> {
+    let url = verify_url(url_template, ip)?;
+    debug!("Verifying external reachability of {} via {}", ip, url);
+
+    let response = client.get(&url).send().await?;
+    let status = response.status();
+    let body = read_body_limited(response, MAX_VERIFY_RESPONSE_BYTES).await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(format!("{} is not reachable from outside (checker responded with HTTP {}: {})", ip, status, body.trim()).into());
+    }
+    debug!("External reachability check of {} succeeded: {}", ip, body.trim());
+    Ok(())
+}
+
+// 主机名应解析到刚发布的地址，DNS 缓存或传播延迟也可能导致不一致
+pub async fn verify_resolves_to(hostname: &str, resolver: Option<&str>, ip: Ipv6Addr) -> Result<(), BoxError> {
+    let addrs = dns::resolve_aaaa(hostname, resolver).await?;
+    if !addrs.contains(&ip) {
+        return Err(format!(
+            "{} resolves to {:?} instead of the published {} (DNS may not have caught up yet)",
+            hostname, addrs, ip
+        )
+        .into());
+    }
+    debug!("{} resolves back to {}", hostname, ip);
+    Ok(())
+}
+
+// 请求 url_template（{ip}、{ipv6} 替换为发布的地址），响应中必须包含本机的标识 expect；
+// 包含其他内容时说明回答的是另一台主机，可能两台主机得到了同一个地址
+pub async fn verify_identity(client: &Client, url_template: &str, expect: &str, ip: Ipv6Addr) -> Result<(), BoxError> {
+    let url = verify_url(url_template, ip)?;
+    debug!("Probing {} via {} to confirm it is this host", ip, url);
+
+    let response = client.get(&url).send().await?;
+    let status = response.status();
+    let body = read_body_limited(response, MAX_VERIFY_RESPONSE_BYTES).await;
+    if !status.is_success() {
+        let body = body.unwrap_or_default();
+        return Err(format!("Identity probe of {} responded with HTTP {}: {}", ip, status, body.trim()).into());
+    }
+    // 读不出响应时报告原因，不能当作空响应，否则会被误报为另一台主机
+    let body = body.map_err(|e| format!("Failed to read the identity probe response for {}: {}", ip, e))?;
+    if !body.contains(expect) {
+        return Err(format!(
+            "{} answered the identity probe with '{}' instead of '{}', another host may be using the same address",
+            ip,
+            body.trim(),
+            expect
+        )
+        .into());
+    }
+    debug!("Identity probe of {} reached this host", ip);
+    Ok(())
+}
+
+pub fn verify_url(url_template: &str, ip: Ipv6Addr) -> Result<String, BoxError> {
+    let ip = ip.to_string();
+    render_template(url_template, &[("ip", &ip), ("ipv6", &ip)])
+}
+
+async fn resolve_ipv6(target: &str) -> Result<Vec<SocketAddr>, BoxError> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host(target)
+        .await
+        .map_err(|e| format!("Failed to resolve connectivity check target '{}': {}", target, e))?
+        .filter(SocketAddr::is_ipv6)
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("Connectivity check target '{}' has no IPv6 address", target).into());
+    }
+    Ok(addrs)
+}
+
+async fn connect(addr: SocketAddr, source: Option<Ipv6Addr>) -> Result<(), BoxError> {
+    let socket = TcpSocket::new_v6()?;
+    if let Some(source) = source
+        && let Err(e) = socket.bind(SocketAddr::new(IpAddr::V6(source), 0))
+    {
+        debug!("{} is not a local address ({}), checking without binding", source, e);
+    }
+
+    timeout(CONNECT_TIMEOUT, socket.connect(addr))
+        .await
+        .map_err(|_| format!("timed out after {}s", CONNECT_TIMEOUT.as_secs()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_check_connects_over_ipv6() {
+        // 没有IPv6回环地址的环境下跳过
+        let Ok(listener) = tokio::net::TcpListener::bind("[::1]:0").await else {
+            return;
+        };
+        let target = listener.local_addr().unwrap().to_string();
+        check(&target, Ipv6Addr::LOCALHOST).await.unwrap();
+
+        drop(listener);
+        assert!(check(&target, Ipv6Addr::LOCALHOST).await.is_err());
+        assert!(check("127.0.0.1:80", Ipv6Addr::LOCALHOST).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_egress() {
+        let Ok(listener) = tokio::net::TcpListener::bind("[::1]:0").await else {
+            return;
+        };
+        let target = listener.local_addr().unwrap().to_string();
+        check_egress(&target).await.unwrap();
+
+        drop(listener);
+        let err = check_egress(&target).await.unwrap_err();
+        assert!(err.to_string().starts_with(&format!("Could not connect to '{}' over IPv6", target)), "{}", err);
+        assert!(check_egress("127.0.0.1:80").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_external() {
+        use crate::tests::{http_response, serve_once};
+        let ip: Ipv6Addr = "2001:db8::1".parse().unwrap();
+
+        let (url, request) = serve_once(http_response("200 OK", "open")).await;
+        verify_external(&Client::new(), &format!("{}/check?host={{ip}}&port=443", url), ip)
+            .await
+            .unwrap();
+        assert!(request.await.unwrap().starts_with("GET /check?host=2001:db8::1&port=443 "));
+
+        let (url, _) = serve_once(http_response("503 Service Unavailable", "closed")).await;
+        let err = verify_external(&Client::new(), &format!("{}/{{ipv6}}", url), ip).await.unwrap_err();
+        assert!(err.to_string().contains("HTTP 503 Service Unavailable: closed"), "{}", err);
+
+        assert!(verify_url("https://example.com/{host}", ip).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_identity() {
+        use crate::tests::{http_response, serve_once};
+        let ip: Ipv6Addr = "2001:db8::1".parse().unwrap();
+
+        let (url, request) = serve_once(http_response("200 OK", "nas-7f3a\n")).await;
+        verify_identity(&Client::new(), &format!("{}/fetch?host={{ip}}", url), "nas-7f3a", ip)
+            .await
+            .unwrap();
+        assert!(request.await.unwrap().starts_with("GET /fetch?host=2001:db8::1 "));
+
+        // 另一台主机回答时报告可能的重复地址
+        let (url, _) = serve_once(http_response("200 OK", "printer-01")).await;
+        let err = verify_identity(&Client::new(), &format!("{}/{{ip}}", url), "nas-7f3a", ip).await.unwrap_err();
+        assert!(err.to_string().contains("another host may be using the same address"), "{}", err);
+
+        // 响应过大时报告读取失败，而不是误报重复地址
+        let (url, _) = serve_once(http_response("200 OK", &"x".repeat(MAX_VERIFY_RESPONSE_BYTES + 1))).await;
+        let err = verify_identity(&Client::new(), &format!("{}/{{ip}}", url), "nas-7f3a", ip).await.unwrap_err();
+        assert!(err.to_string().contains("Failed to read the identity probe response"), "{}", err);
+        assert!(!err.to_string().contains("another host"), "{}", err);
+    }
+}
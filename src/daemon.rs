@@ -0,0 +1,52 @@
+// --daemonize：在没有服务管理器的环境中脱离终端、转入后台运行（仅 Unix）
+// 由 systemd、OpenRC 等管理时不要使用，它们需要进程留在前台
+use crate::BoxError;
+
+// 必须在创建 tokio 运行时之前调用：fork 只会复制当前线程
+// 不切换工作目录，config.toml 和配置中的相对路径保持有效
+#[cfg(unix)]
+pub fn daemonize(pid_file: Option<&str>) -> Result<(), BoxError> {
+    // 第一次 fork 后父进程退出，子进程不是进程组组长，才能创建新会话
+    fork_and_exit_parent()?;
+    if unsafe { libc::setsid() } < 0 {
+        return Err(format!("setsid failed: {}", std::io::Error::last_os_error()).into());
+    }
+    // 第二次 fork，使进程不再是会话首进程，之后打开终端也不会成为控制终端
+    fork_and_exit_parent()?;
+
+    // 在关闭标准错误之前写入 PID 文件，失败时还能看到错误
+    if let Some(path) = pid_file {
+        std::fs::write(path, format!("{}\n", std::process::id()))
+            .map_err(|e| format!("Failed to write pid file '{}': {}", path, e))?;
+    }
+
+    redirect_stdio_to_null()
+}
+
+#[cfg(not(unix))]
+pub fn daemonize(_pid_file: Option<&str>) -> Result<(), BoxError> {
+    Err("--daemonize is only supported on Unix".into())
+}
+
+#[cfg(unix)]
+fn fork_and_exit_parent() -> Result<(), BoxError> {
+    match unsafe { libc::fork() } {
+        -1 => Err(format!("fork failed: {}", std::io::Error::last_os_error()).into()),
+        0 => Ok(()),
+        _ => std::process::exit(0),
+    }
+}
+
+// 标准输入输出指向 /dev/null，日志需要写到文件中才能保留
+#[cfg(unix)]
+fn redirect_stdio_to_null() -> Result<(), BoxError> {
+    use std::os::fd::AsRawFd;
+
+    let null = std::fs::OpenOptions::new().read(true).write(true).open("/dev/null")?;
+    for fd in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        if unsafe { libc::dup2(null.as_raw_fd(), fd) } < 0 {
+            return Err(format!("dup2 failed: {}", std::io::Error::last_os_error()).into());
+        }
+    }
+    Ok(())
+}
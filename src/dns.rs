@@ -0,0 +1,195 @@
+// 通过DNS查询域名当前的AAAA记录
+// 指定 dns_resolver 时直接向该服务器发送UDP查询（可以指定权威服务器以绕开缓存），
+// 否则使用系统解析器，结果可能受本地缓存和TTL影响
+use std::net::{Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+use tracing::debug;
+
+use crate::BoxError;
+
+const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+
+// 查询 hostname 的所有AAAA记录
+pub async fn resolve_aaaa(hostname: &str, resolver: Option<&str>) -> Result<Vec<Ipv6Addr>, BoxError> {
+    match resolver {
+        Some(resolver) => query_resolver(hostname, resolver).await,
+        None => {
+            let addrs = tokio::net::lookup_host((hostname, 0)).await?;
+            Ok(addrs
+                .filter_map(|addr| match addr {
+                    SocketAddr::V6(addr) => Some(*addr.ip()),
+                    SocketAddr::V4(_) => None,
+                })
+                .collect())
+        }
+    }
+}
+
+// resolver 可以写成 "1.1.1.1"、"1.1.1.1:53" 或 "[2606:4700:4700::1111]:53"
+fn parse_resolver(resolver: &str) -> Result<SocketAddr, BoxError> {
+    if let Ok(addr) = resolver.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    resolver
+        .parse::<std::net::IpAddr>()
+        .map(|ip| SocketAddr::new(ip, 53))
+        .map_err(|_| format!("Invalid DNS resolver '{}', expected an IP address with optional port", resolver).into())
+}
+
+async fn query_resolver(hostname: &str, resolver: &str) -> Result<Vec<Ipv6Addr>, BoxError> {
+    let server = parse_resolver(resolver)?;
+    let bind_addr = if server.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(server).await?;
+
+    let id = rand_id();
+    socket.send(&build_query(id, hostname)?).await?;
+    debug!("Sent AAAA query for {} to {}", hostname, server);
+
+    let mut buf = [0u8; 1500];
+    let len = timeout(DNS_TIMEOUT, socket.recv(&mut buf))
+        .await
+        .map_err(|_| format!("DNS query to {} timed out", server))??;
+    parse_response(&buf[..len], id)
+}
+
+// 用当前时间生成查询ID，只用于匹配响应
+fn rand_id() -> u16 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    (nanos ^ (nanos >> 16)) as u16
+}
+
+fn build_query(id: u16, hostname: &str) -> Result<Vec<u8>, BoxError> {
+    let mut packet = Vec::with_capacity(64);
+    packet.extend_from_slice(&id.to_be_bytes());
+    // 标准查询，期望递归
+    packet.extend_from_slice(&[0x01, 0x00]);
+    // QDCOUNT=1，其余计数为0
+    packet.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    for label in hostname.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("Invalid hostname '{}'", hostname).into());
+        }
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&TYPE_AAAA.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(packet)
+}
+
+fn read_u16(packet: &[u8], pos: usize) -> Result<u16, BoxError> {
+    packet
+        .get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| "Truncated DNS response".into())
+}
+
+// 跳过一个（可能被压缩的）域名，返回其后的位置
+fn skip_name(packet: &[u8], mut pos: usize) -> Result<usize, BoxError> {
+    loop {
+        let len = *packet.get(pos).ok_or("Truncated DNS response")?;
+        match len {
+            0 => return Ok(pos + 1),
+            // 压缩指针占两个字节，且总是名字的结尾
+            l if l & 0xC0 == 0xC0 => return Ok(pos + 2),
+            l => pos += 1 + l as usize,
+        }
+    }
+}
+
+fn parse_response(packet: &[u8], id: u16) -> Result<Vec<Ipv6Addr>, BoxError> {
+    if read_u16(packet, 0)? != id {
+        return Err("DNS response ID does not match the query".into());
+    }
+    let flags = read_u16(packet, 2)?;
+    match flags & 0x000F {
+        0 => {}
+        // NXDOMAIN：域名不存在，视为没有记录
+        3 => return Ok(Vec::new()),
+        rcode => return Err(format!("DNS query failed with rcode {}", rcode).into()),
+    }
+
+    let questions = read_u16(packet, 4)?;
+    let answers = read_u16(packet, 6)?;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(packet, pos)? + 4;
+    }
+
+    let mut addrs = Vec::new();
+    for _ in 0..answers {
+        pos = skip_name(packet, pos)?;
+        let rtype = read_u16(packet, pos)?;
+        let rdlength = read_u16(packet, pos + 8)? as usize;
+        let rdata = packet.get(pos + 10..pos + 10 + rdlength).ok_or("Truncated DNS response")?;
+        // 跳过CNAME等其他类型的记录
+        if rtype == TYPE_AAAA && rdlength == 16 {
+            let octets: [u8; 16] = rdata.try_into()?;
+            addrs.push(Ipv6Addr::from(octets));
+        }
+        pos += 10 + rdlength;
+    }
+    Ok(addrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resolver() {
+        assert_eq!(parse_resolver("1.1.1.1").unwrap(), "1.1.1.1:53".parse().unwrap());
+        assert_eq!(parse_resolver("1.1.1.1:5353").unwrap(), "1.1.1.1:5353".parse().unwrap());
+        assert_eq!(
+            parse_resolver("[2606:4700:4700::1111]:53").unwrap(),
+            "[2606:4700:4700::1111]:53".parse().unwrap()
+        );
+        assert!(parse_resolver("dns.example.com").is_err());
+    }
+
+    #[test]
+    fn test_build_query() {
+        let query = build_query(0x1234, "home.duckdns.org.").unwrap();
+        assert_eq!(&query[..4], &[0x12, 0x34, 0x01, 0x00]);
+        assert_eq!(&query[12..], b"\x04home\x07duckdns\x03org\x00\x00\x1c\x00\x01");
+        assert!(build_query(1, "bad..name").is_err());
+    }
+
+    #[test]
+    fn test_parse_response_with_cname() {
+        let mut packet = build_query(0x1234, "home.example.com").unwrap();
+        // 设置响应标志和 ANCOUNT=2
+        packet[2] = 0x81;
+        packet[3] = 0x80;
+        packet[7] = 2;
+        // CNAME 记录，名字使用压缩指针指向问题中的域名
+        packet.extend_from_slice(&[0xC0, 0x0C, 0x00, 0x05, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x02, 0xC0, 0x0C]);
+        // AAAA 记录
+        packet.extend_from_slice(&[0xC0, 0x0C, 0x00, 0x1C, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x10]);
+        packet.extend_from_slice(&"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets());
+
+        let addrs = parse_response(&packet, 0x1234).unwrap();
+        assert_eq!(addrs, vec!["2001:db8::1".parse::<Ipv6Addr>().unwrap()]);
+        assert!(parse_response(&packet, 0x4321).is_err());
+    }
+
+    #[test]
+    fn test_parse_nxdomain() {
+        let mut packet = build_query(7, "missing.example.com").unwrap();
+        packet[2] = 0x81;
+        packet[3] = 0x83;
+        assert!(parse_response(&packet, 7).unwrap().is_empty());
+    }
+}
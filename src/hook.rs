@@ -0,0 +1,62 @@
+// 地址变化后执行的用户命令（更新防火墙规则、重启服务等）
+// 新地址通过环境变量 DDNS_IP 和最后一个参数传入，命令失败不影响更新结果
+use std::time::Duration;
+
+use tokio::process::Command;
+use tokio::time::timeout;
+use tracing::info;
+
+use crate::BoxError;
+
+const HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+
+// 把环境变量中的命令字符串包装为 sh -c，额外的 "sh" 作为 $0，使新地址成为 $1
+pub fn shell_command(command: String) -> Vec<String> {
+    vec!["sh".to_string(), "-c".to_string(), command, "sh".to_string()]
+}
+
+pub async fn run_post_update(command: &[String], ip: &str) -> Result<(), BoxError> {
+    let (program, args) = command.split_first().ok_or("post_update_command is empty")?;
+
+    let run = Command::new(program)
+        .args(args)
+        .arg(ip)
+        .env("DDNS_IP", ip)
+        .kill_on_drop(true)
+        .output();
+    let output = timeout(HOOK_TIMEOUT, run)
+        .await
+        .map_err(|_| format!("post_update_command timed out after {}s", HOOK_TIMEOUT.as_secs()))??;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(format!(
+            "post_update_command failed ({}) - stdout: {}, stderr: {}",
+            output.status,
+            stdout.trim(),
+            stderr.trim()
+        )
+        .into());
+    }
+
+    info!(
+        "post_update_command finished - stdout: {}, stderr: {}",
+        stdout.trim(),
+        stderr.trim()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_passes_ip_as_argument_and_env() {
+        let command = shell_command(r#"test "$1" = 2001:db8::1 && test "$DDNS_IP" = 2001:db8::1"#.to_string());
+        run_post_update(&command, "2001:db8::1").await.unwrap();
+        assert!(run_post_update(&command, "2001:db8::2").await.is_err());
+    }
+}
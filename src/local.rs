@@ -0,0 +1,892 @@
+// 直接获取本地IPv6地址
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+use crate::BoxError;
+
+// 直接获取本地IPv6地址 - 改进版本
+// 收集所有候选地址后由 select_local_ipv6 挑选；默认只考虑全局地址，
+// allow_non_global 为 true 时也可以选中 ULA、链路本地等地址（例如只在 VPN 内访问），仍优先全局地址
+pub async fn get_local_ipv6_address(
+    interface_name: Option<&str>,
+    filter: &InterfaceFilter<'_>,
+    interface_priority: &[String],
+    allow_non_global: bool,
+) -> Result<String, BoxError> {
+    let candidates = list_local_ipv6_addresses(interface_name, filter)?;
+
+    if let Some((name, ipv6)) = select_publishable_ipv6(candidates, interface_priority, allow_non_global)? {
+        let ip_str = ipv6.to_string();
+        if scope_preference(&ipv6) != GLOBAL_SCOPE {
+            warn!("Publishing non-global IPv6 address {} from interface '{}' because ALLOW_NON_GLOBAL_IPV6 is set", ip_str, name);
+        }
+        debug!("Got IPv6 address from interface '{}': {}", name, ip_str);
+        return Ok(ip_str);
+    }
+
+    // 统计检查过的接口和IPv4地址数量，帮助用户判断主机是否根本没有IPv6
+    let (interface_count, ipv4_count) = count_addresses(interface_name, filter)?;
+    Err(no_ipv6_error(interface_name, interface_count, ipv4_count).into())
+}
+
+// confirm 不为零时，读到地址后等待这段时间再读一次，两次结果相同才返回
+// 接口刚出现或正在消失时 get_if_addrs 可能读到过渡状态，确认可以避免发布很快就失效的地址
+async fn get_confirmed_ipv6_address(
+    interface_name: Option<&str>,
+    filter: &InterfaceFilter<'_>,
+    interface_priority: &[String],
+    confirm: Duration,
+    allow_non_global: bool,
+) -> Result<String, BoxError> {
+    let address = get_local_ipv6_address(interface_name, filter, interface_priority, allow_non_global).await?;
+    if confirm.is_zero() {
+        return Ok(address);
+    }
+
+    tokio::time::sleep(confirm).await;
+    let confirmed = get_local_ipv6_address(interface_name, filter, interface_priority, allow_non_global)
+        .await
+        .map_err(|e| format!("Local IPv6 address {} disappeared while confirming: {}", address, e))?;
+    if confirmed != address {
+        return Err(format!("Local IPv6 address is not stable ({} then {}), not publishing", address, confirmed).into());
+    }
+    debug!("Local IPv6 address {} confirmed after {:?}", address, confirm);
+    Ok(address)
+}
+
+// 本地检测的结果缓存，local_cache_secs 大于 0 时使用；每个 Supervisor（配置档案）各持有一个，
+// 并按检测参数分别缓存，接口、过滤条件、优先级等不同的检测不会拿到彼此的结果
+#[derive(Default)]
+pub struct LocalCache(Mutex<HashMap<CacheKey, CachedAddress>>);
+
+// 影响检测结果的参数
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct CacheKey {
+    interface_name: Option<String>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    interface_priority: Vec<String>,
+    confirm: Duration,
+    allow_non_global: bool,
+}
+
+struct CachedAddress {
+    // 检测时各接口的链路状态，无法获取时为 None，只按时间过期
+    link_state: Option<Vec<String>>,
+    expires: Instant,
+    address: String,
+}
+
+impl CachedAddress {
+    fn is_fresh(&self, link_state: &Option<Vec<String>>, now: Instant) -> bool {
+        self.link_state == *link_state && now < self.expires
+    }
+}
+
+impl LocalCache {
+    fn get(&self, key: &CacheKey, link_state: &Option<Vec<String>>, now: Instant) -> Option<String> {
+        let entries = self.0.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|cached| cached.is_fresh(link_state, now))
+            .map(|cached| cached.address.clone())
+    }
+
+    fn insert(&self, key: CacheKey, cached: CachedAddress) {
+        self.0.lock().unwrap().insert(key, cached);
+    }
+
+    // 地址已知发生变化时丢弃缓存，下一次检测重新枚举
+    pub fn invalidate(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+// 带缓存的本地检测：在 ttl 内、检测参数相同且各接口链路状态没有变化时直接返回上次的结果，ttl 为 0 时不缓存
+// 链路状态（Linux 上为 operstate 和 carrier_changes）比重新枚举所有地址便宜，适合任务很多的嵌入式设备
+pub async fn get_local_ipv6_address_cached(
+    cache: &LocalCache,
+    interface_name: Option<&str>,
+    filter: &InterfaceFilter<'_>,
+    interface_priority: &[String],
+    ttl: Duration,
+    confirm: Duration,
+    allow_non_global: bool,
+) -> Result<String, BoxError> {
+    if ttl.is_zero() {
+        return get_confirmed_ipv6_address(interface_name, filter, interface_priority, confirm, allow_non_global).await;
+    }
+
+    let key = CacheKey {
+        interface_name: interface_name.map(str::to_string),
+        include: filter.include.to_vec(),
+        exclude: filter.exclude.to_vec(),
+        interface_priority: interface_priority.to_vec(),
+        confirm,
+        allow_non_global,
+    };
+    let link_state = link_state();
+    if let Some(address) = cache.get(&key, &link_state, Instant::now()) {
+        debug!("Using cached local IPv6 address: {}", address);
+        return Ok(address);
+    }
+
+    let address = get_confirmed_ipv6_address(interface_name, filter, interface_priority, confirm, allow_non_global).await?;
+    cache.insert(
+        key,
+        CachedAddress {
+            link_state,
+            expires: Instant::now() + ttl,
+            address: address.clone(),
+        },
+    );
+    Ok(address)
+}
+
+// 每个接口一行："接口名 operstate carrier_changes"，任一接口上下线或新增、消失时都会变化
+#[cfg(target_os = "linux")]
+fn link_state() -> Option<Vec<String>> {
+    let mut state = Vec::new();
+    for entry in std::fs::read_dir("/sys/class/net").ok()? {
+        let entry = entry.ok()?;
+        let read = |file: &str| std::fs::read_to_string(entry.path().join(file)).unwrap_or_default();
+        state.push(format!(
+            "{} {} {}",
+            entry.file_name().to_string_lossy(),
+            read("operstate").trim(),
+            read("carrier_changes").trim()
+        ));
+    }
+    state.sort();
+    Some(state)
+}
+
+// 其他平台没有便宜的方式获取链路状态，缓存只按时间过期
+#[cfg(not(target_os = "linux"))]
+fn link_state() -> Option<Vec<String>> {
+    None
+}
+
+// 未设置 route_target 时用于选路的目标，只用于让内核选出源地址，不会发送数据
+pub const DEFAULT_ROUTE_TARGET: &str = "[2001:4860:4860::8888]:53";
+
+// route 方式：把UDP套接字"连接"到 target，读取内核按路由表选出的源地址，即系统对外连接实际使用的地址
+// UDP 的 connect 只设置默认目的地址，不会产生网络流量；指定接口时要求该地址属于该接口
+pub async fn get_route_ipv6_address(target: Option<&str>, interface_name: Option<&str>) -> Result<String, BoxError> {
+    let target = target.unwrap_or(DEFAULT_ROUTE_TARGET);
+    let addr = resolve_route_target(target).await?;
+
+    let socket = tokio::net::UdpSocket::bind("[::]:0").await?;
+    socket
+        .connect(addr)
+        .await
+        .map_err(|e| format!("No IPv6 route to '{}': {}", target, e))?;
+    let ipv6 = match socket.local_addr()?.ip() {
+        IpAddr::V6(ipv6) if !ipv6.is_unspecified() => ipv6,
+        other => return Err(format!("Kernel chose no usable source address towards '{}': {}", target, other).into()),
+    };
+
+    if let Some(name) = interface_name
+        && !list_local_ipv6_addresses(Some(name), &InterfaceFilter::default())?.iter().any(|(_, addr)| *addr == ipv6)
+    {
+        return Err(format!("Route to '{}' uses {}, which is not on interface '{}'", target, ipv6, name).into());
+    }
+
+    debug!("Got IPv6 address from route to '{}': {}", target, ipv6);
+    Ok(ipv6.to_string())
+}
+
+// 内核访问 remote 时选用的源地址及其所在接口，用于确认提供商请求实际从哪里发出
+// 与 route 方式相同，UDP 的 connect 不会产生网络流量；结果是当前的路由选择，连接池中较早建立的连接可能不同
+pub fn egress_for(remote: SocketAddr) -> Result<(IpAddr, Option<String>), BoxError> {
+    let bind: SocketAddr = if remote.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" }.parse()?;
+    let socket = std::net::UdpSocket::bind(bind)?;
+    socket.connect(remote)?;
+    let source = socket.local_addr()?.ip();
+    let interface = if_addrs::get_if_addrs()?
+        .into_iter()
+        .find(|iface| iface.ip() == source)
+        .map(|iface| iface.name);
+    Ok((source, interface))
+}
+
+// 目标可以是不带端口的IPv6地址，也可以是 host:port
+async fn resolve_route_target(target: &str) -> Result<SocketAddr, BoxError> {
+    if let Ok(ipv6) = target.trim_matches(['[', ']']).parse::<Ipv6Addr>() {
+        return Ok(SocketAddr::new(IpAddr::V6(ipv6), 53));
+    }
+    tokio::net::lookup_host(target)
+        .await
+        .map_err(|e| format!("Failed to resolve route target '{}': {}", target, e))?
+        .find(SocketAddr::is_ipv6)
+        .ok_or_else(|| format!("Route target '{}' has no IPv6 address", target).into())
+}
+
+// 启动时检查接口名，写错时列出现有接口，而不是等到第一次更新才失败
+// if_addrs 只能看到有地址的接口
+// 配置了多个候选接口时只要有一个存在即可
+pub fn check_interface_exists(wanted: &[String]) -> Result<(), BoxError> {
+    let mut names: Vec<String> = if_addrs::get_if_addrs()?.into_iter().map(|iface| iface.name).collect();
+    if names.iter().any(|existing| wanted.contains(existing)) {
+        return Ok(());
+    }
+    names.sort();
+    names.dedup();
+    let not_found = match wanted {
+        [name] => format!("Interface '{}' not found", name),
+        _ => format!("None of the interfaces '{}' found", wanted.join("', '")),
+    };
+    Err(format!("{}; available interfaces: {}", not_found, names.join(", ")).into())
+}
+
+// 自动查找（未指定接口）时参与的接口：include 非空时只考虑匹配的接口，再去掉匹配 exclude 的接口
+// 用于跳过 docker0、veth*、tailscale0 等不应发布的虚拟接口
+#[derive(Default)]
+pub struct InterfaceFilter<'a> {
+    pub include: &'a [String],
+    pub exclude: &'a [String],
+}
+
+impl InterfaceFilter<'_> {
+    fn allows(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|pattern| interface_matches(name, pattern)))
+            && !self.exclude.iter().any(|pattern| interface_matches(name, pattern))
+    }
+}
+
+// 列出所有候选的本地IPv6地址（接口名, 地址），不做任何选择
+// 指定接口时只列出该接口的地址，否则列出 filter 允许的、除回环接口以外的所有接口
+pub fn list_local_ipv6_addresses(
+    interface_name: Option<&str>,
+    filter: &InterfaceFilter<'_>,
+) -> Result<Vec<(String, Ipv6Addr)>, BoxError> {
+    let addresses = matching_interfaces(interface_name, filter)?
+        .into_iter()
+        .map(|iface| {
+            let ip = iface.ip();
+            (iface.name, ip)
+        });
+    Ok(ipv6_candidates(addresses))
+}
+
+// 从（接口名, 地址）中取出IPv6地址，与读取系统接口分开，便于用构造的地址测试
+fn ipv6_candidates(addresses: impl IntoIterator<Item = (String, IpAddr)>) -> Vec<(String, Ipv6Addr)> {
+    addresses
+        .into_iter()
+        .filter_map(|(name, ip)| match ip {
+            IpAddr::V6(ipv6) => {
+                debug!("Found IPv6 candidate on interface '{}': {}", name, ipv6);
+                Some((name, ipv6))
+            }
+            IpAddr::V4(_) => None,
+        })
+        .collect()
+}
+
+// 从候选地址中挑选要发布的地址：去掉重复地址后按 RFC 6724 源地址选择规则的近似实现挑选，
+// 使发布的地址尽量与系统对外连接实际使用的地址一致；
+// interface_priority 中靠前的接口优先，用于同时存在网桥、容器等虚拟接口时选中真正的WAN接口
+pub fn select_local_ipv6(candidates: Vec<(String, Ipv6Addr)>, interface_priority: &[String]) -> Option<(String, Ipv6Addr)> {
+    select_from_candidates(candidates, &deprecated_ipv6_addresses(), interface_priority)
+}
+
+// select_local_ipv6 的选择逻辑，已废弃的地址由调用方给出，不读取系统状态
+fn select_from_candidates(
+    candidates: Vec<(String, Ipv6Addr)>,
+    deprecated: &HashSet<Ipv6Addr>,
+    interface_priority: &[String],
+) -> Option<(String, Ipv6Addr)> {
+    let candidates = dedup_candidates(candidates, interface_priority);
+    debug!("Local IPv6 candidates: {:?}", candidates);
+    select_preferred_ipv6(&candidates, deprecated, interface_priority).cloned()
+}
+
+// 按 allow_non_global 过滤后挑选；只有非全局地址且不允许发布时报错并列出这些地址
+fn select_publishable_ipv6(
+    candidates: Vec<(String, Ipv6Addr)>,
+    interface_priority: &[String],
+    allow_non_global: bool,
+) -> Result<Option<(String, Ipv6Addr)>, BoxError> {
+    if allow_non_global {
+        return Ok(select_local_ipv6(candidates, interface_priority));
+    }
+
+    let (global, non_global): (Vec<_>, Vec<_>) =
+        candidates.into_iter().partition(|(_, ip)| scope_preference(ip) == GLOBAL_SCOPE);
+    if global.is_empty() && !non_global.is_empty() {
+        let found: Vec<String> = non_global.iter().map(|(name, ip)| format!("{} on {}", ip, name)).collect();
+        return Err(format!(
+            "Only non-global IPv6 addresses found ({}); set ALLOW_NON_GLOBAL_IPV6=true to publish them",
+            found.join(", ")
+        )
+        .into());
+    }
+    Ok(select_local_ipv6(global, interface_priority))
+}
+
+fn matching_interfaces(interface_name: Option<&str>, filter: &InterfaceFilter<'_>) -> Result<Vec<if_addrs::Interface>, BoxError> {
+    let interfaces = if_addrs::get_if_addrs()?
+        .into_iter()
+        // 如果指定了接口名称，则只检查该接口；否则跳过回环接口和 filter 排除的接口
+        .filter(|iface| match interface_name {
+            Some(name) => iface.name == name,
+            None => !iface.is_loopback() && filter.allows(&iface.name),
+        })
+        .collect();
+    Ok(interfaces)
+}
+
+// 匹配的接口数量和其中的IPv4地址数量
+fn count_addresses(interface_name: Option<&str>, filter: &InterfaceFilter<'_>) -> Result<(usize, usize), BoxError> {
+    let interfaces = matching_interfaces(interface_name, filter)?;
+    let names: HashSet<&str> = interfaces.iter().map(|iface| iface.name.as_str()).collect();
+    let ipv4_count = interfaces.iter().filter(|iface| iface.ip().is_ipv4()).count();
+    Ok((names.len(), ipv4_count))
+}
+
+fn no_ipv6_error(interface_name: Option<&str>, interface_count: usize, ipv4_count: usize) -> String {
+    let not_found = match interface_name {
+        Some(name) => format!("No IPv6 address found for interface '{}'", name),
+        None => "No public IPv6 address found on any interface".to_string(),
+    };
+
+    if interface_count == 0 {
+        return match interface_name {
+            Some(_) => format!("{} (the interface does not exist or has no addresses)", not_found),
+            None => format!("{} (no non-loopback interfaces with addresses)", not_found),
+        };
+    }
+
+    let mut message = format!(
+        "{} (checked {} interface(s) with {} IPv4 address(es) and no IPv6 addresses",
+        not_found, interface_count, ipv4_count
+    );
+    if ipv4_count > 0 {
+        message.push_str("; this host appears to have IPv4 only");
+    }
+    message.push(')');
+    message
+}
+
+// 全局地址的作用域优先级
+const GLOBAL_SCOPE: u8 = 4;
+
+// 地址作用域的优先级，数值越大越优先（RFC 6724 规则2：目标为全局地址时优先选择更大的作用域）
+fn scope_preference(ip: &Ipv6Addr) -> u8 {
+    let segments = ip.segments();
+    if ip.is_loopback() || ip.is_unspecified() {
+        0
+    } else if segments[0] & 0xffc0 == 0xfe80 {
+        // 链路本地 fe80::/10
+        1
+    } else if segments[0] & 0xffc0 == 0xfec0 {
+        // 已废弃的站点本地 fec0::/10
+        2
+    } else if segments[0] & 0xfe00 == 0xfc00 {
+        // 唯一本地地址 ULA fc00::/7
+        3
+    } else {
+        GLOBAL_SCOPE
+    }
+}
+
+// 接口名是否匹配模式，模式以 * 结尾时按前缀匹配
+fn interface_matches(name: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+// 接口在优先级列表中的排名，越靠前数值越大，不在列表中为 0
+fn interface_rank(name: &str, priority: &[String]) -> usize {
+    priority
+        .iter()
+        .position(|pattern| interface_matches(name, pattern))
+        .map(|position| priority.len() - position)
+        .unwrap_or(0)
+}
+
+// 同一地址出现在多个接口上时只保留一个，优先保留排名更高的接口，排名相同时保留先出现的
+fn dedup_candidates(candidates: Vec<(String, Ipv6Addr)>, priority: &[String]) -> Vec<(String, Ipv6Addr)> {
+    let mut unique: Vec<(String, Ipv6Addr)> = Vec::new();
+    for (name, ip) in candidates {
+        match unique.iter_mut().find(|(_, existing)| *existing == ip) {
+            Some(existing) if interface_rank(&name, priority) > interface_rank(&existing.0, priority) => {
+                existing.0 = name;
+            }
+            Some(_) => {}
+            None => unique.push((name, ip)),
+        }
+    }
+    unique
+}
+
+// 按以下顺序挑选最合适的地址，优先级相同时保持原有顺序：
+// 1. 作用域更大（全局 > ULA > 站点本地 > 链路本地 > 回环）
+// 2. 未被废弃（RFC 6724 规则3）
+// 3. 接口在 interface_priority 中更靠前
+// 注意不采用规则7（优先临时地址），DDNS 需要发布的是稳定地址
+fn select_preferred_ipv6<'a>(
+    candidates: &'a [(String, Ipv6Addr)],
+    deprecated: &HashSet<Ipv6Addr>,
+    priority: &[String],
+) -> Option<&'a (String, Ipv6Addr)> {
+    candidates
+        .iter()
+        .enumerate()
+        .max_by_key(|(index, (name, ip))| {
+            (
+                scope_preference(ip),
+                !deprecated.contains(ip),
+                interface_rank(name, priority),
+                std::cmp::Reverse(*index),
+            )
+        })
+        .map(|(_, candidate)| candidate)
+}
+
+// 内核地址标志位（linux/if_addr.h）
+const IFA_F_TEMPORARY: u32 = 0x01;
+const IFA_F_DEPRECATED: u32 = 0x20;
+const IFA_F_MNGTMPADDR: u32 = 0x100;
+
+// 已被废弃（preferred lifetime 已过期）的地址，仅 Linux 可以通过 /proc/net/if_inet6 获取
+fn deprecated_ipv6_addresses() -> HashSet<Ipv6Addr> {
+    ipv6_address_flags()
+        .into_iter()
+        .filter(|(_, flags)| flags & IFA_F_DEPRECATED != 0)
+        .map(|(ip, _)| ip)
+        .collect()
+}
+
+// publish_stable_only：检测到的地址是本机的临时地址（隐私扩展，每隔几小时轮换）时，
+// 改用同一 /64 前缀下未废弃的稳定地址，优先选择生成临时地址所用的地址；不是本机临时地址时原样返回
+// 仅 Linux 可以区分临时地址，其他平台总是原样返回
+pub fn stable_ipv6_for(ip: Ipv6Addr) -> Result<Ipv6Addr, BoxError> {
+    resolve_stable_ipv6(ip, &ipv6_address_flags())
+}
+
+fn resolve_stable_ipv6(ip: Ipv6Addr, flags: &[(Ipv6Addr, u32)]) -> Result<Ipv6Addr, BoxError> {
+    if !flags.iter().any(|(addr, f)| *addr == ip && f & IFA_F_TEMPORARY != 0) {
+        return Ok(ip);
+    }
+
+    let prefix = |addr: &Ipv6Addr| addr.segments()[..4].to_vec();
+    flags
+        .iter()
+        .filter(|(addr, f)| f & (IFA_F_TEMPORARY | IFA_F_DEPRECATED) == 0 && prefix(addr) == prefix(&ip))
+        .max_by_key(|(_, f)| f & IFA_F_MNGTMPADDR != 0)
+        .map(|(addr, _)| *addr)
+        .ok_or_else(|| format!("{} is a temporary address and no stable address with the same /64 prefix was found", ip).into())
+}
+
+// 读取内核记录的地址标志位
+#[cfg(target_os = "linux")]
+fn ipv6_address_flags() -> Vec<(Ipv6Addr, u32)> {
+    std::fs::read_to_string("/proc/net/if_inet6")
+        .map(|contents| parse_if_inet6(&contents))
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn ipv6_address_flags() -> Vec<(Ipv6Addr, u32)> {
+    Vec::new()
+}
+
+// /proc/net/if_inet6 每行格式：地址(32位十六进制) 接口序号 前缀长度 作用域 标志位 接口名
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_if_inet6(contents: &str) -> Vec<(Ipv6Addr, u32)> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 6 || fields[0].len() != 32 {
+                return None;
+            }
+            let ip = Ipv6Addr::from(u128::from_str_radix(fields[0], 16).ok()?);
+            let flags = u32::from_str_radix(fields[4], 16).ok()?;
+            Some((ip, flags))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_key(interface_name: &str, interface_priority: &[&str]) -> CacheKey {
+        CacheKey {
+            interface_name: Some(interface_name.to_string()),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            interface_priority: interface_priority.iter().map(|name| name.to_string()).collect(),
+            confirm: Duration::ZERO,
+            allow_non_global: false,
+        }
+    }
+
+    #[test]
+    fn test_check_interface_exists() {
+        let existing = if_addrs::get_if_addrs().unwrap().remove(0).name;
+        assert!(check_interface_exists(std::slice::from_ref(&existing)).is_ok());
+        assert!(check_interface_exists(&["nonexistent_interface".to_string(), existing.clone()]).is_ok());
+
+        let err = check_interface_exists(&["nonexistent_interface".to_string()]).unwrap_err().to_string();
+        assert!(err.starts_with("Interface 'nonexistent_interface' not found"), "{}", err);
+        assert!(err.contains(&existing), "{}", err);
+        let err = check_interface_exists(&["wan0".to_string(), "wan1".to_string()]).unwrap_err().to_string();
+        assert!(err.starts_with("None of the interfaces 'wan0', 'wan1' found"), "{}", err);
+    }
+
+    #[test]
+    fn test_cached_address_freshness() {
+        let now = Instant::now();
+        let state = Some(vec!["eth0 up 1".to_string()]);
+        let cache = LocalCache::default();
+        let cached = |address: &str| CachedAddress {
+            link_state: state.clone(),
+            expires: now + Duration::from_secs(60),
+            address: address.to_string(),
+        };
+        cache.insert(cache_key("eth0", &[]), cached("2001:db8::1"));
+
+        assert_eq!(cache.get(&cache_key("eth0", &[]), &state, now).as_deref(), Some("2001:db8::1"));
+        assert_eq!(cache.get(&cache_key("eth1", &[]), &state, now), None);
+        assert_eq!(cache.get(&cache_key("eth0", &[]), &Some(vec!["eth0 down 2".to_string()]), now), None);
+        assert_eq!(cache.get(&cache_key("eth0", &[]), &state, now + Duration::from_secs(61)), None);
+
+        // 同一接口、不同优先级的检测各自缓存，互不复用
+        assert_eq!(cache.get(&cache_key("eth0", &["wan*"]), &state, now), None);
+        cache.insert(cache_key("eth0", &["wan*"]), cached("2001:db8::2"));
+        assert_eq!(cache.0.lock().unwrap().len(), 2);
+        assert_eq!(cache.get(&cache_key("eth0", &[]), &state, now).as_deref(), Some("2001:db8::1"));
+        assert_eq!(cache.get(&cache_key("eth0", &["wan*"]), &state, now).as_deref(), Some("2001:db8::2"));
+
+        cache.invalidate();
+        assert_eq!(cache.get(&cache_key("eth0", &[]), &state, now), None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_route_target() {
+        let expected: SocketAddr = "[2001:db8::1]:53".parse().unwrap();
+        assert_eq!(resolve_route_target("2001:db8::1").await.unwrap(), expected);
+        assert_eq!(resolve_route_target("[2001:db8::1]").await.unwrap(), expected);
+        assert_eq!(resolve_route_target("[2001:db8::1]:53").await.unwrap(), expected);
+        assert!(resolve_route_target("127.0.0.1:53").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_route_to_loopback_uses_loopback() {
+        // 没有IPv6回环地址的环境下跳过
+        let Ok(ip) = get_route_ipv6_address(Some("::1"), None).await else {
+            return;
+        };
+        assert_eq!(ip, "::1");
+    }
+
+    #[test]
+    fn test_list_local_ipv6_addresses_filters_interfaces() {
+        let all = list_local_ipv6_addresses(None, &InterfaceFilter::default()).unwrap();
+        assert!(all.iter().all(|(_, ip)| !ip.is_loopback()));
+
+        for (name, _) in &all {
+            let only = list_local_ipv6_addresses(Some(name), &InterfaceFilter::default()).unwrap();
+            assert!(only.iter().all(|(other, _)| other == name));
+        }
+        assert!(list_local_ipv6_addresses(Some("nonexistent_interface"), &InterfaceFilter::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_interface_filter() {
+        let include = vec!["eth*".to_string(), "ppp0".to_string()];
+        let exclude = vec!["docker0".to_string(), "veth*".to_string(), "eth1".to_string()];
+
+        let all = InterfaceFilter::default();
+        assert!(all.allows("docker0"));
+
+        let filter = InterfaceFilter { include: &[], exclude: &exclude };
+        assert!(filter.allows("eth0"));
+        assert!(filter.allows("tailscale0"));
+        assert!(!filter.allows("docker0"));
+        assert!(!filter.allows("veth1a2b"));
+
+        // exclude 优先于 include
+        let filter = InterfaceFilter { include: &include, exclude: &exclude };
+        assert!(filter.allows("eth0"));
+        assert!(filter.allows("ppp0"));
+        assert!(!filter.allows("eth1"));
+        assert!(!filter.allows("wlan0"));
+
+        // 指定接口时不应用 filter
+        let exclude_all = vec!["*".to_string()];
+        let filter = InterfaceFilter { include: &[], exclude: &exclude_all };
+        assert!(list_local_ipv6_addresses(None, &filter).unwrap().is_empty());
+        for (name, _) in list_local_ipv6_addresses(None, &InterfaceFilter::default()).unwrap() {
+            assert!(!list_local_ipv6_addresses(Some(&name), &filter).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_interface_priority_and_dedup() {
+        let priority = vec!["eth0".to_string(), "wan*".to_string()];
+        let candidates = vec![
+            candidate("br0", "2001:db8::1"),
+            candidate("eth0", "2001:db8::1"),
+            candidate("docker0", "2001:db8::2"),
+            candidate("wan1", "2001:db8::3"),
+        ];
+
+        let unique = dedup_candidates(candidates.clone(), &priority);
+        assert_eq!(
+            unique,
+            vec![candidate("eth0", "2001:db8::1"), candidate("docker0", "2001:db8::2"), candidate("wan1", "2001:db8::3")]
+        );
+
+        let selected = select_preferred_ipv6(&unique, &HashSet::new(), &priority).unwrap();
+        assert_eq!(selected, &candidate("eth0", "2001:db8::1"));
+
+        let priority = vec!["wan*".to_string()];
+        let selected = select_preferred_ipv6(&candidates, &HashSet::new(), &priority).unwrap();
+        assert_eq!(selected, &candidate("wan1", "2001:db8::3"));
+
+        // 没有优先级列表时保持原有顺序
+        let selected = select_preferred_ipv6(&candidates, &HashSet::new(), &[]).unwrap();
+        assert_eq!(selected, &candidate("br0", "2001:db8::1"));
+    }
+
+    #[test]
+    fn test_no_ipv6_error_mentions_ipv4_only() {
+        let message = no_ipv6_error(None, 2, 3);
+        assert!(message.contains("checked 2 interface(s) with 3 IPv4 address(es)"), "{}", message);
+        assert!(message.contains("IPv4 only"), "{}", message);
+
+        let message = no_ipv6_error(Some("eth9"), 0, 0);
+        assert!(message.starts_with("No IPv6 address found for interface 'eth9'"), "{}", message);
+        assert!(message.contains("does not exist"), "{}", message);
+    }
+
+    fn candidate(name: &str, ip: &str) -> (String, Ipv6Addr) {
+        (name.to_string(), ip.parse().unwrap())
+    }
+
+    #[test]
+    fn test_select_prefers_larger_scope() {
+        let candidates = vec![
+            candidate("eth0", "fe80::1"),
+            candidate("eth0", "fd00::1"),
+            candidate("eth0", "2001:db8::1"),
+        ];
+        let selected = select_preferred_ipv6(&candidates, &HashSet::new(), &[]).unwrap();
+        assert_eq!(selected.1, "2001:db8::1".parse::<Ipv6Addr>().unwrap());
+
+        let candidates = vec![candidate("eth0", "fe80::1"), candidate("eth0", "fd00::1")];
+        let selected = select_preferred_ipv6(&candidates, &HashSet::new(), &[]).unwrap();
+        assert_eq!(selected.1, "fd00::1".parse::<Ipv6Addr>().unwrap());
+    }
+
+    #[test]
+    fn test_select_avoids_deprecated() {
+        let candidates = vec![candidate("eth0", "2001:db8::1"), candidate("eth0", "2001:db8::2")];
+        let deprecated: HashSet<Ipv6Addr> = ["2001:db8::1".parse().unwrap()].into_iter().collect();
+        let selected = select_preferred_ipv6(&candidates, &deprecated, &[]).unwrap();
+        assert_eq!(selected.1, "2001:db8::2".parse::<Ipv6Addr>().unwrap());
+
+        // 优先级相同时保持原有顺序
+        let selected = select_preferred_ipv6(&candidates, &HashSet::new(), &[]).unwrap();
+        assert_eq!(selected.1, "2001:db8::1".parse::<Ipv6Addr>().unwrap());
+    }
+
+    #[test]
+    fn test_select_publishable_requires_global() {
+        let candidates = vec![candidate("wg0", "fd00::1"), candidate("eth0", "fe80::1")];
+        let err = select_publishable_ipv6(candidates.clone(), &[], false).unwrap_err();
+        assert!(err.to_string().contains("fd00::1 on wg0"), "{}", err);
+
+        let selected = select_publishable_ipv6(candidates, &[], true).unwrap().unwrap();
+        assert_eq!(selected.1, "fd00::1".parse::<Ipv6Addr>().unwrap());
+
+        let candidates = vec![candidate("wg0", "fd00::1"), candidate("eth0", "2001:db8::1")];
+        let selected = select_publishable_ipv6(candidates, &[], false).unwrap().unwrap();
+        assert_eq!(selected.1, "2001:db8::1".parse::<Ipv6Addr>().unwrap());
+
+        assert!(select_publishable_ipv6(Vec::new(), &[], false).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_egress_for_loopback() {
+        let (source, _) = egress_for("127.0.0.1:9".parse().unwrap()).unwrap();
+        assert!(source.is_loopback());
+    }
+
+    #[test]
+    fn test_select_empty() {
+        assert!(select_preferred_ipv6(&[], &HashSet::new(), &[]).is_none());
+    }
+
+    #[test]
+    fn test_parse_if_inet6() {
+        let contents = "\
+20010db8000000000000000000000001 02 40 00 20     eth0
+fe800000000000000000000000000001 02 40 20 80     eth0
+00000000000000000000000000000001 01 80 10 80       lo
+";
+        let flags = parse_if_inet6(contents);
+        assert_eq!(flags.len(), 3);
+        assert_eq!(flags[0], ("2001:db8::1".parse().unwrap(), 0x20));
+        assert_eq!(flags[2], (Ipv6Addr::LOCALHOST, 0x80));
+    }
+
+    #[test]
+    fn test_resolve_stable_ipv6() {
+        let temporary: Ipv6Addr = "2001:db8::a1b2".parse().unwrap();
+        let flags = vec![
+            (temporary, 0x01),
+            ("2001:db8::dead".parse().unwrap(), 0x21),
+            ("2001:db8::1".parse().unwrap(), 0x00),
+            ("2001:db8::2".parse().unwrap(), 0x100),
+            ("2001:db8:1::3".parse().unwrap(), 0x100),
+        ];
+        // 优先选择同一前缀下生成临时地址所用的稳定地址
+        assert_eq!(resolve_stable_ipv6(temporary, &flags).unwrap(), "2001:db8::2".parse::<Ipv6Addr>().unwrap());
+
+        // 不是临时地址（或不在本机上）时原样返回
+        let stable: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        assert_eq!(resolve_stable_ipv6(stable, &flags).unwrap(), stable);
+        let remote: Ipv6Addr = "2001:db8:2::1".parse().unwrap();
+        assert_eq!(resolve_stable_ipv6(remote, &flags).unwrap(), remote);
+
+        assert!(resolve_stable_ipv6(temporary, &flags[..2]).is_err());
+    }
+
+    // 本机上第一个有全局IPv6地址的非回环接口，以及该接口的所有地址；没有时返回 None，相关测试跳过
+    fn interface_with_global_ipv6() -> Option<(String, Vec<Ipv6Addr>)> {
+        let interfaces = if_addrs::get_if_addrs().unwrap_or_default();
+        let name = interfaces
+            .iter()
+            .filter(|iface| !iface.is_loopback())
+            .find(|iface| matches!(iface.ip(), IpAddr::V6(ip) if scope_preference(&ip) == GLOBAL_SCOPE))?
+            .name
+            .clone();
+        let addresses = ipv6_candidates(
+            interfaces
+                .into_iter()
+                .filter(|iface| iface.name == name)
+                .map(|iface| {
+                    let ip = iface.ip();
+                    (iface.name, ip)
+                }),
+        );
+        Some((name, addresses.into_iter().map(|(_, ip)| ip).collect()))
+    }
+
+    #[test]
+    fn test_ipv6_candidates_skips_ipv4() {
+        let addresses = vec![
+            ("eth0".to_string(), IpAddr::from([192, 168, 1, 2])),
+            ("eth0".to_string(), "2001:db8::1".parse().unwrap()),
+            ("wg0".to_string(), IpAddr::from([10, 0, 0, 1])),
+            ("wg0".to_string(), "fd00::1".parse().unwrap()),
+        ];
+        assert_eq!(
+            ipv6_candidates(addresses),
+            vec![candidate("eth0", "2001:db8::1"), candidate("wg0", "fd00::1")]
+        );
+        assert!(ipv6_candidates(vec![("eth0".to_string(), IpAddr::from([192, 0, 2, 1]))]).is_empty());
+    }
+
+    #[test]
+    fn test_select_from_constructed_addresses() {
+        let addresses = vec![
+            ("eth0".to_string(), IpAddr::from([203, 0, 113, 7])),
+            ("eth0".to_string(), "fe80::1".parse().unwrap()),
+            ("docker0".to_string(), "2001:db8::10".parse().unwrap()),
+            ("eth0".to_string(), "2001:db8::10".parse().unwrap()),
+            ("eth0".to_string(), "2001:db8::20".parse().unwrap()),
+            ("wg0".to_string(), "fd00::1".parse().unwrap()),
+        ];
+        let candidates = ipv6_candidates(addresses);
+        let priority = vec!["eth*".to_string()];
+
+        // 全局地址优先，同一地址在多个接口上时记为优先级更高的接口
+        let selected = select_from_candidates(candidates.clone(), &HashSet::new(), &priority).unwrap();
+        assert_eq!(selected, candidate("eth0", "2001:db8::10"));
+
+        // 已废弃的地址让位于同一作用域的其他地址
+        let deprecated: HashSet<Ipv6Addr> = ["2001:db8::10".parse().unwrap()].into_iter().collect();
+        let selected = select_from_candidates(candidates.clone(), &deprecated, &priority).unwrap();
+        assert_eq!(selected, candidate("eth0", "2001:db8::20"));
+
+        // 只有非全局地址时选择作用域最大的 ULA
+        let non_global: Vec<_> = candidates.into_iter().filter(|(_, ip)| scope_preference(ip) != GLOBAL_SCOPE).collect();
+        let selected = select_from_candidates(non_global, &HashSet::new(), &[]).unwrap();
+        assert_eq!(selected, candidate("wg0", "fd00::1"));
+
+        assert!(select_from_candidates(Vec::new(), &HashSet::new(), &[]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_local_ipv6_address() {
+        let Some((name, addresses)) = interface_with_global_ipv6() else {
+            println!("No interface with a global IPv6 address, skipping");
+            return;
+        };
+
+        // 指定接口时返回该接口上的一个全局地址
+        let ip = get_local_ipv6_address(Some(&name), &InterfaceFilter::default(), &[], false).await.unwrap();
+        let ip: Ipv6Addr = ip.parse().unwrap();
+        assert!(addresses.contains(&ip), "{} is not on interface '{}'", ip, name);
+        assert_eq!(scope_preference(&ip), GLOBAL_SCOPE);
+    }
+
+    #[tokio::test]
+    async fn test_get_local_ipv6_address_with_specific_interface() {
+        let err = get_local_ipv6_address(Some("nonexistent_interface"), &InterfaceFilter::default(), &[], false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{}", err);
+
+        // 只允许该接口参与自动查找时，结果与指定接口时相同
+        let Some((name, _)) = interface_with_global_ipv6() else {
+            return;
+        };
+        let include = vec![name.clone()];
+        let filter = InterfaceFilter { include: &include, exclude: &[] };
+        let auto = get_local_ipv6_address(None, &filter, &[], false).await.unwrap();
+        let specific = get_local_ipv6_address(Some(&name), &InterfaceFilter::default(), &[], false).await.unwrap();
+        assert_eq!(auto, specific);
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_address_matches_single_read() {
+        // 测试期间地址不会变化，确认后的结果应与直接读取一致
+        let direct = get_local_ipv6_address(None, &InterfaceFilter::default(), &[], false).await;
+        let confirmed = get_confirmed_ipv6_address(None, &InterfaceFilter::default(), &[], Duration::from_millis(10), false).await;
+        match (direct, confirmed) {
+            (Ok(direct), Ok(confirmed)) => assert_eq!(direct, confirmed),
+            (Err(_), Err(_)) => {}
+            (direct, confirmed) => panic!("direct {:?} but confirmed {:?}", direct.ok(), confirmed.ok()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_local_ipv6_address_auto_discovery() {
+        // 测试自动发现功能（不指定接口）
+        let result = get_local_ipv6_address(None, &InterfaceFilter::default(), &[], false).await;
+        match result {
+            Ok(ip) => {
+                println!("Auto-discovered IPv6 address: {}", ip);
+                assert!(!ip.is_empty());
+                assert!(ip.contains(":"));
+            }
+            Err(e) => {
+                // 在某些环境中可能没有可用的 IPv6 地址，这是可以接受的
+                println!("No IPv6 address found in auto-discovery (may be expected): {}", e);
+            }
+        }
+    }
+}
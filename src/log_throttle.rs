@@ -0,0 +1,99 @@
+// 重复错误日志节流
+// 网络中断时每次定时运行都会得到相同的错误，相同的消息在一个窗口内只记录一次，
+// 窗口结束时输出一条汇总，说明仍在失败以及失败次数
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    // 新的错误或未开启节流，正常记录
+    Log,
+    // 窗口内的重复错误，不记录
+    Suppress,
+    // 窗口结束，记录汇总：自首次出现以来共失败的次数
+    Summary(u64),
+}
+
+pub struct ErrorThrottle {
+    window: Duration,
+    last: Option<Repeated>,
+}
+
+struct Repeated {
+    message: String,
+    count: u64,
+    window_start: Instant,
+}
+
+impl ErrorThrottle {
+    // window 为零时不节流
+    pub fn new(window: Duration) -> Self {
+        Self { window, last: None }
+    }
+
+    pub fn check(&mut self, message: &str, now: Instant) -> Decision {
+        if self.window.is_zero() {
+            return Decision::Log;
+        }
+
+        match &mut self.last {
+            Some(last) if last.message == message => {
+                last.count += 1;
+                if now.duration_since(last.window_start) < self.window {
+                    Decision::Suppress
+                } else {
+                    last.window_start = now;
+                    Decision::Summary(last.count)
+                }
+            }
+            _ => {
+                self.last = Some(Repeated {
+                    message: message.to_string(),
+                    count: 1,
+                    window_start: now,
+                });
+                Decision::Log
+            }
+        }
+    }
+
+    // 成功后清除记录，下一次错误重新记录；返回被压制的重复次数（不含首次记录的那次）
+    pub fn reset(&mut self) -> u64 {
+        self.last.take().map(|last| last.count - 1).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_always_logs() {
+        let mut throttle = ErrorThrottle::new(Duration::ZERO);
+        let now = Instant::now();
+        assert_eq!(throttle.check("boom", now), Decision::Log);
+        assert_eq!(throttle.check("boom", now), Decision::Log);
+    }
+
+    #[test]
+    fn test_suppresses_and_summarizes_repeats() {
+        let mut throttle = ErrorThrottle::new(Duration::from_secs(600));
+        let start = Instant::now();
+        assert_eq!(throttle.check("boom", start), Decision::Log);
+        assert_eq!(throttle.check("boom", start + Duration::from_secs(60)), Decision::Suppress);
+        assert_eq!(throttle.check("boom", start + Duration::from_secs(120)), Decision::Suppress);
+        assert_eq!(throttle.check("boom", start + Duration::from_secs(600)), Decision::Summary(4));
+        assert_eq!(throttle.check("boom", start + Duration::from_secs(660)), Decision::Suppress);
+    }
+
+    #[test]
+    fn test_new_message_and_reset() {
+        let mut throttle = ErrorThrottle::new(Duration::from_secs(600));
+        let now = Instant::now();
+        assert_eq!(throttle.check("boom", now), Decision::Log);
+        assert_eq!(throttle.check("other", now), Decision::Log);
+        assert_eq!(throttle.check("other", now), Decision::Suppress);
+        assert_eq!(throttle.reset(), 1);
+        assert_eq!(throttle.check("other", now), Decision::Log);
+        assert_eq!(throttle.reset(), 0);
+    }
+}
@@ -0,0 +1,78 @@
+// 日志输出：控制台，以及可选的按天轮转的日志文件
+use std::path::Path;
+
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, Layer};
+
+use crate::{BoxError, Config};
+
+// 保留的日志文件数量
+pub const DEFAULT_LOG_FILE_RETENTION: usize = 7;
+
+pub fn init(config: &Config, level: tracing::Level, one_shot: bool) -> Result<(), BoxError> {
+    // 一次性命令的结果写到标准输出，日志改写到标准错误，便于脚本解析
+    let console = config.log_console.then(|| {
+        if one_shot {
+            fmt::layer().with_writer(std::io::stderr).boxed()
+        } else {
+            fmt::layer().boxed()
+        }
+    });
+
+    // 直接写文件而不使用后台线程，--daemonize 的 fork 之后仍然可用
+    let file = match config.log_file.as_deref() {
+        Some(path) => Some(fmt::layer().with_ansi(false).with_writer(file_appender(path, config.log_file_retention)?).boxed()),
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(LevelFilter::from_level(level))
+        .with(console)
+        .with(file)
+        .init();
+    Ok(())
+}
+
+// 文件按天轮转，实际文件名为 <文件名>.<YYYY-MM-DD>，只保留最近 retention 个
+fn file_appender(path: &str, retention: usize) -> Result<RollingFileAppender, BoxError> {
+    let path = Path::new(path);
+    let directory = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let file_name = path.file_name().ok_or_else(|| format!("Invalid log_file '{}'", path.display()))?;
+    // 目录不存在时 tracing-appender 清理旧文件前会向标准错误输出一条错误，提前创建
+    std::fs::create_dir_all(directory).map_err(|e| format!("Failed to create log directory '{}': {}", directory.display(), e))?;
+
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(file_name.to_string_lossy())
+        .max_log_files(retention.max(1))
+        .build(directory)
+        .map_err(|e| format!("Failed to open log_file '{}': {}", path.display(), e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_file_appender_writes_dated_file() {
+        let dir = std::env::temp_dir().join(format!("rs-refresh-ddns-logs-{}", std::process::id()));
+        let path = dir.join("ddns.log");
+
+        let mut appender = file_appender(&path.to_string_lossy(), 3).unwrap();
+        appender.write_all(b"hello\n").unwrap();
+        appender.flush().unwrap();
+
+        let files: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].starts_with("ddns.log."), "{:?}", files);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
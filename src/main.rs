@@ -1,349 +1,2560 @@
 use std::ffi::OsStr;
-use std::time::Duration;
-use tokio::time;
-use tokio_cron_scheduler::{Job, JobScheduler};
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use futures::future::{join_all, try_join_all};
+use tokio::sync::Semaphore;
 use reqwest::Client;
-use tracing::{info, error, debug};
-use tracing_subscriber;
+use tracing::{info, error, debug, warn, Instrument};
+
+mod cidr;
+mod cli;
+mod commands;
+mod connectivity;
+mod daemon;
+mod dns;
+mod hook;
+mod local;
+mod log_throttle;
+mod logging;
+mod metrics;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(target_os = "linux")]
+mod netlink;
+mod notify;
+mod providers;
+mod proxy;
+mod sample_config;
+mod schedule;
+mod supervisor;
+mod upnp;
+
+// 统一的错误类型，需要 Send + Sync 以便在异步任务之间传递
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+// 按名称读取环境变量，测试中用闭包代替真实环境
+type EnvLookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+use cli::Cli;
+use local::LocalCache;
+use providers::{Provider, ProviderUpdate};
+use supervisor::Supervisor;
+
+fn main() -> Result<(), BoxError> {
+    let cli = Cli::from_env().map_err(|e| format!("{}\n\n{}", e, cli::USAGE))?;
+    if cli.help {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    // 生成示例配置后直接退出
+    if let Some(path) = cli.generate_config {
+        let sample = sample_config::generate()?;
+        match path {
+            Some(path) => std::fs::write(&path, sample)?,
+            None => print!("{}", sample),
+        }
+        return Ok(());
+    }
+
+    // 从环境变量或配置文件读取配置；日志、运行时、pid_file 等进程级设置使用顶层配置
+    let config = Config::from_env()?;
+    let profiles = config.select_profiles(cli.profile.as_deref())?;
+
+    // 初始化日志，命令行的 -v/-q 优先于配置中的 log_level
+    let level = match cli.log_level() {
+        Some(level) => level,
+        None => parse_log_level(config.log_level.as_deref())?,
+    };
+    logging::init(&config, level, cli.one_shot())?;
+
+    if cli.show_config {
+        commands::show_config(&single_profile(profiles)?, cli.format)?;
+        return Ok(());
+    }
+
+    if cli.reset_state {
+        let removed = commands::reset_state(&single_profile(profiles)?)?;
+        if removed.is_empty() {
+            println!("No state files to remove");
+        }
+        for path in removed {
+            println!("Removed {}", path);
+        }
+        return Ok(());
+    }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // 初始化日志
-    tracing_subscriber::fmt::init();
-    
     info!("Starting DDNS updater");
-    
-    // 从环境变量或配置文件读取配置
-    let config = Config::from_env();
-    
-    // 创建调度器
-    let scheduler = JobScheduler::new().await?;
-    
-    // 克隆需要的数据，避免借用冲突
-    let cron_expr = config.cron.clone();
-
-    // 创建定时任务
-    let job = Job::new_async(cron_expr.as_str(), move |_uuid, _l| {
-        let config_clone = config.clone();
-        Box::pin(async move {
-            match update_ddns(&config_clone).await {
-                Ok(_) => info!("DDNS update completed successfully"),
-                Err(e) => error!("Failed to update DDNS: {}", e),
-            }
-        })
-    })?;
 
-    scheduler.add(job).await?;
+    // 转入后台需要在创建运行时之前完成
+    if cli.daemonize {
+        daemon::daemonize(config.pid_file.as_deref())?;
+    }
+    let pid_file = config.pid_file.clone().filter(|_| cli.daemonize);
+
+    // 手动创建运行时，小内存设备上可以选择单线程运行时
+    let runtime = if config.single_threaded {
+        debug!("Using current-thread tokio runtime");
+        tokio::runtime::Builder::new_current_thread().enable_all().build()?
+    } else {
+        tokio::runtime::Builder::new_multi_thread().enable_all().build()?
+    };
 
-    scheduler.start().await?;
+    // 发送示例通知后退出
+    if cli.test_notify {
+        return runtime.block_on(notify::test_notify(&single_profile(profiles)?));
+    }
 
-    // 保持程序运行
-    loop {
-        time::sleep(Duration::from_secs(60)).await;
+    // 一次性命令：失败时已按 --format 输出结果，只需以非零状态退出
+    if cli.print_ip || cli.show_url || cli.once || cli.test_providers {
+        let config = single_profile(profiles)?;
+        let success = if cli.print_ip {
+            runtime.block_on(commands::print_ip(&config, cli.format))?
+        } else if cli.show_url {
+            runtime.block_on(commands::show_url(&config, cli.format))?
+        } else if cli.test_providers {
+            runtime.block_on(commands::test_providers(&config, cli.format))?
+        } else {
+            runtime.block_on(commands::once(config, cli.format, cli.fail_fast))?
+        };
+        if !success {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // 停止时可能还有超过等待时间的更新任务，不再等待它们结束
+    let result = runtime.block_on(run(profiles));
+    runtime.shutdown_background();
+    if let Some(path) = pid_file {
+        let _ = std::fs::remove_file(path);
     }
+    result
 }
 
+// 启动定时任务并保持运行，每个配置档案有各自的调度和运行状态
+async fn run(profiles: Vec<Config>) -> Result<(), BoxError> {
+    // 创建客户端和提供商，任一档案配置错误时在启动阶段直接失败
+    let mut supervisors = Vec::new();
+    for config in profiles {
+        let name = config.profile.clone();
+        let supervisor = Supervisor::new(config).map_err(|e| match &name {
+            Some(name) => format!("Profile '{}': {}", name, e).into(),
+            None => e,
+        })?;
+        supervisors.push(Arc::new(supervisor));
+    }
+    if supervisors.len() > 1 {
+        info!("Running {} profiles", supervisors.len());
+    }
+    try_join_all(supervisors.into_iter().map(|supervisor| {
+        let span = supervisor.log_span();
+        supervisor.run_scheduled().instrument(span)
+    }))
+    .await?;
+    Ok(())
+}
+
+
+// 构建时通过 DDNS_EMBEDDED_CONFIG 指定的配置文件（建议使用绝对路径）
+#[cfg(feature = "embedded-config")]
+const EMBEDDED_CONFIG: &str = include_str!(env!("DDNS_EMBEDDED_CONFIG"));
+
+// 未配置 cron 时的默认值，打包时可以通过 DDNS_DEFAULT_CRON 修改（见 build.rs），否则每5分钟执行一次
+const DEFAULT_CRON: &str = env!("DDNS_DEFAULT_CRON");
+
+// 同时进行的提供商更新数量上限
+const DEFAULT_MAX_CONCURRENT_UPDATES: usize = 4;
 
-// 配置结构体
-#[derive(Clone, Debug)]
-struct Config {
+// 连续失败后暂停定时更新的最长时间
+const DEFAULT_MAX_BACKOFF_SECS: u64 = 3600;
+
+// 外部服务和提供商响应体的最大字节数；IP地址很短，提供商 API 的 JSON 响应（如列出多条记录）可能超过 1 KiB
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 4096;
+
+// 按顺序尝试IP服务时两次尝试之间的基础等待时间，之后按指数增长
+const DEFAULT_IP_SERVICE_RETRY_DELAY_MS: u64 = 500;
+
+// IP服务之间等待时间的上限
+const MAX_IP_SERVICE_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+// 提供商重试前的基础等待时间，之后按指数增长
+const DEFAULT_PROVIDER_RETRY_DELAY_MS: u64 = 2000;
+
+// 提供商重试等待时间的上限
+const MAX_PROVIDER_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+// 收到停止信号后等待进行中的更新完成的最长时间
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 15;
+
+// 配置结构体，序列化结果用于 --show-config，字段名与 config.toml 一致
+// 字段不公开，嵌入方通过下面的只读访问方法读取调度、提供商和检测相关的配置
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Config {
     cron: String,
     ipv6_method: String,
     ip_service_url: String,
-    duckdns_domain: String,
-    duckdns_token: String,
+    providers: Vec<String>,
+    #[cfg_attr(not(feature = "duckdns"), allow(dead_code))]
+    duckdns_domain: Option<String>,
+    #[cfg_attr(not(feature = "duckdns"), allow(dead_code))]
+    #[serde(serialize_with = "redact")]
+    duckdns_token: Option<String>,
+    #[cfg_attr(not(feature = "dyndns2"), allow(dead_code))]
+    dyndns2_server: Option<String>,
+    #[cfg_attr(not(feature = "dyndns2"), allow(dead_code))]
+    dyndns2_username: Option<String>,
+    #[cfg_attr(not(feature = "dyndns2"), allow(dead_code))]
+    #[serde(serialize_with = "redact")]
+    dyndns2_password: Option<String>,
+    #[cfg_attr(not(feature = "dyndns2"), allow(dead_code))]
+    dyndns2_hostname: Option<String>,
+    #[cfg_attr(not(feature = "cloudflare"), allow(dead_code))]
+    #[serde(serialize_with = "redact")]
+    cloudflare_api_token: Option<String>,
+    #[cfg_attr(not(feature = "cloudflare"), allow(dead_code))]
+    cloudflare_zone_id: Option<String>,
+    #[cfg_attr(not(feature = "cloudflare"), allow(dead_code))]
+    cloudflare_record_name: Option<String>,
+    #[cfg_attr(not(feature = "cloudflare"), allow(dead_code))]
+    cloudflare_record_type: Option<String>,
+    #[cfg_attr(not(feature = "cloudflare"), allow(dead_code))]
+    cloudflare_proxied: Option<bool>,
     hosts_interface: Option<String>,
-    shell_command: Option<Vec<String>>
+    shell_command: Option<Vec<String>>,
+    required_interface: Option<String>,
+    status_file: Option<String>,
+    max_concurrent_updates: usize,
+    quiet_success: bool,
+    tls_min_version: Option<String>,
+    extra_ca_cert: Option<String>,
+    single_threaded: bool,
+    #[cfg_attr(not(feature = "duckdns"), allow(dead_code))]
+    log_response_headers: bool,
+    max_backoff_secs: u64,
+    max_response_bytes: usize,
+    nochange_cooldown_secs: u64,
+    timezone: Option<String>,
+    error_log_window_secs: u64,
+    verify_via_dns: bool,
+    dns_resolver: Option<String>,
+    mqtt_broker: Option<String>,
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    mqtt_topic: Option<String>,
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    mqtt_username: Option<String>,
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    #[serde(serialize_with = "redact")]
+    mqtt_password: Option<String>,
+    post_update_command: Option<Vec<String>>,
+    ip_service_urls: Vec<String>,
+    ip_service_quorum: usize,
+    http_version: Option<String>,
+    log_level: Option<String>,
+    #[cfg_attr(not(feature = "template"), allow(dead_code))]
+    update_url_template: Option<String>,
+    #[cfg_attr(not(feature = "template"), allow(dead_code))]
+    template_domain: Option<String>,
+    #[cfg_attr(not(feature = "template"), allow(dead_code))]
+    #[serde(serialize_with = "redact")]
+    template_token: Option<String>,
+    #[cfg_attr(not(feature = "template"), allow(dead_code))]
+    template_success_match: Option<String>,
+    #[cfg_attr(not(feature = "template"), allow(dead_code))]
+    template_nochange_match: Option<String>,
+    interface_priority: Vec<String>,
+    pause_file: Option<String>,
+    startup_delay_secs: u64,
+    notify_webhook_url: Option<String>,
+    cron_auto_seconds: bool,
+    #[cfg_attr(not(feature = "route53"), allow(dead_code))]
+    route53_hosted_zone_id: Option<String>,
+    #[cfg_attr(not(feature = "route53"), allow(dead_code))]
+    route53_record_name: Option<String>,
+    #[cfg_attr(not(feature = "route53"), allow(dead_code))]
+    route53_ttl: Option<u32>,
+    connectivity_check: Option<String>,
+    #[cfg_attr(not(feature = "duckdns"), allow(dead_code))]
+    duckdns_base_url: Option<String>,
+    route_target: Option<String>,
+    shutdown_timeout_secs: u64,
+    allow_ci: bool,
+    http_timeout_secs: Option<u64>,
+    ip_lookup_timeout_secs: Option<u64>,
+    provider_timeout_secs: Option<u64>,
+    local_cache_secs: u64,
+    pid_file: Option<String>,
+    event_driven: bool,
+    #[cfg_attr(not(any(feature = "route53", feature = "cloudflare")), allow(dead_code))]
+    record_comment: Option<String>,
+    ip_service_retry_delay_ms: u64,
+    log_file: Option<String>,
+    log_file_retention: usize,
+    log_console: bool,
+    #[cfg_attr(not(feature = "inwx"), allow(dead_code))]
+    inwx_user: Option<String>,
+    #[serde(serialize_with = "redact")]
+    #[cfg_attr(not(feature = "inwx"), allow(dead_code))]
+    inwx_pass: Option<String>,
+    #[cfg_attr(not(feature = "inwx"), allow(dead_code))]
+    inwx_record_id: Option<u64>,
+    local_confirm_ms: u64,
+    allow_non_global_ipv6: bool,
+    schedule_file: Option<String>,
+    local_providers: Vec<String>,
+    #[cfg_attr(not(feature = "hosts_file"), allow(dead_code))]
+    hosts_file: Option<String>,
+    #[cfg_attr(not(feature = "hosts_file"), allow(dead_code))]
+    hosts_file_hostname: Option<String>,
+    #[cfg_attr(not(feature = "duckdns"), allow(dead_code))]
+    domain_pattern: Option<String>,
+    include_interfaces: Vec<String>,
+    exclude_interfaces: Vec<String>,
+    metrics_file: Option<String>,
+    ipv6_env_var: Option<String>,
+    change_confirm_secs: u64,
+    publish_stable_only: bool,
+    template_success_status: Vec<u16>,
+    provider_mode: String,
+    post_update_verify_url: Option<String>,
+    skip_ipv6_startup_check: bool,
+    #[cfg_attr(not(feature = "porkbun"), allow(dead_code))]
+    porkbun_apikey: Option<String>,
+    #[serde(serialize_with = "redact")]
+    #[cfg_attr(not(feature = "porkbun"), allow(dead_code))]
+    porkbun_secretkey: Option<String>,
+    #[cfg_attr(not(feature = "porkbun"), allow(dead_code))]
+    porkbun_domain: Option<String>,
+    #[cfg_attr(not(feature = "porkbun"), allow(dead_code))]
+    porkbun_subdomain: Option<String>,
+    provider_proxy: Option<String>,
+    pre_update_delay_secs: u64,
+    ip_service_family: String,
+    notify_on: String,
+    allowed_ipv6_cidrs: Vec<String>,
+    denied_ipv6_cidrs: Vec<String>,
+    identity_probe_url: Option<String>,
+    identity_probe_expect: Option<String>,
+    ip_lookup_retries: u32,
+    provider_retries: u32,
+    provider_retry_delay_ms: u64,
+    // 配置档案的名称，来自配置文件中的 [profiles.<name>]，不是配置项
+    #[serde(skip)]
+    profile: Option<String>,
+    // 配置文件中定义的各配置档案，每个档案继承顶层字段并覆盖其中的一部分
+    #[serde(skip)]
+    profiles: Vec<Config>,
+}
+
+// 输出配置时隐藏密钥，只保留是否设置
+fn redact<S: serde::Serializer>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(_) => serializer.serialize_some("***"),
+        None => serializer.serialize_none(),
+    }
 }
 
 impl Config {
-    fn from_env() -> Self {
-        // 尝试从配置文件读取
-        if let Ok(config) = Self::from_file("config.toml") {
-            return config;
+    // 定时任务的 cron 表达式（配置了 schedule_file 时以调度文件为准）
+    pub fn cron(&self) -> &str {
+        &self.cron
+    }
+
+    // 解释 cron 的时区，未配置时为 None（UTC）
+    pub fn timezone(&self) -> Option<&str> {
+        self.timezone.as_deref()
+    }
+
+    // 按配置顺序排列的提供商名称
+    pub fn providers(&self) -> &[String] {
+        &self.providers
+    }
+
+    // IPv6 获取方式，如 external、local、route
+    pub fn ipv6_method(&self) -> &str {
+        &self.ipv6_method
+    }
+
+    // local/route 方式使用的网络接口，未设置时自动查找
+    pub fn hosts_interface(&self) -> Option<&str> {
+        self.hosts_interface.as_deref()
+    }
+
+    // hosts_interface 解析后的候选接口列表，未设置时为 None
+    pub fn hosts_interfaces(&self) -> Option<Vec<String>> {
+        self.hosts_interface
+            .as_deref()
+            .map(|spec| parse_interface_list(spec, &|name| std::env::var(name).ok()))
+    }
+
+    // 配置档案的名称，不是来自 [profiles.<name>] 时为 None
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    // 要运行的配置档案：指定名称时只返回该档案，否则返回全部；没有定义档案时返回配置本身
+    fn select_profiles(&self, name: Option<&str>) -> Result<Vec<Config>, BoxError> {
+        match name {
+            None if self.profiles.is_empty() => Ok(vec![self.clone()]),
+            None => Ok(self.profiles.clone()),
+            Some(name) if self.profiles.is_empty() => {
+                Err(format!("--profile {} was given but the config defines no [profiles.<name>] sections", name).into())
+            }
+            Some(name) => match self.profiles.iter().find(|profile| profile.profile() == Some(name)) {
+                Some(profile) => Ok(vec![profile.clone()]),
+                None => Err(format!("Unknown profile '{}', available: {}", name, profile_names(&self.profiles)).into()),
+            },
         }
+    }
 
-        // 如果配置文件不存在，则从环境变量读取
-        let shell_command = if let Ok(cmd) = std::env::var("SHELL_COMMAND") {
-            Some(vec!["sh".to_string(), "-c".to_string(), cmd])
-        } else {
-            None
-        };
+    // 查询外部IP服务的超时，未单独设置时为 None，沿用客户端的全局超时
+    fn ip_lookup_timeout(&self) -> Option<Duration> {
+        self.ip_lookup_timeout_secs.or(self.http_timeout_secs).map(Duration::from_secs)
+    }
+
+    // 提供商更新请求的超时，规则同上
+    #[cfg_attr(
+        not(any(
+            feature = "duckdns",
+            feature = "dyndns2",
+            feature = "cloudflare",
+            feature = "template",
+            feature = "route53",
+            feature = "inwx",
+            feature = "porkbun"
+        )),
+        allow(dead_code)
+    )]
+    fn provider_timeout(&self) -> Option<Duration> {
+        self.provider_timeout_secs.or(self.http_timeout_secs).map(Duration::from_secs)
+    }
+
+    fn from_env() -> Result<Self, BoxError> {
+        // 配置文件存在时从配置文件读取，设置了的环境变量逐项覆盖文件中的值；文件有误时直接报错而不是悄悄改用环境变量
+        // 用 symlink_metadata 判断，断开或成环的符号链接也算存在，由 from_file 报告原因
+        if std::fs::symlink_metadata("config.toml").is_ok() {
+            return Self::from_file("config.toml");
+        }
+
+        // 编译时嵌入的默认配置，没有配置文件且没有设置任何配置环境变量时使用
+        #[cfg(feature = "embedded-config")]
+        if !sample_config::any_env_set(|name| std::env::var_os(name).is_some()) {
+            info!("No config.toml or config environment variables found, using the embedded config");
+            return Self::from_toml_str(EMBEDDED_CONFIG).map_err(|e| format!("Invalid embedded config: {}", e).into());
+        }
+
+        // 如果配置文件不存在，则从环境变量读取；与覆盖配置文件时走同一套按字段类型的解析和校验
+        Self::from_table(toml::Table::new(), Some(&|name| std::env::var(name).ok()))
+    }
 
-        // 如果配置文件不存在，则从环境变量读取
-        Self {
-            cron: std::env::var("CRON").unwrap_or_else(|_| "0 */5 * * * *".to_string()), // 默认每5分钟执行一次
-            ipv6_method: std::env::var("IPV6_METHOD").unwrap_or_else(|_| "external".to_string()), // 默认使用外部服务
-            ip_service_url: std::env::var("IP_SERVICE_URL").unwrap_or_else(|_| "https://6.ipw.cn".to_string()),
-            duckdns_domain: std::env::var("DUCKDNS_DOMAIN").expect("DUCKDNS_DOMAIN must be set"),
-            duckdns_token: std::env::var("DUCKDNS_TOKEN").expect("DUCKDNS_TOKEN must be set"),
-            hosts_interface: std::env::var("HOSTS_INTERFACE").ok(),
-            shell_command,
+    fn from_file(path: &str) -> Result<Self, BoxError> {
+        let contents = read_config_file(path)?;
+        Self::from_toml(&contents, Some(&|name| std::env::var(name).ok()))
+    }
+
+    #[cfg_attr(not(feature = "embedded-config"), allow(dead_code))]
+    fn from_toml_str(contents: &str) -> Result<Self, BoxError> {
+        Self::from_toml(contents, None)
+    }
+
+    // env_overrides 不为 None 时，其中设置了的配置环境变量覆盖文件中的同名字段
+    fn from_toml(contents: &str, env_overrides: Option<EnvLookup>) -> Result<Self, BoxError> {
+        let mut table: toml::Table = toml::from_str(contents)?;
+        // 展开字符串中的 ${VAR} 环境变量引用，便于将令牌等敏感信息放在环境变量中
+        for (_, value) in table.iter_mut() {
+            expand_env_in_value(value, &|name| std::env::var(name).ok())?;
+        }
+        // 配置档案单独解析：以顶层字段为基础，用档案中的字段覆盖，再应用环境变量
+        let profiles = table.remove("profiles");
+        let mut config = Self::from_table(table.clone(), env_overrides)?;
+        let Some(profiles) = profiles else {
+            return Ok(config);
+        };
+        let toml::Value::Table(profiles) = profiles else {
+            return Err("'profiles' must contain [profiles.<name>] sections".into());
+        };
+        if profiles.is_empty() {
+            return Err("'profiles' must define at least one profile".into());
+        }
+        for (name, overrides) in profiles {
+            let toml::Value::Table(overrides) = overrides else {
+                return Err(format!("Profile '{}' must be a [profiles.{}] section", name, name).into());
+            };
+            let mut merged = table.clone();
+            merged.extend(overrides);
+            let mut profile = Self::from_table(merged, env_overrides).map_err(|e| format!("Invalid profile '{}': {}", name, e))?;
+            profile.profile = Some(name);
+            config.profiles.push(profile);
         }
+        check_profile_files(&config.profiles)?;
+        Ok(config)
     }
 
-    fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        use std::fs;
-        let contents = fs::read_to_string(path)?; // 这里是安全的，因为 path 是 &str
-        let config: ConfigFile = toml::from_str(&contents)?;
+    fn from_table(mut table: toml::Table, env_overrides: Option<EnvLookup>) -> Result<Self, BoxError> {
+        if let Some(lookup) = env_overrides {
+            apply_env_overrides(&mut table, lookup)?;
+        }
+        let config: ConfigFile = toml::Value::Table(table).try_into()?;
 
         Ok(Self {
-            cron: config.cron.unwrap_or_else(|| "0 */5 * * * *".to_string()),
+            cron: config.cron.unwrap_or_else(|| DEFAULT_CRON.to_string()),
             ipv6_method: config.ipv6_method.unwrap_or_else(|| "external".to_string()),
             ip_service_url: config.ip_service_url.unwrap_or_else(|| "https://6.ipw.cn".to_string()),
-            duckdns_domain: config.duckdns_domain.ok_or("DUCKDNS_DOMAIN must be set")?,
-            duckdns_token: config.duckdns_token.ok_or("DUCKDNS_TOKEN must be set")?,
+            providers: config.providers.unwrap_or_else(|| vec!["duckdns".to_string()]),
+            duckdns_domain: config.duckdns_domain,
+            duckdns_token: config.duckdns_token,
+            dyndns2_server: config.dyndns2_server,
+            dyndns2_username: config.dyndns2_username,
+            dyndns2_password: config.dyndns2_password,
+            dyndns2_hostname: config.dyndns2_hostname,
+            cloudflare_api_token: config.cloudflare_api_token,
+            cloudflare_zone_id: config.cloudflare_zone_id,
+            cloudflare_record_name: config.cloudflare_record_name,
+            cloudflare_record_type: config.cloudflare_record_type,
+            cloudflare_proxied: config.cloudflare_proxied,
             hosts_interface: config.hosts_interface,
             shell_command: config.shell_command,
+            required_interface: config.required_interface,
+            status_file: config.status_file,
+            max_concurrent_updates: config.max_concurrent_updates.unwrap_or(DEFAULT_MAX_CONCURRENT_UPDATES),
+            quiet_success: config.quiet_success.unwrap_or(false),
+            tls_min_version: config.tls_min_version,
+            extra_ca_cert: config.extra_ca_cert,
+            single_threaded: config.single_threaded.unwrap_or(false),
+            log_response_headers: config.log_response_headers.unwrap_or(false),
+            max_backoff_secs: config.max_backoff_secs.unwrap_or(DEFAULT_MAX_BACKOFF_SECS),
+            max_response_bytes: config.max_response_bytes.unwrap_or(DEFAULT_MAX_RESPONSE_BYTES),
+            nochange_cooldown_secs: config.nochange_cooldown_secs.unwrap_or(0),
+            timezone: config.timezone,
+            error_log_window_secs: config.error_log_window_secs.unwrap_or(0),
+            verify_via_dns: config.verify_via_dns.unwrap_or(false),
+            dns_resolver: config.dns_resolver,
+            mqtt_broker: config.mqtt_broker,
+            mqtt_topic: config.mqtt_topic,
+            mqtt_username: config.mqtt_username,
+            mqtt_password: config.mqtt_password,
+            post_update_command: config.post_update_command,
+            ip_service_urls: config.ip_service_urls.unwrap_or_default(),
+            ip_service_quorum: config.ip_service_quorum.unwrap_or(0),
+            http_version: config.http_version,
+            log_level: config.log_level,
+            update_url_template: config.update_url_template,
+            template_domain: config.template_domain,
+            template_token: config.template_token,
+            template_success_match: config.template_success_match,
+            template_nochange_match: config.template_nochange_match,
+            interface_priority: config.interface_priority.unwrap_or_default(),
+            pause_file: config.pause_file,
+            startup_delay_secs: config.startup_delay_secs.unwrap_or(0),
+            notify_webhook_url: config.notify_webhook_url,
+            cron_auto_seconds: config.cron_auto_seconds.unwrap_or(false),
+            route53_hosted_zone_id: config.route53_hosted_zone_id,
+            route53_record_name: config.route53_record_name,
+            route53_ttl: config.route53_ttl,
+            connectivity_check: config.connectivity_check,
+            duckdns_base_url: config.duckdns_base_url,
+            route_target: config.route_target,
+            shutdown_timeout_secs: config.shutdown_timeout_secs.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_SECS),
+            allow_ci: config.allow_ci.unwrap_or(false),
+            http_timeout_secs: config.http_timeout_secs,
+            ip_lookup_timeout_secs: config.ip_lookup_timeout_secs,
+            provider_timeout_secs: config.provider_timeout_secs,
+            local_cache_secs: config.local_cache_secs.unwrap_or(0),
+            pid_file: config.pid_file,
+            event_driven: config.event_driven.unwrap_or(false),
+            record_comment: config.record_comment,
+            ip_service_retry_delay_ms: config.ip_service_retry_delay_ms.unwrap_or(DEFAULT_IP_SERVICE_RETRY_DELAY_MS),
+            log_file: config.log_file,
+            log_file_retention: config.log_file_retention.unwrap_or(logging::DEFAULT_LOG_FILE_RETENTION),
+            log_console: config.log_console.unwrap_or(true),
+            inwx_user: config.inwx_user,
+            inwx_pass: config.inwx_pass,
+            inwx_record_id: config.inwx_record_id,
+            local_confirm_ms: config.local_confirm_ms.unwrap_or(0),
+            allow_non_global_ipv6: config.allow_non_global_ipv6.unwrap_or(false),
+            schedule_file: config.schedule_file,
+            local_providers: config.local_providers.unwrap_or_default(),
+            hosts_file: config.hosts_file,
+            hosts_file_hostname: config.hosts_file_hostname,
+            domain_pattern: config.domain_pattern,
+            include_interfaces: config.include_interfaces.unwrap_or_default(),
+            exclude_interfaces: config.exclude_interfaces.unwrap_or_default(),
+            metrics_file: config.metrics_file,
+            ipv6_env_var: config.ipv6_env_var,
+            change_confirm_secs: config.change_confirm_secs.unwrap_or(0),
+            publish_stable_only: config.publish_stable_only.unwrap_or(false),
+            template_success_status: config.template_success_status.unwrap_or_default(),
+            provider_mode: config.provider_mode.unwrap_or_else(|| "all".to_string()),
+            post_update_verify_url: config.post_update_verify_url,
+            skip_ipv6_startup_check: config.skip_ipv6_startup_check.unwrap_or(false),
+            porkbun_apikey: config.porkbun_apikey,
+            porkbun_secretkey: config.porkbun_secretkey,
+            porkbun_domain: config.porkbun_domain,
+            porkbun_subdomain: config.porkbun_subdomain,
+            provider_proxy: config.provider_proxy,
+            pre_update_delay_secs: config.pre_update_delay_secs.unwrap_or(0),
+            ip_service_family: config.ip_service_family.unwrap_or_else(|| "auto".to_string()),
+            notify_on: config.notify_on.unwrap_or_else(|| "change_or_failure".to_string()),
+            allowed_ipv6_cidrs: config.allowed_ipv6_cidrs.unwrap_or_default(),
+            denied_ipv6_cidrs: config.denied_ipv6_cidrs.unwrap_or_default(),
+            profile: None,
+            profiles: Vec::new(),
+            identity_probe_url: config.identity_probe_url,
+            identity_probe_expect: config.identity_probe_expect,
+            ip_lookup_retries: config.ip_lookup_retries.unwrap_or(0),
+            provider_retries: config.provider_retries.unwrap_or(0),
+            provider_retry_delay_ms: config.provider_retry_delay_ms.unwrap_or(DEFAULT_PROVIDER_RETRY_DELAY_MS),
         })
     }
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, serde::Serialize)]
 struct ConfigFile {
     cron: Option<String>,
     ipv6_method: Option<String>,
     ip_service_url: Option<String>,
+    providers: Option<Vec<String>>,
     duckdns_domain: Option<String>,
     duckdns_token: Option<String>,
+    dyndns2_server: Option<String>,
+    dyndns2_username: Option<String>,
+    dyndns2_password: Option<String>,
+    dyndns2_hostname: Option<String>,
+    cloudflare_api_token: Option<String>,
+    cloudflare_zone_id: Option<String>,
+    cloudflare_record_name: Option<String>,
+    cloudflare_record_type: Option<String>,
+    cloudflare_proxied: Option<bool>,
     hosts_interface: Option<String>,
     shell_command: Option<Vec<String>>,
+    required_interface: Option<String>,
+    status_file: Option<String>,
+    max_concurrent_updates: Option<usize>,
+    quiet_success: Option<bool>,
+    tls_min_version: Option<String>,
+    extra_ca_cert: Option<String>,
+    single_threaded: Option<bool>,
+    log_response_headers: Option<bool>,
+    max_backoff_secs: Option<u64>,
+    max_response_bytes: Option<usize>,
+    nochange_cooldown_secs: Option<u64>,
+    timezone: Option<String>,
+    error_log_window_secs: Option<u64>,
+    verify_via_dns: Option<bool>,
+    dns_resolver: Option<String>,
+    mqtt_broker: Option<String>,
+    mqtt_topic: Option<String>,
+    mqtt_username: Option<String>,
+    mqtt_password: Option<String>,
+    post_update_command: Option<Vec<String>>,
+    ip_service_urls: Option<Vec<String>>,
+    ip_service_quorum: Option<usize>,
+    http_version: Option<String>,
+    log_level: Option<String>,
+    update_url_template: Option<String>,
+    template_domain: Option<String>,
+    template_token: Option<String>,
+    template_success_match: Option<String>,
+    template_nochange_match: Option<String>,
+    interface_priority: Option<Vec<String>>,
+    pause_file: Option<String>,
+    startup_delay_secs: Option<u64>,
+    notify_webhook_url: Option<String>,
+    cron_auto_seconds: Option<bool>,
+    route53_hosted_zone_id: Option<String>,
+    route53_record_name: Option<String>,
+    route53_ttl: Option<u32>,
+    connectivity_check: Option<String>,
+    duckdns_base_url: Option<String>,
+    route_target: Option<String>,
+    shutdown_timeout_secs: Option<u64>,
+    allow_ci: Option<bool>,
+    http_timeout_secs: Option<u64>,
+    ip_lookup_timeout_secs: Option<u64>,
+    provider_timeout_secs: Option<u64>,
+    local_cache_secs: Option<u64>,
+    pid_file: Option<String>,
+    event_driven: Option<bool>,
+    record_comment: Option<String>,
+    ip_service_retry_delay_ms: Option<u64>,
+    log_file: Option<String>,
+    log_file_retention: Option<usize>,
+    log_console: Option<bool>,
+    inwx_user: Option<String>,
+    inwx_pass: Option<String>,
+    inwx_record_id: Option<u64>,
+    local_confirm_ms: Option<u64>,
+    allow_non_global_ipv6: Option<bool>,
+    schedule_file: Option<String>,
+    local_providers: Option<Vec<String>>,
+    hosts_file: Option<String>,
+    hosts_file_hostname: Option<String>,
+    domain_pattern: Option<String>,
+    include_interfaces: Option<Vec<String>>,
+    exclude_interfaces: Option<Vec<String>>,
+    metrics_file: Option<String>,
+    ipv6_env_var: Option<String>,
+    change_confirm_secs: Option<u64>,
+    publish_stable_only: Option<bool>,
+    template_success_status: Option<Vec<u16>>,
+    provider_mode: Option<String>,
+    post_update_verify_url: Option<String>,
+    skip_ipv6_startup_check: Option<bool>,
+    porkbun_apikey: Option<String>,
+    porkbun_secretkey: Option<String>,
+    porkbun_domain: Option<String>,
+    porkbun_subdomain: Option<String>,
+    provider_proxy: Option<String>,
+    pre_update_delay_secs: Option<u64>,
+    ip_service_family: Option<String>,
+    notify_on: Option<String>,
+    allowed_ipv6_cidrs: Option<Vec<String>>,
+    denied_ipv6_cidrs: Option<Vec<String>>,
+    identity_probe_url: Option<String>,
+    identity_probe_expect: Option<String>,
+    ip_lookup_retries: Option<u32>,
+    provider_retries: Option<u32>,
+    provider_retry_delay_ms: Option<u64>,
 }
 
-// 更新DDNS的主函数
-async fn update_ddns(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
-    info!("Starting DDNS update process");
-    
-    // 获取IPv6地址
-    let ipv6 = get_ipv6_address(config).await?;
-    info!("Current IPv6 address: {}", ipv6);
-    
-    // 调用DuckDNS更新接口
-    update_duckdns(config, &ipv6).await?;
-    
-    Ok(())
+// 读取配置文件，常见的误配置给出明确提示而不是操作系统错误码：
+// Docker 挂载宿主机上不存在的文件时会创建同名目录，符号链接可能断开或成环
+fn read_config_file(path: &str) -> Result<String, BoxError> {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => {
+            return Err(format!("Config path '{}' is a directory, expected a file", path).into());
+        }
+        Ok(_) => {}
+        Err(e) if is_symlink_loop(&e) => {
+            return Err(format!("Config path '{}' is a symlink loop, expected a file", path).into());
+        }
+        Err(e) if std::fs::symlink_metadata(path).is_ok() => {
+            return Err(format!("Config path '{}' is a symlink whose target cannot be read: {}", path, e).into());
+        }
+        Err(_) => {}
+    }
+    std::fs::read_to_string(path).map_err(|e| format!("Failed to read config file '{}': {}", path, e).into())
 }
 
-// 获取IPv6地址
-async fn get_ipv6_address(config: &Config) -> Result<String, Box<dyn std::error::Error>> {
-    match config.ipv6_method.as_str() {
-        "external" => {
-            // 通过外部服务获取IPv6地址
-            get_ipv6_from_external_service(&config.ip_service_url).await
-        },
-        "local" => {
-            // 直接获取本地IPv6地址
-            get_local_ipv6_address(config.hosts_interface.as_deref()).await
-        },
-        "shell" => {
-            // 通过执行shell命令获取IPv6地址
-            get_ipv6_from_custom_shell(config).await
-        },
-        _ => {
-            error!("Invalid IPV6_METHOD: {}. Using external service.", config.ipv6_method);
-            get_ipv6_from_external_service(&config.ip_service_url).await
+fn profile_names(profiles: &[Config]) -> String {
+    profiles.iter().filter_map(Config::profile).collect::<Vec<_>>().join(", ")
+}
+
+// 各配置档案写入同一个状态文件或指标文件时会互相覆盖，要求每个档案使用自己的路径
+fn check_profile_files(profiles: &[Config]) -> Result<(), BoxError> {
+    for (i, profile) in profiles.iter().enumerate() {
+        for other in &profiles[i + 1..] {
+            let files = [
+                ("status_file", &profile.status_file, &other.status_file),
+                ("metrics_file", &profile.metrics_file, &other.metrics_file),
+            ];
+            for (field, a, b) in files {
+                if let (Some(a), Some(b)) = (a, b)
+                    && a == b
+                {
+                    return Err(format!(
+                        "Profiles '{}' and '{}' both use {} '{}', set a different path in each profile",
+                        profile.profile().unwrap_or_default(),
+                        other.profile().unwrap_or_default(),
+                        field,
+                        a
+                    )
+                    .into());
+                }
+            }
         }
     }
+    Ok(())
 }
 
-// 通过外部服务获取IPv6地址
-async fn get_ipv6_from_external_service(url: &str) -> Result<String, Box<dyn std::error::Error>> {
-    debug!("Fetching IPv6 from external service: {}", url);
-    
-    let client = Client::new();
-    let response = client.get(url).send().await?;
-    let ip = response.text().await?;
-    
-    debug!("Got IPv6 from external service: {}", ip);
-    Ok(ip)
+// 一次性命令只针对一个配置档案，定义了多个档案时需要用 --profile 选择
+fn single_profile(mut profiles: Vec<Config>) -> Result<Config, BoxError> {
+    if profiles.len() > 1 {
+        return Err(format!(
+            "The config defines several profiles ({}), choose one with --profile <name>",
+            profile_names(&profiles)
+        )
+        .into());
+    }
+    profiles.pop().ok_or_else(|| "No profile selected".into())
 }
 
-// 直接获取本地IPv6地址
-// 直接获取本地IPv6地址 - 改进版本
-async fn get_local_ipv6_address(interface_name: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
-    // 添加 if-addrs 依赖到 Cargo.toml:
-    // if-addrs = "0.12"
-    let interfaces = if_addrs::get_if_addrs()?;
+#[cfg(unix)]
+fn is_symlink_loop(error: &std::io::Error) -> bool {
+    error.raw_os_error() == Some(libc::ELOOP)
+}
 
-    for iface in interfaces {
-        // 如果指定了接口名称，则只检查该接口
-        if let Some(name) = interface_name {
-            if iface.name != name {
-                continue;
-            }
-        }
+#[cfg(not(unix))]
+fn is_symlink_loop(_error: &std::io::Error) -> bool {
+    false
+}
 
-        // 跳过回环接口（除非用户明确指定）
-        if iface.is_loopback() && interface_name.is_none() {
+// 用环境变量（字段名的大写形式）覆盖配置文件中的字段，按示例配置中该字段的类型解析；只用环境变量时也走这里：
+// 列表以逗号分隔，布尔值只接受 true 或 false，shell_command 和 post_update_command 通过 sh -c 执行
+fn apply_env_overrides(table: &mut toml::Table, lookup: EnvLookup) -> Result<(), BoxError> {
+    use toml::Value;
+
+    let strings = |values: Vec<String>| Value::Array(values.into_iter().map(Value::String).collect());
+    for (name, sample) in sample_config::field_types()? {
+        let env_name = name.to_uppercase();
+        let Some(raw) = lookup(&env_name) else {
             continue;
+        };
+        let integer = |raw: &str| {
+            raw.trim()
+                .parse()
+                .map(Value::Integer)
+                .map_err(|_| format!("Invalid value for {}: '{}', expected an integer", env_name, raw))
+        };
+        let value = match (name.as_str(), sample) {
+            ("shell_command", _) => strings(vec!["sh".to_string(), "-c".to_string(), raw]),
+            ("post_update_command", _) => strings(hook::shell_command(raw)),
+            (_, Value::Boolean(_)) => match raw.trim() {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                _ => return Err(format!("Invalid value for {}: '{}', expected true or false", env_name, raw).into()),
+            },
+            (_, Value::Integer(_)) => integer(&raw)?,
+            (_, Value::Array(sample_items)) => {
+                let items = raw.split(',').map(str::trim).filter(|item| !item.is_empty());
+                if matches!(sample_items.first(), Some(Value::Integer(_))) {
+                    Value::Array(items.map(integer).collect::<Result<_, _>>()?)
+                } else {
+                    strings(items.map(str::to_string).collect())
+                }
+            }
+            _ => Value::String(raw),
+        };
+        table.insert(name, value);
+    }
+    Ok(())
+}
+
+// 递归展开TOML中所有字符串里的 ${VAR} 引用
+fn expand_env_in_value(value: &mut toml::Value, lookup: &dyn Fn(&str) -> Option<String>) -> Result<(), BoxError> {
+    match value {
+        toml::Value::String(s) => *s = expand_env_vars(s, lookup)?,
+        toml::Value::Array(items) => {
+            for item in items {
+                expand_env_in_value(item, lookup)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                expand_env_in_value(item, lookup)?;
+            }
         }
+        _ => {}
+    }
+    Ok(())
+}
 
-        // 查找 IPv6 地址
-        if let std::net::IpAddr::V6(ipv6) = iface.ip() {
+// 将字符串中的 ${VAR} 替换为环境变量的值，变量未设置时报错
+fn expand_env_vars(input: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, BoxError> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
 
-            let ip_str = ipv6.to_string();
-            debug!("Got IPv6 address from interface '{}': {}", iface.name, ip_str);
-            return Ok(ip_str);
-        }
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("Unterminated '${{' in config value: {}", input))?;
+        let name = &after[..end];
+        let value = lookup(name)
+            .ok_or_else(|| format!("Environment variable '{}' referenced in config is not set", name))?;
+        output.push_str(&value);
+        rest = &after[end + 1..];
     }
 
-    if let Some(name) = interface_name {
-        Err(format!("No IPv6 address found for interface '{}'", name).into())
-    } else {
-        Err("No public IPv6 address found on any interface".into())
+    output.push_str(rest);
+    Ok(output)
+}
+
+// 解析日志级别，未配置时为 info
+fn parse_log_level(level: Option<&str>) -> Result<tracing::Level, BoxError> {
+    match level {
+        None => Ok(tracing::Level::INFO),
+        Some(level) => level
+            .parse()
+            .map_err(|_| format!("Invalid LOG_LEVEL: {}. Expected error, warn, info, debug or trace", level).into()),
     }
 }
 
-// 通过执行自定义shell命令获取IPv6地址
-async fn get_ipv6_from_custom_shell(config: &Config) -> Result<String, Box<dyn std::error::Error>> {
-    // 仅支持Linux和macOS
-    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
-    return Err("Shell command method only supported on Linux and macOS".into());
+// 根据配置创建HTTP客户端
+fn build_http_client(config: &Config) -> Result<Client, BoxError> {
+    Ok(http_client_builder(config)?.build()?)
+}
 
-    #[cfg(any(target_os = "linux", target_os = "macos"))]
-    {
-        // 检查是否配置了shell命令
-        let command_parts = config.shell_command.as_ref().ok_or("No shell command configured for IPv6 method 'shell'")?;
+// 按配置设置好 TLS、协议版本、超时和证书的客户端构建器，需要额外设置（如不跟随重定向）时使用
+fn http_client_builder(config: &Config) -> Result<reqwest::ClientBuilder, BoxError> {
+    let mut builder = Client::builder();
 
-        if command_parts.is_empty() {
-            return Err("Shell command is empty".into());
+    if let Some(version) = config.tls_min_version.as_deref() {
+        let version = match version {
+            "1.0" => reqwest::tls::Version::TLS_1_0,
+            "1.1" => reqwest::tls::Version::TLS_1_1,
+            "1.2" => reqwest::tls::Version::TLS_1_2,
+            "1.3" => reqwest::tls::Version::TLS_1_3,
+            _ => return Err(format!("Invalid TLS_MIN_VERSION: {}. Expected 1.0, 1.1, 1.2 or 1.3", version).into()),
+        };
+        builder = builder.min_tls_version(version);
+    }
+
+    // 固定HTTP协议版本，用于协议协商有问题的服务；http2 不经协商直接使用 HTTP/2
+    match config.http_version.as_deref() {
+        None | Some("auto") => {}
+        Some("http1") => builder = builder.http1_only(),
+        Some("http2") => builder = builder.http2_prior_knowledge(),
+        Some(version) => {
+            return Err(format!("Invalid HTTP_VERSION: {}. Expected auto, http1 or http2", version).into());
         }
+    }
 
-        debug!("Fetching IPv6 address by executing custom shell command: {:?}", command_parts);
+    // 全局超时，查询IP服务和提供商更新可以分别单独设置
+    if let Some(secs) = config.http_timeout_secs {
+        builder = builder.timeout(Duration::from_secs(secs));
+    }
 
-        // 提取命令和参数
-        let command = &command_parts[0];
-        let args = &command_parts[1..];
+    // 额外信任的CA证书（PEM格式），用于自建或内网的提供商
+    if let Some(path) = config.extra_ca_cert.as_deref() {
+        let pem = std::fs::read(path).map_err(|e| format!("Failed to read extra_ca_cert '{}': {}", path, e))?;
+        let cert = reqwest::Certificate::from_pem(&pem)
+            .map_err(|e| format!("Invalid extra_ca_cert '{}': {}", path, e))?;
+        builder = builder.add_root_certificate(cert);
+    }
 
-        debug!("Executing command: {} {:?}", command, args);
+    Ok(builder)
+}
 
-        let output = tokio::process::Command::new(command.to_string())
-            .args(args.iter().map(|s| <std::string::String as AsRef<OsStr>>::as_ref(s)))
-            .output()
-            .await?;
+// 一次DDNS更新的结果
+#[derive(Debug)]
+struct UpdateOutcome {
+    // 是否有提供商记录发生了变化
+    changed: bool,
+    // 本次发布的地址
+    ip: Option<IpAddr>,
+    // 各提供商返回的原始响应，每行一个提供商
+    provider_response: String,
+    // 每个提供商（多域名时每个域名一个）的结果
+    providers: Vec<ProviderResult>,
+}
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            error!("Shell command failed - stderr: {}, stdout: {}", stderr, stdout);
-            return Err(format!("Shell command failed: {}", String::from_utf8_lossy(&output.stderr)).into());
-        }
+impl UpdateOutcome {
+    fn ip_string(&self) -> String {
+        self.ip.map(|ip| ip.to_string()).unwrap_or_default()
+    }
 
-        let ipv6 = String::from_utf8(output.stdout)?.trim().to_string();
-        if ipv6.is_empty() {
-            return Err("Shell command returned empty output".into());
-        }
+    // 形如 "3 updated, 1 failed (duckdns:b: KO)"，省略数量为 0 的类别
+    fn summary(&self) -> String {
+        let count = |status| self.providers.iter().filter(|result| result.status == status).count();
+        let failures: Vec<String> = self
+            .providers
+            .iter()
+            .filter(|result| result.status == "failed")
+            .map(|result| format!("{}: {}", result.provider, result.detail))
+            .collect();
 
-        // 验证IPv6地址格式
-        if !ipv6.contains(":") {
-            return Err(format!("Shell command returned invalid IPv6 address: {}", ipv6).into());
+        let mut parts = Vec::new();
+        for status in ["updated", "unchanged", "failed", "skipped"] {
+            if count(status) > 0 {
+                parts.push(format!("{} {}", count(status), status));
+            }
+        }
+        let mut summary = parts.join(", ");
+        if !failures.is_empty() {
+            summary.push_str(&format!(" ({})", failures.join("; ")));
         }
+        summary
+    }
+}
 
-        debug!("Got IPv6 from shell command: {}", ipv6);
-        Ok(ipv6)
+// 单个提供商的更新结果，写入状态文件和 --once 的输出
+#[derive(Clone, Debug, serde::Serialize)]
+struct ProviderResult {
+    provider: String,
+    // updated、unchanged、failed，或 failover 模式下无需尝试时为 skipped
+    status: &'static str,
+    // 提供商的响应或错误信息
+    detail: String,
+}
+
+// 有提供商更新失败：整体视为失败（--once 退出码非零、进入退避），但保留所有提供商的结果
+#[derive(Debug)]
+struct PartialFailure(UpdateOutcome);
+
+impl std::fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "provider updates failed: {}", self.0.summary())
     }
 }
 
-// 更新DuckDNS
-async fn update_duckdns(config: &Config, ipv6: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let url = format!(
-        "https://www.duckdns.org/update?domains={}&token={}&ipv6={}&verbose=true",
-        config.duckdns_domain,
-        config.duckdns_token,
-        ipv6
-    );
-    
-    info!("Updating DuckDNS with URL: {}", url);
-    
-    let client = Client::new();
-    let response = client.get(&url).send().await?;
-    
-    let status = response.status();
-    let body = response.text().await?;
-    
-    info!("DuckDNS update response - Status: {}, Body: {}", status, body);
-    
-    if status.is_success() {
-        Ok(())
-    } else {
-        Err(format!("DuckDNS update failed with status: {}", status).into())
+impl std::error::Error for PartialFailure {}
+
+// 本次运行得到的结果：成功，或部分失败时已完成的部分
+fn outcome_of(result: &Result<UpdateOutcome, BoxError>) -> Option<&UpdateOutcome> {
+    match result {
+        Ok(outcome) => Some(outcome),
+        Err(e) => e.downcast_ref::<PartialFailure>().map(|partial| &partial.0),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[tokio::test]
-    async fn test_get_local_ipv6_address() {
-        
-        let result = get_local_ipv6_address(Some("en0")).await;
-        match result {
-            Ok(ip) => println!("Local IPv6 address: {}", ip),
-            Err(e) => println!("Error getting local IPv6 address: {}", e),
+// 判断本次是否需要执行更新
+// 配置了 required_interface 时，只有该网络接口存在（例如已连接家庭网络）才执行
+fn should_run(config: &Config) -> bool {
+    let Some(required) = config.required_interface.as_deref() else {
+        return true;
+    };
+
+    match if_addrs::get_if_addrs() {
+        Ok(interfaces) => {
+            if interfaces.iter().any(|iface| iface.name == required) {
+                true
+            } else {
+                info!("Required interface '{}' not present, skipping DDNS update", required);
+                false
+            }
+        }
+        Err(e) => {
+            error!("Failed to list network interfaces: {}. Skipping DDNS update", e);
+            false
         }
     }
+}
 
-    #[tokio::test]
-    async fn test_get_local_ipv6_address_with_specific_interface() {
-        // 首先获取系统中存在的网络接口列表
-        let interfaces = if_addrs::get_if_addrs().unwrap_or_default();
-        let mut found_ipv6 = false;
-
-        // 遍历接口，查找一个有 IPv6 地址的接口进行测试
-        for iface in interfaces {
-            println!("{}", iface.name);
-            if iface.name != "en0" {
-                continue; // 跳过 loopback 接口
+// CI 服务设置的环境变量，任一存在即认为运行在 CI 中
+const CI_ENV_VARS: &[&str] = &["CI", "GITHUB_ACTIONS", "GITLAB_CI", "BUILDKITE", "JENKINS_URL", "TF_BUILD"];
+
+// 在 CI 中误运行会覆盖生产记录，未设置 allow_ci 时只演练不更新
+fn ci_dry_run(config: &Config, lookup: &dyn Fn(&str) -> Option<String>) -> bool {
+    if config.allow_ci {
+        return false;
+    }
+    CI_ENV_VARS
+        .iter()
+        .any(|name| lookup(name).is_some_and(|value| !matches!(value.as_str(), "" | "0" | "false")))
+}
+
+// 更新DDNS的主函数，成功时返回本次更新的结果
+// local_providers 与公网提供商同时更新，失败只记录警告，不影响本次运行的结果
+// last_ip 为上次成功发布的地址，用于判断地址是否变化；fail_fast 时第一个提供商失败就放弃其余更新
+async fn update_ddns(
+    config: &Config,
+    client: &Client,
+    cache: &LocalCache,
+    providers: &[Box<dyn Provider>],
+    local_providers: &[Box<dyn Provider>],
+    last_ip: Option<IpAddr>,
+    fail_fast: bool,
+) -> Result<UpdateOutcome, BoxError> {
+    debug!("Starting DDNS update process");
+    
+    // 获取IPv6地址
+    let started = Instant::now();
+    let detected = detect_with_retries(config, client, cache).await?;
+    // 统一为标准压缩格式后再比较和发布，避免不同写法被误判为地址变化
+    let mut ip = normalize_ipv6(&detected)?;
+    if config.publish_stable_only {
+        let stable = local::stable_ipv6_for(ip)?;
+        if stable != ip {
+            debug!("{} is a temporary address, publishing stable address {} instead", ip, stable);
+            ip = stable;
+        }
+    }
+    // 不在允许的前缀内或在拒绝的前缀内时不发布，可能检测到了其他接口（如 VPN）的地址
+    cidr::check(
+        ip,
+        &cidr::parse_list(&config.allowed_ipv6_cidrs)?,
+        &cidr::parse_list(&config.denied_ipv6_cidrs)?,
+    )?;
+    let ipv6 = ip.to_string();
+    debug!("Current IPv6 address: {} (detected in {:?})", ipv6, started.elapsed());
+
+    // 地址变化后等待一段时间再检测一次，变回原地址时取消本次发布
+    if let Some(IpAddr::V6(last)) = last_ip
+        && last != ip
+        && config.change_confirm_secs > 0
+        && !confirm_change(config, client, cache, last, ip).await?
+    {
+        return Ok(UpdateOutcome {
+            changed: false,
+            ip: last_ip,
+            provider_response: format!("change to {} reverted during confirmation", ipv6),
+            providers: Vec::new(),
+        });
+    }
+
+    // 新地址需要先由上游系统（路由、防火墙等）处理时，等待一段时间再发布
+    if config.pre_update_delay_secs > 0 && last_ip != Some(IpAddr::V6(ip)) {
+        info!("Waiting {}s before publishing {}", config.pre_update_delay_secs, ipv6);
+        tokio::time::sleep(Duration::from_secs(config.pre_update_delay_secs)).await;
+    }
+
+    // 发布前确认新地址确实可用，失败时本次不更新，下次触发时重试
+    if let Some(target) = config.connectivity_check.as_deref() {
+        connectivity::check(target, ip).await?;
+    }
+    
+    // 调用各提供商的更新接口，通过信号量限制同时进行的请求数量，避免触发限流
+    let semaphore = Semaphore::new(config.max_concurrent_updates.max(1));
+    let updates: Vec<_> = providers.iter().map(|provider| {
+        let semaphore = &semaphore;
+        let ipv6 = &ipv6;
+        let span = providers::log_span(provider.as_ref());
+        async move {
+            let _permit = semaphore.acquire().await?;
+            if config.verify_via_dns && dns_record_up_to_date(config, provider.as_ref(), ip).await {
+                return Ok(ProviderUpdate {
+                    changed: false,
+                    response: "DNS record already up to date".to_string(),
+                });
             }
-            if let std::net::IpAddr::V6(_) = iface.ip() {
-                // 找到一个有 IPv6 地址的接口，用它进行测试
-                let result = get_local_ipv6_address(Some(&iface.name)).await;
-                match result {
-                    Ok(ip) => {
-                        println!("IPv6 address from interface '{}': {}", iface.name, ip);
-                        assert!(!ip.is_empty());
-                        assert!(ip.contains(":")); // IPv6 地址应该包含冒号
-                        found_ipv6 = true;
-                    }
-                    Err(e) => {
-                        println!("Failed to get IPv6 from interface '{}': {}", iface.name, e);
-                    }
+            debug!("Updating provider '{}'", provider.name());
+            let started = Instant::now();
+            let result = update_with_retries(config, provider.as_ref(), client, ipv6).await;
+            debug!("Provider '{}' update took {:?}", provider.name(), started.elapsed());
+            result
+        }
+        .instrument(span)
+    }).collect();
+    let failover = config.provider_mode == "failover";
+    let public_updates = async {
+        if failover {
+            // 按顺序逐个尝试，第一个成功后停止，其余提供商不更新
+            let mut results = Vec::new();
+            for update in updates {
+                let result = update.await;
+                let succeeded = result.is_ok();
+                results.push(result);
+                if succeeded {
+                    break;
                 }
             }
+            return Ok(results);
         }
+        if !fail_fast {
+            return Ok(join_all(updates).await);
+        }
+        // 任一提供商失败时立即返回，丢弃仍在进行和尚未开始的更新
+        try_join_all(providers.iter().zip(updates).map(|(provider, update)| async move {
+            update
+                .await
+                .map(Ok)
+                .map_err(|e| format!("Provider '{}' failed, remaining updates cancelled: {}", provider.name(), e))
+        }))
+        .await
+    };
+    let local_updates = join_all(
+        local_providers
+            .iter()
+            .map(|provider| provider.update(client, &ipv6).instrument(providers::log_span(provider.as_ref()))),
+    );
+    let (results, local_results) = futures::try_join!(public_updates, async { Ok(local_updates.await) })?;
 
-        // 如果没有找到任何有 IPv6 的接口，则测试指定不存在接口的情况
-        if !found_ipv6 {
-            let result = get_local_ipv6_address(Some("nonexistent_interface")).await;
-            match result {
-                Ok(ip) => {
-                    // 意外找到了 IP，也认为测试通过
-                    println!("Unexpectedly found IPv6 address: {}", ip);
-                }
-                Err(e) => {
-                    // 这是预期的结果
-                    println!("Expected error for interface without IPv6: {}", e);
+    for (provider, result) in local_providers.iter().zip(local_results) {
+        match result {
+            Ok(update) if update.changed => info!("Local provider '{}' record changed to {}", provider.name(), ipv6),
+            Ok(_) => debug!("Local provider '{}' already up to date", provider.name()),
+            Err(e) => warn!("Local provider '{}' update failed: {}", provider.name(), e),
+        }
+    }
+
+    let mut changed = false;
+    let mut responses = Vec::new();
+    let mut provider_results = Vec::new();
+    let attempted = results.len();
+    for (provider, result) in providers.iter().zip(results) {
+        let (status, detail) = match result {
+            Ok(update) => {
+                if update.changed {
+                    info!("Provider '{}' record changed to {}", provider.name(), ipv6);
                 }
+                changed |= update.changed;
+                let response = update.response.replace('\n', " ");
+                responses.push(format!("{}: {}", provider.name(), response));
+                (if update.changed { "updated" } else { "unchanged" }, response)
             }
-        }
+            Err(e) => ("failed", e.to_string()),
+        };
+        provider_results.push(ProviderResult {
+            provider: provider.name().to_string(),
+            status,
+            detail,
+        });
+    }
+    for provider in &providers[attempted..] {
+        provider_results.push(ProviderResult {
+            provider: provider.name().to_string(),
+            status: "skipped",
+            detail: "an earlier provider succeeded".to_string(),
+        });
     }
 
-    #[tokio::test]
-    async fn test_get_local_ipv6_address_auto_discovery() {
-        // 测试自动发现功能（不指定接口）
-        let result = get_local_ipv6_address(None).await;
-        match result {
-            Ok(ip) => {
-                println!("Auto-discovered IPv6 address: {}", ip);
-                assert!(!ip.is_empty());
-                assert!(ip.contains(":"));
+    let outcome = UpdateOutcome {
+        changed,
+        ip: Some(IpAddr::V6(ip)),
+        provider_response: responses.join("\n"),
+        providers: provider_results,
+    };
+    // failover 模式下只要有一个提供商成功就算成功，并报告由哪个提供商发布
+    let failed = if failover {
+        match outcome.providers.iter().find(|result| matches!(result.status, "updated" | "unchanged")) {
+            Some(authoritative) if attempted > 1 => {
+                warn!("Primary provider failed, published via backup provider '{}'", authoritative.provider);
+                false
             }
-            Err(e) => {
-                // 在某些环境中可能没有可用的 IPv6 地址，这是可以接受的
-                println!("No IPv6 address found in auto-discovery (may be expected): {}", e);
+            Some(authoritative) => {
+                debug!("Published via primary provider '{}'", authoritative.provider);
+                false
             }
+            None => true,
         }
+    } else {
+        outcome.providers.iter().any(|result| result.status == "failed")
+    };
+    if failed {
+        return Err(Box::new(PartialFailure(outcome)));
     }
-    
-    
-}
\ No newline at end of file
+
+    // 说明本次发布了哪些记录类型；目前只检测和发布IPv6，A记录总是跳过
+    let summary = format!(
+        "published AAAA={} A=(skipped) to {} provider(s): {}",
+        ipv6,
+        providers.len(),
+        outcome.summary()
+    );
+    if changed || !config.quiet_success {
+        info!("{}", summary);
+    } else {
+        debug!("{}", summary);
+    }
+
+    Ok(outcome)
+}
+
+// 等待 change_confirm_secs 后重新检测：仍是新地址时返回 true，变回原地址时返回 false，
+// 又变成其他地址时报错，下次触发时重新开始确认
+async fn confirm_change(
+    config: &Config,
+    client: &Client,
+    cache: &LocalCache,
+    last: Ipv6Addr,
+    ip: Ipv6Addr,
+) -> Result<bool, BoxError> {
+    info!(
+        "IPv6 address changed from {} to {}, confirming in {}s before publishing",
+        last, ip, config.change_confirm_secs
+    );
+    tokio::time::sleep(Duration::from_secs(config.change_confirm_secs)).await;
+
+    // 丢弃本地缓存，确保确认时重新读取接口地址
+    cache.invalidate();
+    let confirmed = normalize_ipv6(&get_ipv6_address(config, client, cache).await?)?;
+    if confirmed == ip {
+        debug!("IPv6 address change to {} confirmed", ip);
+        Ok(true)
+    } else if confirmed == last {
+        info!("IPv6 address reverted to {} during confirmation, not publishing {}", last, ip);
+        Ok(false)
+    } else {
+        Err(format!("IPv6 address is still changing ({} then {}), not publishing", ip, confirmed).into())
+    }
+}
+
+// 解析检测到的地址，去掉首尾空白、/64 这样的前缀长度（如 ip addr 的输出）和 %eth0 这样的区域标识；
+// 不是合法的IPv6地址或前缀长度超过 128 时报错
+fn normalize_ipv6(raw: &str) -> Result<Ipv6Addr, BoxError> {
+    let trimmed = raw.trim();
+    let address = match trimmed.split_once('/') {
+        Some((address, prefix_len)) if prefix_len.parse::<u8>().is_ok_and(|len| len <= 128) => address,
+        Some(_) => return Err(format!("Detected address has an invalid prefix length: {}", trimmed).into()),
+        None => trimmed,
+    };
+    let address = address.split_once('%').map(|(address, _)| address).unwrap_or(address);
+    address
+        .parse()
+        .map_err(|_| format!("Detected address is not a valid IPv6 address: {}", trimmed).into())
+}
+
+// 通过DNS查询提供商当前发布的地址，已经是 ipv6 时跳过更新；查询失败时照常更新
+async fn dns_record_up_to_date(config: &Config, provider: &dyn Provider, ip: Ipv6Addr) -> bool {
+    let Some(hostname) = provider.hostname() else {
+        return false;
+    };
+
+    match dns::resolve_aaaa(&hostname, config.dns_resolver.as_deref()).await {
+        Ok(addrs) if addrs.contains(&ip) => {
+            debug!("{} already resolves to {}, skipping provider '{}'", hostname, ip, provider.name());
+            true
+        }
+        Ok(addrs) => {
+            debug!("{} resolves to {:?}, updating provider '{}'", hostname, addrs, provider.name());
+            false
+        }
+        Err(e) => {
+            warn!("DNS lookup of {} failed, updating provider '{}' anyway: {}", hostname, provider.name(), e);
+            false
+        }
+    }
+}
+
+// 获取IPv6地址
+async fn get_ipv6_address(config: &Config, client: &Client, cache: &LocalCache) -> Result<String, BoxError> {
+    match config.ipv6_method.as_str() {
+        "external" => {
+            // 通过外部服务获取IPv6地址
+            get_ipv6_from_external_services(config, client).await
+        },
+        "local" => {
+            // 直接获取本地IPv6地址
+            get_local_ipv6(config, cache).await
+        },
+        "external_then_local" => {
+            // 优先使用外部服务，失败时回退到本地接口
+            with_fallback(get_ipv6_from_external_services(config, client).await, "external service", get_local_ipv6(config, cache)).await
+        },
+        "local_then_external" => {
+            // 优先使用本地接口，失败时回退到外部服务
+            with_fallback(get_local_ipv6(config, cache).await, "local interface", get_ipv6_from_external_services(config, client)).await
+        },
+        "shell" => {
+            // 通过执行shell命令获取IPv6地址
+            get_ipv6_from_custom_shell(config).await
+        },
+        "upnp" => {
+            // 通过 UPnP IGD 向路由器查询外部IPv6地址
+            upnp::get_ipv6_from_upnp(client).await
+        },
+        "route" => {
+            // 取内核访问 route_target 时选用的源地址
+            for_each_interface(config, |name| async move {
+                local::get_route_ipv6_address(config.route_target.as_deref(), name.as_deref()).await
+            })
+            .await
+        },
+        "env" => {
+            // 读取编排平台注入的环境变量，不做任何检测
+            get_ipv6_from_env(config, &|name| std::env::var(name).ok())
+        },
+        _ => {
+            error!("Invalid IPV6_METHOD: {}. Using external service.", config.ipv6_method);
+            get_ipv6_from_external_services(config, client).await
+        }
+    }
+}
+
+async fn get_local_ipv6(config: &Config, cache: &LocalCache) -> Result<String, BoxError> {
+    for_each_interface(config, |name| async move {
+        local::get_local_ipv6_address_cached(
+            cache,
+            name.as_deref(),
+            &local::InterfaceFilter {
+                include: &config.include_interfaces,
+                exclude: &config.exclude_interfaces,
+            },
+            &config.interface_priority,
+            Duration::from_secs(config.local_cache_secs),
+            Duration::from_millis(config.local_confirm_ms),
+            config.allow_non_global_ipv6,
+        )
+        .await
+    })
+    .await
+}
+
+// hosts_interface 可以是逗号分隔的有序列表，env:NAME 表示取环境变量 NAME 的值，未设置或为空时跳过该项
+// 用于各主机WAN接口名不同的集群：例如 "env:WAN_INTERFACE,eth0,ens3"
+fn parse_interface_list(spec: &str, lookup: EnvLookup) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let name = match entry.strip_prefix("env:") {
+            Some(var) => match lookup(var.trim()).map(|value| value.trim().to_string()) {
+                Some(value) if !value.is_empty() => value,
+                _ => {
+                    debug!("Skipping interface entry '{}': environment variable is not set", entry);
+                    continue;
+                }
+            },
+            None => entry.to_string(),
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn no_interface_resolved() -> String {
+    "HOSTS_INTERFACE did not resolve to any interface (all referenced environment variables are unset)".to_string()
+}
+
+// 按 hosts_interface 中的顺序依次尝试各接口，第一个得到地址的接口胜出；未设置时自动查找
+// 只有一个接口时原样返回它的错误，多个接口都失败时汇总每个接口的错误
+async fn for_each_interface<F, Fut>(config: &Config, detect: F) -> Result<String, BoxError>
+where
+    F: Fn(Option<String>) -> Fut,
+    Fut: std::future::Future<Output = Result<String, BoxError>>,
+{
+    let Some(names) = config.hosts_interfaces() else {
+        return detect(None).await;
+    };
+    match names.as_slice() {
+        [] => return Err(no_interface_resolved().into()),
+        [name] => return detect(Some(name.clone())).await,
+        _ => {}
+    }
+
+    let mut errors = Vec::new();
+    for name in names {
+        match detect(Some(name.clone())).await {
+            Ok(ip) => {
+                if !errors.is_empty() {
+                    debug!("Using interface '{}' after earlier candidates failed: {}", name, errors.join("; "));
+                }
+                return Ok(ip);
+            }
+            Err(e) => errors.push(format!("{}: {}", name, e)),
+        }
+    }
+    Err(format!("No interface in HOSTS_INTERFACE yielded an IPv6 address ({})", errors.join("; ")).into())
+}
+
+// env 方式：从 ipv6_env_var 指定的环境变量读取地址，每次运行时重新读取
+fn get_ipv6_from_env(config: &Config, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, BoxError> {
+    let name = config.ipv6_env_var.as_deref().ok_or("IPV6_ENV_VAR must be set for IPv6 method 'env'")?;
+    let value = lookup(name)
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| format!("Environment variable '{}' is not set or empty", name))?;
+    normalize_ipv6(&value).map_err(|e| format!("Invalid address in environment variable '{}': {}", name, e))?;
+    debug!("Got IPv6 address from environment variable '{}': {}", name, value.trim());
+    Ok(value.trim().to_string())
+}
+
+// 首选方式失败或返回的不是有效IPv6地址时，改用备选方式；两者都失败时报告两个错误
+async fn with_fallback(
+    primary: Result<String, BoxError>,
+    primary_name: &str,
+    fallback: impl std::future::Future<Output = Result<String, BoxError>>,
+) -> Result<String, BoxError> {
+    let primary_error = match primary.and_then(|raw| normalize_ipv6(&raw).map(|_| raw)) {
+        Ok(raw) => return Ok(raw),
+        Err(e) => e,
+    };
+    warn!("IPv6 detection via {} failed, trying fallback: {}", primary_name, primary_error);
+    fallback
+        .await
+        .map_err(|e| format!("IPv6 detection via {} failed: {}; fallback failed: {}", primary_name, primary_error, e).into())
+}
+
+// 配置了 ip_service_urls 时使用该列表，否则只使用 ip_service_url
+// ip_service_quorum 大于 0 时并发查询所有服务并投票，否则按顺序尝试，直到有一个成功
+async fn get_ipv6_from_external_services(config: &Config, client: &Client) -> Result<String, BoxError> {
+    let urls: Vec<&str> = if config.ip_service_urls.is_empty() {
+        vec![config.ip_service_url.as_str()]
+    } else {
+        config.ip_service_urls.iter().map(String::as_str).collect()
+    };
+
+    let family_client = ip_service_client(config, client, &urls).await?;
+    let client = &family_client;
+    let timeout = config.ip_lookup_timeout();
+    if config.ip_service_quorum > 0 {
+        return get_ipv6_by_quorum(client, &urls, config.ip_service_quorum, config.max_response_bytes, timeout).await;
+    }
+
+    let mut errors = Vec::new();
+    let base_delay = Duration::from_millis(config.ip_service_retry_delay_ms);
+    for (attempt, url) in urls.iter().enumerate() {
+        // 网络故障时不要连续快速地请求所有服务
+        if attempt > 0 && !base_delay.is_zero() {
+            let delay = retry_delay(base_delay, attempt as u32, jitter_fraction(), MAX_IP_SERVICE_RETRY_DELAY);
+            debug!("Waiting {:?} before trying the next IP service", delay);
+            tokio::time::sleep(delay).await;
+        }
+        match get_ipv6_from_external_service(client, url, config.max_response_bytes, timeout).await {
+            Ok(ip) => return Ok(ip),
+            Err(e) => {
+                debug!("IP service {} failed: {}", url, e);
+                errors.push(format!("{}: {}", url, e));
+            }
+        }
+    }
+    Err(format!("All {} IP services failed: {}", urls.len(), errors.join("; ")).into())
+}
+
+// 第 attempt 次重试前的等待时间：base 按指数增长，不超过 max，再随机取其 50%~100%，避免多个实例同时重试
+fn retry_delay(base: Duration, attempt: u32, jitter: f64, max: Duration) -> Duration {
+    let exponential = base.saturating_mul(1u32 << attempt.saturating_sub(1).min(16));
+    exponential.min(max).mul_f64(0.5 + jitter.clamp(0.0, 1.0) * 0.5)
+}
+
+// 检测失败时按 ip_lookup_retries 重新执行整个检测，等待时间按 ip_service_retry_delay_ms 计算
+async fn detect_with_retries(config: &Config, client: &Client, cache: &LocalCache) -> Result<String, BoxError> {
+    let base_delay = Duration::from_millis(config.ip_service_retry_delay_ms);
+    let mut attempt = 0;
+    loop {
+        match get_ipv6_address(config, client, cache).await {
+            Err(e) if attempt < config.ip_lookup_retries => {
+                attempt += 1;
+                let delay = retry_delay(base_delay, attempt, jitter_fraction(), MAX_IP_SERVICE_RETRY_DELAY);
+                warn!("IPv6 detection failed, retrying in {:?} ({}/{}): {}", delay, attempt, config.ip_lookup_retries, e);
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+// 提供商更新失败时按 provider_retries 重试，与检测的重试分开设置，提供商通常有更严格的限流；
+// 只重试网络错误和暂时性的失败（5xx、429 等），认证失败等永久性的错误立即返回
+async fn update_with_retries(config: &Config, provider: &dyn Provider, client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+    let base_delay = Duration::from_millis(config.provider_retry_delay_ms);
+    let mut attempt = 0;
+    loop {
+        match provider.update(client, ipv6).await {
+            Err(e) if attempt < config.provider_retries && providers::is_retryable(&e) => {
+                attempt += 1;
+                let delay = retry_delay(base_delay, attempt, jitter_fraction(), MAX_PROVIDER_RETRY_DELAY);
+                warn!(
+                    "Provider '{}' update failed, retrying in {:?} ({}/{}): {}",
+                    provider.name(),
+                    delay,
+                    attempt,
+                    config.provider_retries,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+// [0, 1) 之间的随机数，只用于抖动，不需要密码学强度
+fn jitter_fraction() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos());
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// ip_service_family 为 ipv6 或 ipv4 时，预先解析各IP服务的主机名，只保留该协议族的地址并固定到新的客户端中，
+// 双栈服务因此一定通过指定的协议族连接；auto 时沿用原客户端。没有该协议族地址的服务请求时会失败
+async fn ip_service_client(config: &Config, client: &Client, urls: &[&str]) -> Result<Client, BoxError> {
+    let want_ipv6 = match config.ip_service_family.as_str() {
+        "auto" => return Ok(client.clone()),
+        "ipv6" => true,
+        "ipv4" => false,
+        other => return Err(format!("Invalid IP_SERVICE_FAMILY: {}. Expected auto, ipv6 or ipv4", other).into()),
+    };
+
+    let mut builder = http_client_builder(config)?;
+    for url in urls {
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid IP service URL '{}': {}", url, e))?;
+        // URL 中直接写的IP地址不经过解析
+        let Some(host) = parsed.domain() else {
+            continue;
+        };
+        let port = parsed.port_or_known_default().unwrap_or(80);
+        let addrs: Vec<std::net::SocketAddr> = match tokio::net::lookup_host((host, port)).await {
+            Ok(addrs) => addrs.filter(|addr| addr.is_ipv6() == want_ipv6).collect(),
+            Err(e) => {
+                debug!("Failed to resolve IP service host '{}': {}", host, e);
+                Vec::new()
+            }
+        };
+        if addrs.is_empty() {
+            warn!("IP service host '{}' has no {} address, requests to it will fail", host, config.ip_service_family);
+        }
+        builder = builder.resolve_to_addrs(host, &addrs);
+    }
+    Ok(builder.build()?)
+}
+
+// 并发查询所有服务，至少 quorum 个服务返回同一地址、且该地址得到超过半数有效回答时才接受，防止单个服务返回错误的地址；
+// 票数最多的地址不唯一时无法判断哪个正确，同样不接受
+async fn get_ipv6_by_quorum(
+    client: &Client,
+    urls: &[&str],
+    quorum: usize,
+    max_bytes: usize,
+    timeout: Option<Duration>,
+) -> Result<String, BoxError> {
+    if quorum > urls.len() {
+        return Err(format!("IP_SERVICE_QUORUM is {} but only {} IP services are configured", quorum, urls.len()).into());
+    }
+
+    let results = join_all(urls.iter().map(|url| get_ipv6_from_external_service(client, url, max_bytes, timeout))).await;
+
+    // 按地址值计票，忽略压缩写法等格式差异
+    let mut votes: Vec<(Ipv6Addr, usize)> = Vec::new();
+    let mut answered = 0;
+    for (url, result) in urls.iter().zip(results) {
+        match result.map(|ip| ip.trim().parse::<Ipv6Addr>()) {
+            Ok(Ok(ip)) => {
+                answered += 1;
+                match votes.iter_mut().find(|(voted, _)| *voted == ip) {
+                    Some((_, count)) => *count += 1,
+                    None => votes.push((ip, 1)),
+                }
+            }
+            Ok(Err(_)) => warn!("IP service {} returned an invalid IPv6 address", url),
+            Err(e) => warn!("IP service {} failed: {}", url, e),
+        }
+    }
+
+    let Some((ip, count)) = votes.iter().max_by_key(|(_, count)| *count).copied() else {
+        return Err(format!("None of the {} IP services returned an IPv6 address", urls.len()).into());
+    };
+    if count < quorum {
+        return Err(format!(
+            "No quorum: at most {} of {} IP services agreed ({}), {} required; answers: {:?}",
+            count,
+            urls.len(),
+            ip,
+            quorum,
+            votes
+        )
+        .into());
+    }
+    if votes.iter().filter(|(_, other)| *other == count).count() > 1 {
+        return Err(format!("No quorum: several addresses got {} votes each; answers: {:?}", count, votes).into());
+    }
+    if count * 2 <= answered {
+        return Err(format!(
+            "No quorum: {} got {} of {} valid answers, a majority is required; answers: {:?}",
+            ip, count, answered, votes
+        )
+        .into());
+    }
+
+    debug!("{} of {} IP services agreed on {}", count, urls.len(), ip);
+    Ok(ip.to_string())
+}
+
+// 通过外部服务获取IPv6地址
+// timeout 为 None 时沿用客户端的全局超时
+async fn get_ipv6_from_external_service(
+    client: &Client,
+    url: &str,
+    max_bytes: usize,
+    timeout: Option<Duration>,
+) -> Result<String, BoxError> {
+    debug!("Fetching IPv6 from external service: {}", url);
+    
+    let mut request = client.get(url);
+    if let Some(timeout) = timeout {
+        request = request.timeout(timeout);
+    }
+    let response = request.send().await?;
+    let body = read_body_limited(response, max_bytes).await?;
+    let ip = clean_echo_response(&body).to_string();
+    
+    debug!("Got IPv6 from external service: {}", ip);
+    Ok(ip)
+}
+
+// 去掉外部服务响应中地址以外的内容：首尾空白、开头的 BOM 以及包裹地址的双引号（部分服务返回JSON字符串）
+fn clean_echo_response(body: &str) -> &str {
+    let body = body.trim().trim_start_matches('\u{feff}').trim();
+    body.strip_prefix('"')
+        .and_then(|inner| inner.strip_suffix('"'))
+        .map(str::trim)
+        .unwrap_or(body)
+}
+
+// 限制大小地读取响应体，防止异常服务或中间代理返回超大响应耗尽内存
+pub(crate) async fn read_body_limited(mut response: reqwest::Response, max_bytes: usize) -> Result<String, BoxError> {
+    if let Some(len) = response.content_length()
+        && len > max_bytes as u64
+    {
+        return Err(format!("Response body too large: {} bytes (limit {})", len, max_bytes).into());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(format!("Response body exceeds limit of {} bytes", max_bytes).into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(String::from_utf8(body)?)
+}
+
+// 通过执行自定义shell命令获取IPv6地址
+async fn get_ipv6_from_custom_shell(config: &Config) -> Result<String, BoxError> {
+    // 仅支持Linux和macOS
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    return Err("Shell command method only supported on Linux and macOS".into());
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        // 检查是否配置了shell命令
+        let command_parts = config.shell_command.as_ref().ok_or("No shell command configured for IPv6 method 'shell'")?;
+
+        if command_parts.is_empty() {
+            return Err("Shell command is empty".into());
+        }
+
+        debug!("Fetching IPv6 address by executing custom shell command: {:?}", command_parts);
+
+        // 提取命令和参数
+        let command = &command_parts[0];
+        let args = &command_parts[1..];
+
+        debug!("Executing command: {} {:?}", command, args);
+
+        let output = tokio::process::Command::new(command)
+            .args(args.iter().map(<std::string::String as AsRef<OsStr>>::as_ref))
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            error!("Shell command failed - stderr: {}, stdout: {}", stderr, stdout);
+            return Err(format!("Shell command failed: {}", String::from_utf8_lossy(&output.stderr)).into());
+        }
+
+        let ipv6 = String::from_utf8(output.stdout)?.trim().to_string();
+        if ipv6.is_empty() {
+            return Err("Shell command returned empty output".into());
+        }
+
+        // 验证IPv6地址格式
+        if !ipv6.contains(":") {
+            return Err(format!("Shell command returned invalid IPv6 address: {}", ipv6).into());
+        }
+
+        debug!("Got IPv6 from shell command: {}", ipv6);
+        Ok(ipv6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) fn test_config() -> Config {
+        Config {
+            cron: DEFAULT_CRON.to_string(),
+            ipv6_method: "external".to_string(),
+            ip_service_url: "https://6.ipw.cn".to_string(),
+            providers: vec!["duckdns".to_string()],
+            duckdns_domain: Some("example".to_string()),
+            duckdns_token: Some("token".to_string()),
+            dyndns2_server: None,
+            dyndns2_username: None,
+            dyndns2_password: None,
+            dyndns2_hostname: None,
+            cloudflare_api_token: None,
+            cloudflare_zone_id: None,
+            cloudflare_record_name: None,
+            cloudflare_record_type: None,
+            cloudflare_proxied: None,
+            hosts_interface: None,
+            shell_command: None,
+            required_interface: None,
+            status_file: None,
+            max_concurrent_updates: DEFAULT_MAX_CONCURRENT_UPDATES,
+            quiet_success: false,
+            tls_min_version: None,
+            extra_ca_cert: None,
+            single_threaded: false,
+            log_response_headers: false,
+            max_backoff_secs: DEFAULT_MAX_BACKOFF_SECS,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            nochange_cooldown_secs: 0,
+            timezone: None,
+            error_log_window_secs: 0,
+            verify_via_dns: false,
+            dns_resolver: None,
+            mqtt_broker: None,
+            mqtt_topic: None,
+            mqtt_username: None,
+            mqtt_password: None,
+            post_update_command: None,
+            ip_service_urls: Vec::new(),
+            ip_service_quorum: 0,
+            http_version: None,
+            log_level: None,
+            update_url_template: None,
+            template_domain: None,
+            template_token: None,
+            template_success_match: None,
+            template_nochange_match: None,
+            interface_priority: Vec::new(),
+            pause_file: None,
+            startup_delay_secs: 0,
+            notify_webhook_url: None,
+            cron_auto_seconds: false,
+            route53_hosted_zone_id: None,
+            route53_record_name: None,
+            route53_ttl: None,
+            connectivity_check: None,
+            duckdns_base_url: None,
+            route_target: None,
+            shutdown_timeout_secs: DEFAULT_SHUTDOWN_TIMEOUT_SECS,
+            allow_ci: false,
+            http_timeout_secs: None,
+            ip_lookup_timeout_secs: None,
+            provider_timeout_secs: None,
+            local_cache_secs: 0,
+            pid_file: None,
+            event_driven: false,
+            record_comment: None,
+            ip_service_retry_delay_ms: DEFAULT_IP_SERVICE_RETRY_DELAY_MS,
+            log_file: None,
+            log_file_retention: logging::DEFAULT_LOG_FILE_RETENTION,
+            log_console: true,
+            inwx_user: None,
+            inwx_pass: None,
+            inwx_record_id: None,
+            local_confirm_ms: 0,
+            allow_non_global_ipv6: false,
+            schedule_file: None,
+            local_providers: Vec::new(),
+            hosts_file: None,
+            hosts_file_hostname: None,
+            domain_pattern: None,
+            include_interfaces: Vec::new(),
+            exclude_interfaces: Vec::new(),
+            metrics_file: None,
+            ipv6_env_var: None,
+            change_confirm_secs: 0,
+            publish_stable_only: false,
+            template_success_status: Vec::new(),
+            provider_mode: "all".to_string(),
+            post_update_verify_url: None,
+            skip_ipv6_startup_check: false,
+            porkbun_apikey: None,
+            porkbun_secretkey: None,
+            porkbun_domain: None,
+            porkbun_subdomain: None,
+            provider_proxy: None,
+            pre_update_delay_secs: 0,
+            ip_service_family: "auto".to_string(),
+            notify_on: "change_or_failure".to_string(),
+            allowed_ipv6_cidrs: Vec::new(),
+            denied_ipv6_cidrs: Vec::new(),
+            profile: None,
+            profiles: Vec::new(),
+            identity_probe_url: None,
+            identity_probe_expect: None,
+            ip_lookup_retries: 0,
+            provider_retries: 0,
+            provider_retry_delay_ms: DEFAULT_PROVIDER_RETRY_DELAY_MS,
+        }
+    }
+
+    // 启动一个只响应一次的本地HTTP服务，返回其地址和收到的请求内容
+    pub(crate) async fn serve_once(response: String) -> (String, tokio::task::JoinHandle<String>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 8192];
+            let len = stream.read(&mut buf).await.unwrap();
+            stream.write_all(response.as_bytes()).await.unwrap();
+            stream.shutdown().await.unwrap();
+            String::from_utf8_lossy(&buf[..len]).to_string()
+        });
+        (url, handle)
+    }
+
+    // 构造一个完整的HTTP响应
+    pub(crate) fn http_response(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    #[tokio::test]
+    async fn test_confirm_change() {
+        let client = Client::new();
+        let last: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let ip: Ipv6Addr = "2001:db8::2".parse().unwrap();
+
+        for (redetected, expected) in [("2001:db8::2", Some(true)), ("2001:db8::1", Some(false)), ("2001:db8::3", None)] {
+            let (url, _) = serve_once(http_response("200 OK", redetected)).await;
+            let config = Config {
+                ip_service_url: url,
+                ..test_config()
+            };
+            let result = confirm_change(&config, &client, &LocalCache::default(), last, ip).await;
+            assert_eq!(result.ok(), expected, "{}", redetected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ip_service_family() {
+        // 测试服务只监听IPv4，按主机名访问时只有指定 ipv4 才能连上
+        let (url, _) = serve_once(http_response("200 OK", "2001:db8::1")).await;
+        let url = url.replace("127.0.0.1", "localhost");
+        let config = Config {
+            ip_service_url: url.clone(),
+            ip_service_family: "ipv4".to_string(),
+            ..test_config()
+        };
+        assert_eq!(get_ipv6_from_external_services(&config, &Client::new()).await.unwrap(), "2001:db8::1");
+
+        let config = Config {
+            ip_service_family: "ipv6".to_string(),
+            ..config
+        };
+        assert!(get_ipv6_from_external_services(&config, &Client::new()).await.is_err());
+
+        let config = Config {
+            ip_service_family: "v6".to_string(),
+            ..config
+        };
+        let err = get_ipv6_from_external_services(&config, &Client::new()).await.unwrap_err();
+        assert!(err.to_string().starts_with("Invalid IP_SERVICE_FAMILY"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_external_service_response_size_limit() {
+        let client = Client::new();
+
+        let (url, _) = serve_once(http_response("200 OK", "2001:db8::1")).await;
+        let ip = get_ipv6_from_external_service(&client, &url, 1024, None).await.unwrap();
+        assert_eq!(ip, "2001:db8::1");
+
+        let (url, _) = serve_once(http_response("200 OK", &"a".repeat(2048))).await;
+        assert!(get_ipv6_from_external_service(&client, &url, 1024, None).await.is_err());
+    }
+
+    // 测试用提供商：延迟指定时间后成功，未指定延迟时失败
+    struct Stub(&'static str, Option<Duration>);
+
+    #[async_trait::async_trait]
+    impl Provider for Stub {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn update(&self, _client: &Client, _ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+            match self.1 {
+                Some(delay) => {
+                    tokio::time::sleep(delay).await;
+                    Ok(ProviderUpdate {
+                        changed: true,
+                        response: "OK".to_string(),
+                    })
+                }
+                None => Err("boom".into()),
+            }
+        }
+    }
+
+    // 前 failures 次更新失败，之后成功
+    struct Flaky(std::sync::atomic::AtomicU32, u32);
+
+    #[async_trait::async_trait]
+    impl Provider for Flaky {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        async fn update(&self, _client: &Client, _ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+            if self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < self.1 {
+                return Err(Box::new(providers::Transient("rate limited".to_string())));
+            }
+            Ok(ProviderUpdate {
+                changed: true,
+                response: "OK".to_string(),
+            })
+        }
+    }
+
+    // 每次都返回永久性的错误
+    struct Rejecting(std::sync::atomic::AtomicU32);
+
+    #[async_trait::async_trait]
+    impl Provider for Rejecting {
+        fn name(&self) -> &str {
+            "rejecting"
+        }
+
+        async fn update(&self, _client: &Client, _ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err("DynDNS2 authentication failed (badauth)".into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provider_retries() {
+        let config = Config {
+            provider_retries: 2,
+            provider_retry_delay_ms: 1,
+            ..test_config()
+        };
+        let provider = Flaky(Default::default(), 2);
+        assert!(update_with_retries(&config, &provider, &Client::new(), "2001:db8::1").await.is_ok());
+        assert_eq!(provider.0.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // 重试次数用完后返回最后一次的错误
+        let provider = Flaky(Default::default(), 3);
+        let result = update_with_retries(&config, &provider, &Client::new(), "2001:db8::1").await;
+        assert_eq!(result.err().map(|e| e.to_string()).as_deref(), Some("rate limited"));
+
+        // 检测的重试单独设置，不影响提供商
+        let config = Config {
+            ip_lookup_retries: 3,
+            ..test_config()
+        };
+        let provider = Flaky(Default::default(), 1);
+        assert!(update_with_retries(&config, &provider, &Client::new(), "2001:db8::1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_provider_retries_skip_permanent_errors() {
+        let config = Config {
+            provider_retries: 2,
+            provider_retry_delay_ms: 1,
+            ..test_config()
+        };
+        // 永久性的错误（如认证失败）只尝试一次
+        let provider = Rejecting(Default::default());
+        let result = update_with_retries(&config, &provider, &Client::new(), "2001:db8::1").await;
+        assert_eq!(result.err().map(|e| e.to_string()).as_deref(), Some("DynDNS2 authentication failed (badauth)"));
+        assert_eq!(provider.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        assert!(!providers::is_retryable(&"DynDNS2 authentication failed (badauth)".into()));
+        assert!(providers::is_retryable(&(Box::new(providers::Transient("HTTP 503".to_string())) as BoxError)));
+    }
+
+    #[tokio::test]
+    async fn test_update_ddns_fail_fast() {
+        let client = Client::new();
+        let providers: Vec<Box<dyn Provider>> = vec![
+            Box::new(Stub("slow", Some(Duration::from_millis(200)))),
+            Box::new(Stub("failing", None)),
+        ];
+
+        // 默认尝试所有提供商，失败的结果和成功的结果都保留
+        let (url, _) = serve_once(http_response("200 OK", "2001:db8::1")).await;
+        let config = Config {
+            ip_service_url: url,
+            ..test_config()
+        };
+        let err = update_ddns(&config, &client, &LocalCache::default(), &providers, &[], None, false).await.unwrap_err();
+        assert_eq!(outcome_of(&Err(err)).unwrap().providers.len(), 2);
+
+        // fail_fast 时不等待较慢的提供商
+        let (url, _) = serve_once(http_response("200 OK", "2001:db8::1")).await;
+        let config = Config {
+            ip_service_url: url,
+            ..test_config()
+        };
+        let started = Instant::now();
+        let err = update_ddns(&config, &client, &LocalCache::default(), &providers, &[], None, true).await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_millis(200));
+        assert!(err.to_string().contains("Provider 'failing' failed"), "{}", err);
+        assert!(outcome_of(&Err(err)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_ddns_failover() {
+        let client = Client::new();
+        let providers: Vec<Box<dyn Provider>> = vec![
+            Box::new(Stub("primary", None)),
+            Box::new(Stub("backup", Some(Duration::ZERO))),
+            Box::new(Stub("spare", Some(Duration::ZERO))),
+        ];
+        let (url, _) = serve_once(http_response("200 OK", "2001:db8::1")).await;
+        let config = Config {
+            ip_service_url: url,
+            provider_mode: "failover".to_string(),
+            ..test_config()
+        };
+
+        let outcome = update_ddns(&config, &client, &LocalCache::default(), &providers, &[], None, false).await.unwrap();
+        let statuses: Vec<&str> = outcome.providers.iter().map(|result| result.status).collect();
+        assert_eq!(statuses, ["failed", "updated", "skipped"]);
+        assert_eq!(outcome.summary(), "1 updated, 1 failed, 1 skipped (primary: boom)");
+
+        // 全部失败时才算失败
+        let providers: Vec<Box<dyn Provider>> = vec![Box::new(Stub("primary", None)), Box::new(Stub("backup", None))];
+        let (url, _) = serve_once(http_response("200 OK", "2001:db8::1")).await;
+        let config = Config {
+            ip_service_url: url,
+            ..config
+        };
+        assert!(update_ddns(&config, &client, &LocalCache::default(), &providers, &[], None, false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pre_update_delay_only_for_new_address() {
+        let client = Client::new();
+        let providers: Vec<Box<dyn Provider>> = vec![Box::new(Stub("stub", Some(Duration::ZERO)))];
+        let last: IpAddr = "2001:db8::1".parse().unwrap();
+
+        // 地址未变化时不等待
+        let (url, _) = serve_once(http_response("200 OK", "2001:db8::1")).await;
+        let config = Config {
+            ip_service_url: url,
+            pre_update_delay_secs: 1,
+            ..test_config()
+        };
+        let started = Instant::now();
+        update_ddns(&config, &client, &LocalCache::default(), &providers, &[], Some(last), false).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let (url, _) = serve_once(http_response("200 OK", "2001:db8::2")).await;
+        let config = Config {
+            ip_service_url: url,
+            ..config
+        };
+        let started = Instant::now();
+        update_ddns(&config, &client, &LocalCache::default(), &providers, &[], Some(last), false).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_update_rejects_address_outside_allowed_cidrs() {
+        let providers: Vec<Box<dyn Provider>> = vec![Box::new(Stub("stub", Some(Duration::ZERO)))];
+        let (url, _) = serve_once(http_response("200 OK", "fd7a:115c:a1e0::5")).await;
+        let config = Config {
+            ip_service_url: url,
+            allowed_ipv6_cidrs: vec!["2001:db8::/32".to_string()],
+            ..test_config()
+        };
+        let err = update_ddns(&config, &Client::new(), &LocalCache::default(), &providers, &[], None, false).await.unwrap_err();
+        assert!(err.to_string().contains("outside the allowed prefixes"), "{}", err);
+    }
+
+    #[test]
+    fn test_clean_echo_response() {
+        assert_eq!(clean_echo_response("\"2001:db8::1\"\n"), "2001:db8::1");
+        assert_eq!(clean_echo_response("\u{feff}2001:db8::1\r\n"), "2001:db8::1");
+        assert_eq!(clean_echo_response("\u{feff}\" 2001:db8::1 \""), "2001:db8::1");
+        assert_eq!(clean_echo_response("  2001:db8::1  "), "2001:db8::1");
+        // 不成对的引号原样保留，由后续解析报错
+        assert_eq!(clean_echo_response("\"2001:db8::1"), "\"2001:db8::1");
+    }
+
+    #[tokio::test]
+    async fn test_external_service_strips_quotes_and_bom() {
+        let (url, _) = serve_once(http_response("200 OK", "\u{feff}\"2001:db8::1\"\n")).await;
+        let ip = get_ipv6_from_external_service(&Client::new(), &url, 1024, None).await.unwrap();
+        assert_eq!(ip, "2001:db8::1");
+    }
+
+    #[tokio::test]
+    async fn test_external_services_fall_back_in_order() {
+        let (url, _) = serve_once(http_response("200 OK", "2001:db8::1")).await;
+        let config = Config {
+            ip_service_urls: vec!["http://127.0.0.1:1".to_string(), url],
+            ..test_config()
+        };
+        let ip = get_ipv6_from_external_services(&config, &Client::new()).await.unwrap();
+        assert_eq!(ip, "2001:db8::1");
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let base = Duration::from_millis(500);
+        let max = MAX_IP_SERVICE_RETRY_DELAY;
+        assert_eq!(retry_delay(base, 1, 1.0, max), base);
+        assert_eq!(retry_delay(base, 1, 0.0, max), base / 2);
+        assert_eq!(retry_delay(base, 3, 1.0, max), base * 4);
+        assert_eq!(retry_delay(base, 30, 1.0, max), MAX_IP_SERVICE_RETRY_DELAY);
+        assert_eq!(retry_delay(base, 30, 1.0, MAX_PROVIDER_RETRY_DELAY), MAX_PROVIDER_RETRY_DELAY);
+
+        let jitter = jitter_fraction();
+        assert!((0.0..1.0).contains(&jitter));
+    }
+
+    #[tokio::test]
+    async fn test_external_services_quorum() {
+        let client = Client::new();
+        let mut urls = Vec::new();
+        for body in ["2001:db8::1", "2001:0db8::0001", "2001:db8::bad"] {
+            urls.push(serve_once(http_response("200 OK", body)).await.0);
+        }
+        let urls: Vec<&str> = urls.iter().map(String::as_str).collect();
+        assert_eq!(get_ipv6_by_quorum(&client, &urls, 2, 1024, None).await.unwrap(), "2001:db8::1");
+
+        let mut urls = Vec::new();
+        for body in ["2001:db8::1", "2001:db8::2", "not an address"] {
+            urls.push(serve_once(http_response("200 OK", body)).await.0);
+        }
+        let urls: Vec<&str> = urls.iter().map(String::as_str).collect();
+        assert!(get_ipv6_by_quorum(&client, &urls, 2, 1024, None).await.is_err());
+        assert!(get_ipv6_by_quorum(&client, &urls, 4, 1024, None).await.is_err());
+
+        // 两个地址各得 2 票时无法判断哪个正确
+        let mut urls = Vec::new();
+        for body in ["2001:db8::1", "2001:db8::1", "2001:db8::2", "2001:db8::2"] {
+            urls.push(serve_once(http_response("200 OK", body)).await.0);
+        }
+        let urls: Vec<&str> = urls.iter().map(String::as_str).collect();
+        let err = get_ipv6_by_quorum(&client, &urls, 2, 1024, None).await.unwrap_err();
+        assert!(err.to_string().contains("several addresses"), "{}", err);
+
+        // quorum 为 1 时也需要超过半数的有效回答一致
+        let mut urls = Vec::new();
+        for body in ["2001:db8::1", "2001:db8::2", "2001:db8::3"] {
+            urls.push(serve_once(http_response("200 OK", body)).await.0);
+        }
+        let urls: Vec<&str> = urls.iter().map(String::as_str).collect();
+        assert!(get_ipv6_by_quorum(&client, &urls, 1, 1024, None).await.is_err());
+
+        // 无效回答不计入多数的分母
+        let mut urls = Vec::new();
+        for body in ["2001:db8::1", "not an address"] {
+            urls.push(serve_once(http_response("200 OK", body)).await.0);
+        }
+        let urls: Vec<&str> = urls.iter().map(String::as_str).collect();
+        assert_eq!(get_ipv6_by_quorum(&client, &urls, 1, 1024, None).await.unwrap(), "2001:db8::1");
+    }
+
+    #[cfg(feature = "embedded-config")]
+    #[test]
+    fn test_embedded_config_is_valid() {
+        Config::from_toml_str(EMBEDDED_CONFIG).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_with_fallback() {
+        let fallback = || async { Ok::<_, BoxError>("2001:db8::2".to_string()) };
+
+        let ip = with_fallback(Ok("2001:db8::1".to_string()), "primary", fallback()).await.unwrap();
+        assert_eq!(ip, "2001:db8::1");
+
+        let ip = with_fallback(Err("timeout".into()), "primary", fallback()).await.unwrap();
+        assert_eq!(ip, "2001:db8::2");
+
+        let ip = with_fallback(Ok("<html>".to_string()), "primary", fallback()).await.unwrap();
+        assert_eq!(ip, "2001:db8::2");
+
+        let err = with_fallback(Err("timeout".into()), "primary", async { Err::<String, BoxError>("no address".into()) })
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "IPv6 detection via primary failed: timeout; fallback failed: no address");
+    }
+
+    #[test]
+    fn test_read_config_file_reports_misconfigured_paths() {
+        let dir = std::env::temp_dir().join(format!("rs-refresh-ddns-config-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let err = read_config_file(&dir.to_string_lossy()).unwrap_err();
+        assert!(err.to_string().ends_with("is a directory, expected a file"), "{}", err);
+        std::fs::remove_dir(&dir).unwrap();
+
+        #[cfg(unix)]
+        {
+            let link = std::env::temp_dir().join(format!("rs-refresh-ddns-config-loop-{}", std::process::id()));
+            std::os::unix::fs::symlink(&link, &link).unwrap();
+            let err = read_config_file(&link.to_string_lossy()).unwrap_err();
+            assert!(err.to_string().ends_with("is a symlink loop, expected a file"), "{}", err);
+            std::fs::remove_file(&link).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_normalize_ipv6() {
+        let canonical = "2001:db8::1".parse::<Ipv6Addr>().unwrap();
+        for raw in [
+            "2001:db8::1",
+            "2001:0db8:0000:0000:0000:0000:0000:0001\n",
+            "  2001:DB8:0:0::1 ",
+            "2001:db8::1%eth0",
+            "2001:db8::1/64",
+            "2001:db8::1%eth0/64\n",
+        ] {
+            let ip = normalize_ipv6(raw).unwrap();
+            assert_eq!(ip, canonical);
+            assert_eq!(ip.to_string(), "2001:db8::1");
+        }
+        assert!(normalize_ipv6("1.2.3.4").is_err());
+        assert!(normalize_ipv6("not:an:address").is_err());
+        assert!(normalize_ipv6("2001:db8::1/129").is_err());
+        assert!(normalize_ipv6("2001:db8::1/").is_err());
+    }
+
+    #[test]
+    fn test_get_ipv6_from_env() {
+        let config = Config {
+            ipv6_env_var: Some("POD_IPV6".to_string()),
+            ..test_config()
+        };
+        let lookup = |value: &'static str| move |name: &str| (name == "POD_IPV6").then(|| value.to_string());
+
+        assert_eq!(get_ipv6_from_env(&config, &lookup(" 2001:db8::1\n")).unwrap(), "2001:db8::1");
+        assert!(get_ipv6_from_env(&config, &lookup("")).unwrap_err().to_string().contains("not set or empty"));
+        assert!(get_ipv6_from_env(&config, &lookup("10.0.0.1")).is_err());
+        assert!(get_ipv6_from_env(&test_config(), &lookup("2001:db8::1")).is_err());
+    }
+
+    #[test]
+    fn test_parse_interface_list() {
+        let lookup = |name: &str| match name {
+            "WAN_INTERFACE" => Some(" enp1s0 ".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        assert_eq!(parse_interface_list("eth0", &lookup), vec!["eth0"]);
+        assert_eq!(
+            parse_interface_list("env:WAN_INTERFACE, env:MISSING, env:EMPTY, eth0, enp1s0", &lookup),
+            vec!["enp1s0", "eth0"]
+        );
+        assert!(parse_interface_list("env:MISSING", &lookup).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_for_each_interface_falls_back() {
+        let detect = |name: Option<String>| async move {
+            match name.as_deref() {
+                Some("eth1") => Ok("2001:db8::1".to_string()),
+                Some(name) => Err(format!("no address on {}", name).into()),
+                None => Ok("auto".to_string()),
+            }
+        };
+
+        assert_eq!(for_each_interface(&test_config(), detect).await.unwrap(), "auto");
+
+        let config = Config {
+            hosts_interface: Some("eth0,eth1,eth2".to_string()),
+            ..test_config()
+        };
+        assert_eq!(for_each_interface(&config, detect).await.unwrap(), "2001:db8::1");
+
+        // 只有一个接口时原样返回它的错误
+        let config = Config {
+            hosts_interface: Some("eth0".to_string()),
+            ..test_config()
+        };
+        assert_eq!(for_each_interface(&config, detect).await.unwrap_err().to_string(), "no address on eth0");
+
+        let config = Config {
+            hosts_interface: Some("eth0,eth2".to_string()),
+            ..test_config()
+        };
+        let err = for_each_interface(&config, detect).await.unwrap_err().to_string();
+        assert!(err.contains("eth0: no address on eth0; eth2: no address on eth2"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_log_level() {
+        assert_eq!(parse_log_level(None).unwrap(), tracing::Level::INFO);
+        assert_eq!(parse_log_level(Some("debug")).unwrap(), tracing::Level::DEBUG);
+        assert_eq!(parse_log_level(Some("WARN")).unwrap(), tracing::Level::WARN);
+        assert!(parse_log_level(Some("loud")).is_err());
+    }
+
+    #[test]
+    fn test_should_run_without_required_interface() {
+        assert!(should_run(&test_config()));
+    }
+
+    #[test]
+    fn test_should_run_skips_when_interface_missing() {
+        let config = Config {
+            required_interface: Some("nonexistent_interface".to_string()),
+            ..test_config()
+        };
+        assert!(!should_run(&config));
+    }
+
+    #[test]
+    fn test_phase_timeouts_fall_back_to_global() {
+        assert_eq!(test_config().provider_timeout(), None);
+
+        let config = Config {
+            http_timeout_secs: Some(30),
+            ip_lookup_timeout_secs: Some(5),
+            ..test_config()
+        };
+        assert_eq!(config.ip_lookup_timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(config.provider_timeout(), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_accessors_reflect_loaded_config() {
+        let config = Config::from_toml_str(
+            "cron = \"0 */10 * * * *\"\ntimezone = \"Asia/Shanghai\"\nipv6_method = \"local\"\nhosts_interface = \"eth0\"\nproviders = [\"duckdns\", \"dyndns2\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.cron(), "0 */10 * * * *");
+        assert_eq!(config.timezone(), Some("Asia/Shanghai"));
+        assert_eq!(config.ipv6_method(), "local");
+        assert_eq!(config.hosts_interface(), Some("eth0"));
+        assert_eq!(config.providers(), ["duckdns", "dyndns2"]);
+    }
+
+    #[test]
+    fn test_ci_dry_run() {
+        let ci = |name: &str| (name == "CI").then(|| "true".to_string());
+        assert!(ci_dry_run(&test_config(), &ci));
+        assert!(!ci_dry_run(&test_config(), &|_| None));
+        assert!(!ci_dry_run(&test_config(), &|name| (name == "CI").then(|| "false".to_string())));
+
+        let config = Config {
+            allow_ci: true,
+            ..test_config()
+        };
+        assert!(!ci_dry_run(&config, &ci));
+    }
+
+    #[test]
+    fn test_expand_env_vars() {
+        let lookup = |name: &str| (name == "DUCKDNS_TOKEN").then(|| "secret".to_string());
+        assert_eq!(expand_env_vars("${DUCKDNS_TOKEN}", &lookup).unwrap(), "secret");
+        assert_eq!(expand_env_vars("a-${DUCKDNS_TOKEN}-b", &lookup).unwrap(), "a-secret-b");
+        assert_eq!(expand_env_vars("no vars", &lookup).unwrap(), "no vars");
+        assert!(expand_env_vars("${MISSING}", &lookup).is_err());
+        assert!(expand_env_vars("${DUCKDNS_TOKEN", &lookup).is_err());
+    }
+
+    #[test]
+    fn test_expand_env_in_value() {
+        let mut value: toml::Value = toml::from_str(
+            "duckdns_token = \"${DUCKDNS_TOKEN}\"\nshell_command = [\"sh\", \"${DUCKDNS_TOKEN}\"]\nmax_backoff_secs = 10",
+        )
+        .unwrap();
+        let lookup = |name: &str| (name == "DUCKDNS_TOKEN").then(|| "secret".to_string());
+        expand_env_in_value(&mut value, &lookup).unwrap();
+
+        let config: ConfigFile = value.try_into().unwrap();
+        assert_eq!(config.duckdns_token.as_deref(), Some("secret"));
+        assert_eq!(config.shell_command, Some(vec!["sh".to_string(), "secret".to_string()]));
+        assert_eq!(config.max_backoff_secs, Some(10));
+    }
+
+    #[test]
+    fn test_env_overrides_file_fields() {
+        let contents = "cron = \"0 */5 * * * *\"\nduckdns_token = \"from-file\"\nduckdns_domain = \"home\"\n";
+        let lookup = |name: &str| match name {
+            "DUCKDNS_TOKEN" => Some("from-env".to_string()),
+            "PROVIDERS" => Some("duckdns, dyndns2".to_string()),
+            "MAX_BACKOFF_SECS" => Some("60".to_string()),
+            "QUIET_SUCCESS" => Some("true".to_string()),
+            "TEMPLATE_SUCCESS_STATUS" => Some("200,302".to_string()),
+            _ => None,
+        };
+        let config = Config::from_toml(contents, Some(&lookup)).unwrap();
+        // 文件中的 cron 保留，令牌被环境变量覆盖
+        assert_eq!(config.cron, "0 */5 * * * *");
+        assert_eq!(config.duckdns_domain.as_deref(), Some("home"));
+        assert_eq!(config.duckdns_token.as_deref(), Some("from-env"));
+        assert_eq!(config.providers, vec!["duckdns", "dyndns2"]);
+        assert_eq!(config.max_backoff_secs, 60);
+        assert!(config.quiet_success);
+        assert_eq!(config.template_success_status, vec![200, 302]);
+
+        let lookup = |name: &str| (name == "MAX_BACKOFF_SECS").then(|| "soon".to_string());
+        let err = Config::from_toml(contents, Some(&lookup)).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid value for MAX_BACKOFF_SECS: 'soon', expected an integer");
+    }
+
+    #[test]
+    fn test_env_only_uses_same_validation() {
+        // 没有配置文件时与 from_env 一样从空表开始，错误的值不再被悄悄忽略
+        let lookup = |name: &str| (name == "MAX_RESPONSE_BYTES").then(|| "abc".to_string());
+        let err = Config::from_table(toml::Table::new(), Some(&lookup)).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid value for MAX_RESPONSE_BYTES: 'abc', expected an integer");
+
+        let lookup = |name: &str| (name == "LOG_CONSOLE").then(|| "no".to_string());
+        let err = Config::from_table(toml::Table::new(), Some(&lookup)).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid value for LOG_CONSOLE: 'no', expected true or false");
+
+        let lookup = |name: &str| match name {
+            "LOG_CONSOLE" => Some("false".to_string()),
+            "SHELL_COMMAND" => Some("curl -s https://ip.example".to_string()),
+            _ => None,
+        };
+        let config = Config::from_table(toml::Table::new(), Some(&lookup)).unwrap();
+        assert!(!config.log_console);
+        assert_eq!(config.shell_command, Some(vec!["sh".to_string(), "-c".to_string(), "curl -s https://ip.example".to_string()]));
+        assert_eq!(config.cron, Config::from_table(toml::Table::new(), None).unwrap().cron);
+    }
+
+    #[test]
+    fn test_profiles_inherit_top_level_fields() {
+        let contents = r#"
+cron = "0 */5 * * * *"
+duckdns_token = "shared"
+
+[profiles.home]
+ipv6_method = "local"
+duckdns_domain = "home"
+status_file = "/tmp/home.toml"
+
+[profiles.vps]
+providers = ["dyndns2"]
+cron = "0 0 * * * *"
+status_file = "/tmp/vps.toml"
+"#;
+        let config = Config::from_toml(contents, None).unwrap();
+        let profiles = config.select_profiles(None).unwrap();
+        assert_eq!(profiles.len(), 2);
+        let home = &profiles[0];
+        assert_eq!(home.profile(), Some("home"));
+        assert_eq!(home.ipv6_method, "local");
+        assert_eq!(home.cron, "0 */5 * * * *");
+        assert_eq!(home.duckdns_token.as_deref(), Some("shared"));
+        let vps = &config.select_profiles(Some("vps")).unwrap()[0];
+        assert_eq!(vps.cron, "0 0 * * * *");
+        assert_eq!(vps.providers, vec!["dyndns2"]);
+
+        let err = config.select_profiles(Some("office")).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown profile 'office', available: home, vps");
+        assert!(single_profile(profiles).is_err());
+
+        // 没有定义档案时就是配置本身
+        let plain = Config::from_toml("cron = \"0 */5 * * * *\"\n", None).unwrap();
+        assert_eq!(plain.select_profiles(None).unwrap()[0].profile(), None);
+        assert!(plain.select_profiles(Some("home")).is_err());
+
+        // 继承同一个状态文件时报错
+        let shared = "status_file = \"/tmp/status.toml\"\n[profiles.a]\n[profiles.b]\n";
+        let err = Config::from_toml(shared, None).unwrap_err();
+        assert!(err.to_string().contains("both use status_file"), "{}", err);
+    }
+
+    #[test]
+    fn test_build_http_client_rejects_bad_tls_settings() {
+        let config = Config {
+            tls_min_version: Some("1.4".to_string()),
+            ..test_config()
+        };
+        assert!(build_http_client(&config).is_err());
+
+        let config = Config {
+            extra_ca_cert: Some("/nonexistent/ca.pem".to_string()),
+            ..test_config()
+        };
+        assert!(build_http_client(&config).is_err());
+
+        let config = Config {
+            tls_min_version: Some("1.2".to_string()),
+            ..test_config()
+        };
+        assert!(build_http_client(&config).is_ok());
+    }
+
+    #[test]
+    fn test_build_http_client_http_version() {
+        for version in ["auto", "http1", "http2"] {
+            let config = Config {
+                http_version: Some(version.to_string()),
+                ..test_config()
+            };
+            assert!(build_http_client(&config).is_ok(), "{}", version);
+        }
+
+        let config = Config {
+            http_version: Some("http3".to_string()),
+            ..test_config()
+        };
+        assert!(build_http_client(&config).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+// Prometheus 文本格式的指标文件（metrics_file），供 node_exporter 的 textfile collector 收集
+// 每次运行后重写；上次变化时间在重启后从已有文件中恢复，地址长期不变时也能正确计算距上次变化的时间
+use std::fmt::Write as _;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::BoxError;
+
+const LAST_CHANGE: &str = "ddns_last_change_timestamp_seconds";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Metrics {
+    // 最近一次成功运行的时间
+    pub last_success: Option<SystemTime>,
+    // 最近一次地址发生变化并成功发布的时间
+    pub last_change: Option<SystemTime>,
+    pub consecutive_failures: u32,
+}
+
+impl Metrics {
+    // 还没有记录的时间不输出对应指标，避免告警规则把 0 当作很久以前
+    pub fn render(&self, now: SystemTime) -> String {
+        let mut out = String::new();
+        if let Some(time) = self.last_success {
+            gauge(&mut out, "ddns_last_success_timestamp_seconds", "Unix time of the last successful run", unix_secs(time));
+        }
+        if let Some(time) = self.last_change {
+            gauge(&mut out, LAST_CHANGE, "Unix time the published address last changed", unix_secs(time));
+            let since = now.duration_since(time).unwrap_or_default().as_secs_f64();
+            gauge(&mut out, "ddns_seconds_since_last_change", "Seconds since the published address last changed", since);
+        }
+        gauge(
+            &mut out,
+            "ddns_consecutive_failures",
+            "Number of consecutive failed runs",
+            f64::from(self.consecutive_failures),
+        );
+        out
+    }
+
+    // 先写临时文件再重命名，textfile collector 不会读到写了一半的文件
+    pub fn write_to(&self, path: &str) -> Result<(), BoxError> {
+        let tmp_path = format!("{}.tmp", path);
+        std::fs::write(&tmp_path, self.render(SystemTime::now()))?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+// 从上次写入的指标文件中读取上次变化时间，文件不存在或格式不对时返回 None
+pub fn read_last_change(path: &str) -> Option<SystemTime> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    contents.lines().find_map(|line| {
+        let value = line.strip_prefix(LAST_CHANGE)?.trim().parse::<f64>().ok()?;
+        (value.is_finite() && value >= 0.0).then(|| UNIX_EPOCH + std::time::Duration::from_secs_f64(value))
+    })
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn unix_secs(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render_and_restore_last_change() {
+        let last_change = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let metrics = Metrics {
+            last_success: Some(last_change + Duration::from_secs(300)),
+            last_change: Some(last_change),
+            consecutive_failures: 2,
+        };
+        let rendered = metrics.render(last_change + Duration::from_secs(600));
+        assert!(rendered.contains("ddns_seconds_since_last_change 600\n"), "{}", rendered);
+        assert!(rendered.contains("ddns_last_success_timestamp_seconds 1700000300\n"), "{}", rendered);
+        assert!(rendered.contains("ddns_consecutive_failures 2\n"), "{}", rendered);
+
+        // 没有变化记录时不输出相关指标
+        let rendered = Metrics::default().render(SystemTime::now());
+        assert!(!rendered.contains("ddns_seconds_since_last_change"), "{}", rendered);
+
+        let path = std::env::temp_dir().join(format!("rs-refresh-ddns-metrics-{}.prom", std::process::id()));
+        let path_str = path.to_string_lossy().to_string();
+        metrics.write_to(&path_str).unwrap();
+        assert_eq!(read_last_change(&path_str), Some(last_change));
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read_last_change(&path_str), None);
+    }
+}
@@ -0,0 +1,85 @@
+// 将每次更新的结果发布到 MQTT broker，供家庭自动化系统使用
+// 每次发布单独建立连接，发布失败只记录日志，不影响DDNS更新
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use tokio::time::timeout;
+use tracing::debug;
+
+use crate::{BoxError, Config};
+
+const DEFAULT_MQTT_PORT: u16 = 1883;
+const MQTT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// mqtt_broker 可以写成 "host"、"host:port"、"[::1]:1883"，可带 mqtt:// 前缀
+fn parse_broker(broker: &str) -> Result<(String, u16), BoxError> {
+    let broker = broker.strip_prefix("mqtt://").unwrap_or(broker).trim_end_matches('/');
+
+    let (host, port) = if let Some(rest) = broker.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']').ok_or_else(|| format!("Invalid MQTT broker '{}'", broker))?;
+        (host, rest.strip_prefix(':'))
+    } else {
+        match broker.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (broker, None),
+        }
+    };
+
+    if host.is_empty() {
+        return Err(format!("Invalid MQTT broker '{}'", broker).into());
+    }
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| format!("Invalid MQTT broker port in '{}'", broker))?,
+        None => DEFAULT_MQTT_PORT,
+    };
+    Ok((host.to_string(), port))
+}
+
+// 以 QoS 1 发布 payload，等待 broker 确认后断开
+pub async fn publish(config: &Config, payload: String) -> Result<(), BoxError> {
+    let (Some(broker), Some(topic)) = (config.mqtt_broker.as_deref(), config.mqtt_topic.as_deref()) else {
+        return Err("MQTT_BROKER and MQTT_TOPIC must both be set".into());
+    };
+    let (host, port) = parse_broker(broker)?;
+
+    let mut options = MqttOptions::new(format!("rs-refresh-ddns-{}", std::process::id()), host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if let Some(username) = config.mqtt_username.as_deref() {
+        options.set_credentials(username, config.mqtt_password.as_deref().unwrap_or_default());
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(options, 10);
+    client.publish(topic, QoS::AtLeastOnce, false, payload).await?;
+
+    let acked = async {
+        loop {
+            if let Event::Incoming(Packet::PubAck(_)) = eventloop.poll().await? {
+                return Ok::<(), BoxError>(());
+            }
+        }
+    };
+    timeout(MQTT_TIMEOUT, acked)
+        .await
+        .map_err(|_| format!("Timed out publishing to MQTT broker {}", broker))??;
+    debug!("Published update result to MQTT topic '{}'", topic);
+
+    // 发送 DISCONNECT，失败无关紧要
+    let _ = client.disconnect().await;
+    let _ = timeout(Duration::from_secs(1), eventloop.poll()).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_broker() {
+        assert_eq!(parse_broker("broker.local").unwrap(), ("broker.local".to_string(), 1883));
+        assert_eq!(parse_broker("mqtt://10.0.0.2:1884").unwrap(), ("10.0.0.2".to_string(), 1884));
+        assert_eq!(parse_broker("[fd00::2]:1885").unwrap(), ("fd00::2".to_string(), 1885));
+        assert_eq!(parse_broker("[fd00::2]").unwrap(), ("fd00::2".to_string(), 1883));
+        assert!(parse_broker("broker.local:abc").is_err());
+        assert!(parse_broker(":1883").is_err());
+    }
+}
@@ -0,0 +1,129 @@
+// 订阅内核的IPv6地址变化事件（rtnetlink RTM_NEWADDR/RTM_DELADDR，仅 Linux）
+// event_driven 开启时，全局地址出现或消失后立即触发一次更新，而不必等到下一次 cron
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+use tokio::sync::mpsc;
+use tracing::{debug, error, warn};
+
+use crate::BoxError;
+
+// nlmsghdr 和 ifaddrmsg 的长度
+const NLMSG_HEADER_LEN: usize = 16;
+const IFADDRMSG_LEN: usize = 8;
+
+// 加入 RTMGRP_IPV6_IFADDR 组，在独立线程中阻塞读取，每批相关事件向通道发送一次通知
+pub fn watch_ipv6_addresses() -> Result<mpsc::UnboundedReceiver<()>, BoxError> {
+    let fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::NETLINK_ROUTE) };
+    if fd < 0 {
+        return Err(format!("Failed to open netlink socket: {}", std::io::Error::last_os_error()).into());
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    addr.nl_groups = libc::RTMGRP_IPV6_IFADDR as u32;
+    let bound = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if bound < 0 {
+        return Err(format!("Failed to subscribe to netlink address events: {}", std::io::Error::last_os_error()).into());
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::Builder::new().name("netlink".to_string()).spawn(move || {
+        let mut buf = vec![0u8; 16 * 1024];
+        loop {
+            let len = unsafe { libc::recv(socket.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+            let changed = if len < 0 {
+                let e = std::io::Error::last_os_error();
+                match e.raw_os_error() {
+                    Some(libc::EINTR) => continue,
+                    // 接收缓冲区溢出，丢失了事件，保守地当作地址已变化
+                    Some(libc::ENOBUFS) => {
+                        warn!("Netlink receive buffer overflowed, some address events were lost");
+                        true
+                    }
+                    _ => {
+                        error!("Netlink address watcher stopped: {}", e);
+                        return;
+                    }
+                }
+            } else {
+                has_global_ipv6_change(&buf[..len as usize])
+            };
+
+            if changed && tx.send(()).is_err() {
+                return;
+            }
+        }
+    })?;
+
+    Ok(rx)
+}
+
+// 一批 netlink 消息中是否有全局作用域IPv6地址的增删，链路本地地址的变化不影响发布的地址
+fn has_global_ipv6_change(mut buf: &[u8]) -> bool {
+    let mut changed = false;
+    while buf.len() >= NLMSG_HEADER_LEN {
+        let len = u32::from_ne_bytes(buf[0..4].try_into().unwrap()) as usize;
+        let kind = u16::from_ne_bytes(buf[4..6].try_into().unwrap());
+        if len < NLMSG_HEADER_LEN || len > buf.len() {
+            break;
+        }
+
+        if (kind == libc::RTM_NEWADDR || kind == libc::RTM_DELADDR) && len >= NLMSG_HEADER_LEN + IFADDRMSG_LEN {
+            let ifaddr = &buf[NLMSG_HEADER_LEN..];
+            let (family, scope) = (ifaddr[0], ifaddr[3]);
+            let index = u32::from_ne_bytes(ifaddr[4..8].try_into().unwrap());
+            if family == libc::AF_INET6 as u8 && scope == libc::RT_SCOPE_UNIVERSE {
+                debug!(
+                    "Netlink: global IPv6 address {} on interface index {}",
+                    if kind == libc::RTM_NEWADDR { "added" } else { "removed" },
+                    index
+                );
+                changed = true;
+            }
+        }
+
+        // 消息按 4 字节对齐
+        let aligned = (len + 3) & !3;
+        buf = &buf[aligned.min(buf.len())..];
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(kind: u16, family: u8, scope: u8) -> Vec<u8> {
+        let mut msg = Vec::new();
+        msg.extend_from_slice(&((NLMSG_HEADER_LEN + IFADDRMSG_LEN) as u32).to_ne_bytes());
+        msg.extend_from_slice(&kind.to_ne_bytes());
+        msg.extend_from_slice(&[0u8; 10]);
+        msg.extend_from_slice(&[family, 64, 0, scope]);
+        msg.extend_from_slice(&2u32.to_ne_bytes());
+        msg
+    }
+
+    #[test]
+    fn test_has_global_ipv6_change() {
+        let inet6 = libc::AF_INET6 as u8;
+        assert!(has_global_ipv6_change(&message(libc::RTM_NEWADDR, inet6, libc::RT_SCOPE_UNIVERSE)));
+        assert!(has_global_ipv6_change(&message(libc::RTM_DELADDR, inet6, libc::RT_SCOPE_UNIVERSE)));
+        assert!(!has_global_ipv6_change(&message(libc::RTM_NEWADDR, inet6, libc::RT_SCOPE_LINK)));
+        assert!(!has_global_ipv6_change(&message(libc::RTM_NEWADDR, libc::AF_INET as u8, libc::RT_SCOPE_UNIVERSE)));
+        assert!(!has_global_ipv6_change(&message(libc::RTM_NEWLINK, inet6, libc::RT_SCOPE_UNIVERSE)));
+
+        // 多条消息中只要有一条相关即可
+        let mut batch = message(libc::RTM_NEWADDR, inet6, libc::RT_SCOPE_LINK);
+        batch.extend(message(libc::RTM_NEWADDR, inet6, libc::RT_SCOPE_UNIVERSE));
+        assert!(has_global_ipv6_change(&batch));
+
+        assert!(!has_global_ipv6_change(&[0u8; 4]));
+    }
+}
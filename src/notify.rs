@@ -0,0 +1,154 @@
+// Webhook 通知：按 notify_on 在地址变化、更新失败或每次运行后向 notify_webhook_url POST 一条JSON消息
+// 消息包含事件类型和与 status_file 相同的运行状态字段
+use reqwest::{Client, StatusCode};
+use tracing::debug;
+
+use crate::supervisor::RunStatus;
+use crate::{build_http_client, BoxError, Config, UpdateOutcome};
+
+#[derive(serde::Serialize)]
+struct Notification<'a> {
+    // change、unchanged、failure、unreachable 或 test
+    event: &'a str,
+    #[serde(flatten)]
+    status: &'a RunStatus,
+}
+
+// 何时发送通知（notify_on）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotifyOn {
+    Change,
+    Failure,
+    ChangeOrFailure,
+    // 每次运行都发送，地址未变化时 event 为 unchanged，可作为心跳
+    Always,
+}
+
+impl NotifyOn {
+    pub fn parse(value: &str) -> Result<Self, BoxError> {
+        match value {
+            "change" => Ok(Self::Change),
+            "failure" => Ok(Self::Failure),
+            "change_or_failure" => Ok(Self::ChangeOrFailure),
+            "always" => Ok(Self::Always),
+            other => Err(format!(
+                "Invalid NOTIFY_ON: {}. Expected change, failure, change_or_failure or always",
+                other
+            )
+            .into()),
+        }
+    }
+
+    // 本次运行结果对应的事件，不需要通知时返回 None
+    pub fn event(self, result: &Result<UpdateOutcome, BoxError>) -> Option<&'static str> {
+        let event = match result {
+            Ok(outcome) if outcome.changed => "change",
+            Ok(_) => "unchanged",
+            Err(_) => "failure",
+        };
+        match (self, event) {
+            (Self::Always, _) => Some(event),
+            (Self::Change, "change") | (Self::Failure, "failure") => Some(event),
+            (Self::ChangeOrFailure, "change" | "failure") => Some(event),
+            _ => None,
+        }
+    }
+
+    // 外部可访问性检查失败（unreachable）按失败处理
+    pub fn reports_failures(self) -> bool {
+        self != Self::Change
+    }
+}
+
+// 发送通知，非 2xx 响应视为失败；返回服务器的状态码
+pub async fn send(client: &Client, url: &str, event: &str, status: &RunStatus) -> Result<StatusCode, BoxError> {
+    let body = serde_json::to_string(&Notification { event, status })?;
+    debug!("Sending {} notification to webhook: {}", event, body);
+
+    let response = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .await?;
+
+    let code = response.status();
+    if !code.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!("Webhook responded with HTTP {}: {}", code, text.trim()).into());
+    }
+    Ok(code)
+}
+
+// 示例消息，使用文档地址段中的地址
+pub fn sample_status() -> RunStatus {
+    RunStatus::from_result(&Ok(UpdateOutcome {
+        changed: true,
+        ip: Some("2001:db8::1".parse().unwrap()),
+        provider_response: "test notification from rs-refresh-ddns".to_string(),
+        providers: Vec::new(),
+    }))
+}
+
+// --test-notify：发送示例通知并输出结果，不需要提供商配置
+pub async fn test_notify(config: &Config) -> Result<(), BoxError> {
+    let url = config.notify_webhook_url.as_deref().ok_or("NOTIFY_WEBHOOK_URL is not set")?;
+    let client = build_http_client(config)?;
+    let code = send(&client, url, "test", &sample_status())
+        .await
+        .map_err(|e| format!("Failed to send test notification: {}", e))?;
+    println!("Webhook responded with HTTP {}", code);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{http_response, serve_once};
+
+    #[test]
+    fn test_notify_on_events() {
+        let changed = |changed| {
+            Ok(UpdateOutcome {
+                changed,
+                ip: Some("2001:db8::1".parse().unwrap()),
+                provider_response: String::new(),
+                providers: Vec::new(),
+            })
+        };
+        let failed: Result<UpdateOutcome, BoxError> = Err("boom".into());
+
+        let default = NotifyOn::parse("change_or_failure").unwrap();
+        assert_eq!(default.event(&changed(true)), Some("change"));
+        assert_eq!(default.event(&changed(false)), None);
+        assert_eq!(default.event(&failed), Some("failure"));
+
+        let always = NotifyOn::parse("always").unwrap();
+        assert_eq!(always.event(&changed(false)), Some("unchanged"));
+        assert_eq!(always.event(&failed), Some("failure"));
+
+        assert_eq!(NotifyOn::Change.event(&failed), None);
+        assert!(!NotifyOn::Change.reports_failures());
+        assert_eq!(NotifyOn::Failure.event(&changed(true)), None);
+        assert!(NotifyOn::parse("never").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_posts_json() {
+        let (url, request) = serve_once(http_response("204 No Content", "")).await;
+        let code = send(&Client::new(), &url, "test", &sample_status()).await.unwrap();
+        assert_eq!(code, StatusCode::NO_CONTENT);
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST / HTTP/1.1"), "{}", request);
+        assert!(request.contains(r#""event":"test""#), "{}", request);
+        assert!(request.contains(r#""ip":"2001:db8::1""#), "{}", request);
+    }
+
+    #[tokio::test]
+    async fn test_send_rejects_error_status() {
+        let (url, _) = serve_once(http_response("500 Internal Server Error", "boom")).await;
+        let err = send(&Client::new(), &url, "test", &sample_status()).await.unwrap_err();
+        assert!(err.to_string().contains("500"), "{}", err);
+    }
+}
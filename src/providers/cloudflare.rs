@@ -0,0 +1,318 @@
+// Cloudflare 提供商
+// 通过 API v4 先按名称和 record_type 列出区域中的记录，地址或代理设置不同时再逐条 PATCH 修改（同时写入 record_comment 备注）；
+// 请求带 Bearer 令牌，响应中 success 为 true 表示成功，否则 errors 给出原因
+use std::net::Ipv6Addr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+use tracing::debug;
+
+use super::{log_egress, render_comment, status_error, with_timeout, Provider, ProviderUpdate};
+use crate::{read_body_limited, BoxError, Config};
+
+const CLOUDFLARE_ENDPOINT: &str = "https://api.cloudflare.com/client/v4";
+
+pub struct Cloudflare {
+    label: String,
+    endpoint: String,
+    token: String,
+    zone_id: String,
+    record_name: String,
+    record_type: String,
+    // 为 None 时保持记录当前的代理设置
+    proxied: Option<bool>,
+    // record_comment 的模板，写入记录本身的备注；为 None 时不修改记录原有的备注
+    comment: Option<String>,
+    timeout: Option<Duration>,
+    // 响应体的最大字节数
+    max_response_bytes: usize,
+}
+
+// 列出的一条记录中与更新有关的部分
+struct Record {
+    id: String,
+    content: String,
+    proxied: bool,
+}
+
+impl Cloudflare {
+    pub fn from_config(config: &Config) -> Result<Vec<Box<dyn Provider>>, BoxError> {
+        let record_name = config.cloudflare_record_name.clone().ok_or("CLOUDFLARE_RECORD_NAME must be set")?;
+        // 启动时检查模板，避免到第一次更新时才发现占位符写错
+        if let Some(comment) = &config.record_comment {
+            render_comment(comment)?;
+        }
+        let provider = Self {
+            label: format!("cloudflare:{}", record_name),
+            endpoint: CLOUDFLARE_ENDPOINT.to_string(),
+            token: config.cloudflare_api_token.clone().ok_or("CLOUDFLARE_API_TOKEN must be set")?,
+            zone_id: config.cloudflare_zone_id.clone().ok_or("CLOUDFLARE_ZONE_ID must be set")?,
+            record_name,
+            record_type: record_type(config.cloudflare_record_type.as_deref())?,
+            proxied: config.cloudflare_proxied,
+            comment: config.record_comment.clone(),
+            timeout: config.provider_timeout(),
+            max_response_bytes: config.max_response_bytes,
+        };
+        Ok(vec![Box::new(provider)])
+    }
+
+    async fn call(&self, action: &str, request: RequestBuilder) -> Result<Value, BoxError> {
+        let request = request.bearer_auth(&self.token).header("Content-Type", "application/json");
+        let response = with_timeout(request, self.timeout).send().await?;
+        log_egress(self.name(), &response);
+
+        let status = response.status();
+        let text = read_body_limited(response, self.max_response_bytes)
+            .await
+            .map_err(|e| format!("Cloudflare {} response rejected: {}", action, e))?;
+        debug!("Cloudflare {} response - Status: {}, Body: {}", action, status, text);
+        parse_response(&text).map_err(|e| status_error(status, format!("Cloudflare {} failed (HTTP {}): {}", action, status, e)))
+    }
+
+    // 名称和类型都匹配的记录，没有时报错（只修改已有记录，同名的 CNAME 不会被列出）
+    async fn current_records(&self, client: &Client) -> Result<Vec<Record>, BoxError> {
+        let request = client
+            .get(format!("{}/zones/{}/dns_records", self.endpoint, self.zone_id))
+            .query(&[("type", self.record_type.as_str()), ("name", self.record_name.as_str())]);
+        let response = self.call("list", request).await?;
+        let records = parse_records(&response);
+        if records.is_empty() {
+            return Err(format!(
+                "Cloudflare has no {} record for {}, create it first (a CNAME with the same name is not updated)",
+                self.record_type, self.record_name
+            )
+            .into());
+        }
+        Ok(records)
+    }
+
+    // PATCH 的请求体：新地址，以及配置了的代理设置和备注
+    fn patch_body(&self, ipv6: &str) -> Result<Value, BoxError> {
+        let mut body = json!({ "content": ipv6 });
+        if let Some(proxied) = self.proxied {
+            body["proxied"] = json!(proxied);
+        }
+        if let Some(comment) = &self.comment {
+            body["comment"] = json!(render_comment(comment)?);
+        }
+        Ok(body)
+    }
+}
+
+#[async_trait]
+impl Provider for Cloudflare {
+    fn name(&self) -> &str {
+        &self.label
+    }
+
+    fn hostname(&self) -> Option<String> {
+        Some(self.record_name.clone())
+    }
+
+    async fn update(&self, client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+        debug!("Updating Cloudflare {} record {}", self.record_type, self.record_name);
+        let records = self.current_records(client).await?;
+        let stale: Vec<&Record> = records.iter().filter(|record| needs_update(record, ipv6, self.proxied)).collect();
+        if stale.is_empty() {
+            return Ok(ProviderUpdate {
+                changed: false,
+                response: format!("{} already points to {}", self.record_name, ipv6),
+            });
+        }
+
+        let body = self.patch_body(ipv6)?;
+        for record in &stale {
+            let request = client
+                .patch(format!("{}/zones/{}/dns_records/{}", self.endpoint, self.zone_id, record.id))
+                .body(body.to_string());
+            self.call("patch", request).await?;
+        }
+        let previous: Vec<&str> = stale.iter().map(|record| record.content.as_str()).collect();
+        Ok(ProviderUpdate {
+            changed: true,
+            response: format!("{} now points to {} (was {})", self.record_name, ipv6, previous.join(", ")),
+        })
+    }
+
+    // 只列出记录，验证令牌、区域和记录是否存在，不修改记录
+    async fn check(&self, client: &Client, _ipv6: &str) -> Result<String, BoxError> {
+        let records = self.current_records(client).await?;
+        let contents: Vec<&str> = records.iter().map(|record| record.content.as_str()).collect();
+        Ok(format!(
+            "{} record {} readable (currently {})",
+            self.record_type,
+            self.record_name,
+            contents.join(", ")
+        ))
+    }
+}
+
+// 未设置时为 AAAA；本工具只检测IPv6地址，其他类型无法写入检测到的地址
+fn record_type(configured: Option<&str>) -> Result<String, BoxError> {
+    let record_type = configured.map(str::trim).unwrap_or("AAAA").to_ascii_uppercase();
+    if record_type != "AAAA" {
+        return Err(format!(
+            "Invalid CLOUDFLARE_RECORD_TYPE: {}. Only AAAA is supported, this tool publishes IPv6 addresses",
+            record_type
+        )
+        .into());
+    }
+    Ok(record_type)
+}
+
+// success 不是 true 时把 errors 中的说明作为错误返回
+fn parse_response(text: &str) -> Result<Value, BoxError> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|_| format!("Unexpected Cloudflare response: {}", text.trim()))?;
+    if value["success"].as_bool() == Some(true) {
+        return Ok(value);
+    }
+    let errors: Vec<String> = value["errors"]
+        .as_array()
+        .map(|errors| {
+            errors
+                .iter()
+                .map(|error| format!("{} ({})", error["message"].as_str().unwrap_or("unknown error"), error["code"]))
+                .collect()
+        })
+        .unwrap_or_default();
+    Err(if errors.is_empty() {
+        "unknown error".into()
+    } else {
+        errors.join("; ").into()
+    })
+}
+
+fn parse_records(response: &Value) -> Vec<Record> {
+    response["result"]
+        .as_array()
+        .map(|records| {
+            records
+                .iter()
+                .filter_map(|record| {
+                    Some(Record {
+                        id: record["id"].as_str()?.to_string(),
+                        content: record["content"].as_str()?.to_string(),
+                        proxied: record["proxied"].as_bool().unwrap_or(false),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// 地址不同，或配置了代理设置且与记录当前的不同时需要修改；地址按值比较，记录中的写法可能与检测到的不同
+fn needs_update(record: &Record, ipv6: &str, proxied: Option<bool>) -> bool {
+    let same_address = match (record.content.parse::<Ipv6Addr>(), ipv6.parse::<Ipv6Addr>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => record.content == ipv6,
+    };
+    !same_address || proxied.is_some_and(|proxied| proxied != record.proxied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(endpoint: &str, proxied: Option<bool>) -> Cloudflare {
+        Cloudflare {
+            label: String::new(),
+            endpoint: endpoint.to_string(),
+            token: "cf_token".to_string(),
+            zone_id: "zone1".to_string(),
+            record_name: "home.example.com".to_string(),
+            record_type: "AAAA".to_string(),
+            proxied,
+            comment: None,
+            timeout: None,
+            max_response_bytes: crate::DEFAULT_MAX_RESPONSE_BYTES,
+        }
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let ok = parse_response(
+            r#"{"success":true,"errors":[],"result":[{"id":"r1","type":"AAAA","content":"2001:db8::1","proxied":true}]}"#,
+        )
+        .unwrap();
+        let records = parse_records(&ok);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, "r1");
+        assert!(records[0].proxied);
+
+        let err = parse_response(r#"{"success":false,"errors":[{"code":10000,"message":"Authentication error"}]}"#)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Authentication error (10000)");
+        assert!(parse_response("<html>").is_err());
+    }
+
+    #[test]
+    fn test_record_type() {
+        assert_eq!(record_type(None).unwrap(), "AAAA");
+        assert_eq!(record_type(Some("aaaa")).unwrap(), "AAAA");
+        let err = record_type(Some("A")).unwrap_err();
+        assert!(err.to_string().contains("Only AAAA is supported"), "{}", err);
+    }
+
+    #[test]
+    fn test_needs_update() {
+        let record = Record {
+            id: "r1".to_string(),
+            content: "2001:DB8:0::1".to_string(),
+            proxied: false,
+        };
+        assert!(!needs_update(&record, "2001:db8::1", None));
+        assert!(!needs_update(&record, "2001:db8::1", Some(false)));
+        // 地址未变但代理设置不同时也要修改
+        assert!(needs_update(&record, "2001:db8::1", Some(true)));
+        assert!(needs_update(&record, "2001:db8::2", None));
+    }
+
+    #[test]
+    fn test_patch_body() {
+        let body = provider(CLOUDFLARE_ENDPOINT, None).patch_body("2001:db8::1").unwrap();
+        assert_eq!(body, json!({ "content": "2001:db8::1" }));
+        let body = provider(CLOUDFLARE_ENDPOINT, Some(true)).patch_body("2001:db8::1").unwrap();
+        assert_eq!(body, json!({ "content": "2001:db8::1", "proxied": true }));
+
+        let provider = Cloudflare {
+            comment: Some("updated at {time}".to_string()),
+            ..provider(CLOUDFLARE_ENDPOINT, Some(true))
+        };
+        let body = provider.patch_body("2001:db8::1").unwrap();
+        assert_eq!(body["proxied"], json!(true));
+        let comment = body["comment"].as_str().unwrap();
+        assert!(comment.starts_with("updated at 20") && comment.ends_with('Z'), "{}", comment);
+    }
+
+    #[tokio::test]
+    async fn test_update_unchanged_skips_patch() {
+        use crate::tests::{http_response, serve_once};
+
+        let body = r#"{"success":true,"errors":[],"result":[{"id":"r1","content":"2001:db8::1","proxied":true}]}"#;
+        let (url, request) = serve_once(http_response("200 OK", body)).await;
+        let update = provider(&url, Some(true)).update(&Client::new(), "2001:db8::1").await.unwrap();
+        assert!(!update.changed);
+
+        let request = request.await.unwrap();
+        assert!(
+            request.starts_with("GET /zones/zone1/dns_records?type=AAAA&name=home.example.com "),
+            "{}",
+            request
+        );
+        assert!(request.to_ascii_lowercase().contains("authorization: bearer cf_token"), "{}", request);
+    }
+
+    #[tokio::test]
+    async fn test_update_rejects_oversized_response() {
+        use crate::tests::{http_response, serve_once};
+
+        let body = format!(r#"{{"success":true,"errors":[],"result":[]{}}}"#, " ".repeat(crate::DEFAULT_MAX_RESPONSE_BYTES));
+        let (url, _) = serve_once(http_response("200 OK", &body)).await;
+        let err = provider(&url, None).update(&Client::new(), "2001:db8::1").await.err().unwrap();
+        assert!(err.to_string().contains("Cloudflare list response rejected"), "{}", err);
+    }
+}
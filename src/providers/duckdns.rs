@@ -0,0 +1,412 @@
+// DuckDNS 提供商
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use tracing::{debug, info, warn};
+
+use super::{expand_pattern, format_headers, log_egress, render_template, status_error, with_timeout, Provider, ProviderUpdate};
+use crate::{read_body_limited, BoxError, Config};
+
+const DUCKDNS_BASE_URL: &str = "https://www.duckdns.org";
+const DUCKDNS_UPDATE_TEMPLATE: &str = "{base_url}/update?domains={domain}&token={token}&ipv6={ipv6}&verbose=true";
+
+pub struct DuckDns {
+    label: String,
+    base_url: String,
+    domain: String,
+    // 轮换令牌期间可以同时配置新旧令牌，按顺序尝试
+    tokens: Vec<String>,
+    // 上次被接受的令牌下标，之后优先使用，直到它被拒绝
+    preferred_token: AtomicUsize,
+    log_response_headers: bool,
+    timeout: Option<Duration>,
+    // 响应体的最大字节数，正常响应只有几十字节
+    max_response_bytes: usize,
+}
+
+impl DuckDns {
+    // duckdns_domain 可以用逗号分隔多个域名，每个域名单独更新以便分别得到结果
+    // domain_pattern 展开后的域名追加在后面，重复的只更新一次
+    pub fn from_config(config: &Config) -> Result<Vec<Box<dyn Provider>>, BoxError> {
+        let mut domains: Vec<String> = config
+            .duckdns_domain
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|domain| !domain.is_empty())
+            .map(str::to_string)
+            .collect();
+        if let Some(pattern) = config.domain_pattern.as_deref() {
+            for domain in expand_pattern(pattern)? {
+                if !domains.contains(&domain) {
+                    domains.push(domain);
+                }
+            }
+        }
+        let tokens: Vec<String> = config
+            .duckdns_token
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(str::to_string)
+            .collect();
+        if tokens.is_empty() {
+            return Err("DUCKDNS_TOKEN must be set".into());
+        }
+        // 可以指向兼容 DuckDNS 的代理或测试服务器
+        let base_url = config
+            .duckdns_base_url
+            .as_deref()
+            .unwrap_or(DUCKDNS_BASE_URL)
+            .trim_end_matches('/')
+            .to_string();
+
+        let providers: Vec<Box<dyn Provider>> = domains
+            .iter()
+            .map(|domain| {
+                Box::new(Self {
+                    label: format!("duckdns:{}", domain),
+                    base_url: base_url.clone(),
+                    domain: domain.clone(),
+                    tokens: tokens.clone(),
+                    preferred_token: AtomicUsize::new(0),
+                    log_response_headers: config.log_response_headers,
+                    timeout: config.provider_timeout(),
+                    max_response_bytes: config.max_response_bytes,
+                }) as Box<dyn Provider>
+            })
+            .collect();
+
+        if providers.is_empty() {
+            return Err("DUCKDNS_DOMAIN or DOMAIN_PATTERN must be set".into());
+        }
+        Ok(providers)
+    }
+
+    // 更新DuckDNS，从上次被接受的令牌开始依次尝试，令牌被拒绝（KO）时换下一个
+    async fn update_duckdns(&self, client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+        let preferred = self.preferred_token.load(Ordering::Relaxed);
+        for offset in 0..self.tokens.len() {
+            let index = (preferred + offset) % self.tokens.len();
+            let Some(update) = self.update_with_token(client, &self.tokens[index], ipv6).await? else {
+                if self.tokens.len() > 1 {
+                    warn!("DuckDNS rejected token #{} for {}", index + 1, self.domain);
+                }
+                continue;
+            };
+
+            if index != preferred {
+                info!("DuckDNS accepted token #{} for {}, using it from now on", index + 1, self.domain);
+                self.preferred_token.store(index, Ordering::Relaxed);
+            }
+            return Ok(update);
+        }
+
+        if self.tokens.len() > 1 {
+            Err(format!("DuckDNS rejected all {} tokens (KO), check domain and tokens", self.tokens.len()).into())
+        } else {
+            Err(REJECTED_ERROR.into())
+        }
+    }
+
+    fn url(&self, token: &str, ipv6: &str) -> Result<String, BoxError> {
+        render_template(
+            DUCKDNS_UPDATE_TEMPLATE,
+            &[
+                ("base_url", &self.base_url),
+                ("domain", &self.domain),
+                ("token", token),
+                ("ipv6", ipv6),
+            ],
+        )
+    }
+
+    // 使用一个令牌更新，令牌被拒绝时返回 None
+    async fn update_with_token(&self, client: &Client, token: &str, ipv6: &str) -> Result<Option<ProviderUpdate>, BoxError> {
+        let url = self.url(token, ipv6)?;
+        // 日志和错误中都不带令牌，-v 的输出常被贴到 issue 里
+        debug!("Updating DuckDNS with URL: {}", self.url("***", ipv6)?);
+
+        let response = with_timeout(client.get(&url), self.timeout).send().await.map_err(|e| e.without_url())?;
+        log_egress(self.name(), &response);
+
+        let status = response.status();
+        let headers = format_headers(response.headers());
+        let body = read_body_limited(response, self.max_response_bytes)
+            .await
+            .map_err(|e| format!("DuckDNS response rejected: {}", e))?;
+
+        debug!("DuckDNS update response - Status: {}, Body: {}", status, body);
+        debug!("DuckDNS response headers: {}", headers);
+
+        if status.is_success() && is_rejected(&body) {
+            return Ok(None);
+        }
+
+        let parsed = if status.is_success() {
+            DuckDnsResponse::parse(&body)
+        } else {
+            Err(status_error(status, format!("DuckDNS update failed with status: {}", status)))
+        };
+
+        let parsed = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                if self.log_response_headers {
+                    warn!("DuckDNS update failed, response headers: {}", headers);
+                }
+                return Err(e);
+            }
+        };
+
+        // 核对DuckDNS实际记录的地址与发送的地址是否一致
+        if !same_ipv6(&parsed.ipv6, ipv6) {
+            warn!(
+                "DuckDNS recorded IPv6 '{}' but '{}' was sent; the provider may have normalized or rejected it",
+                parsed.ipv6, ipv6
+            );
+        }
+
+        Ok(Some(ProviderUpdate {
+            changed: parsed.updated,
+            response: body,
+        }))
+    }
+}
+
+const REJECTED_ERROR: &str = "DuckDNS rejected the update (KO), check domain and token";
+
+// DuckDNS 对错误的域名或令牌只返回 KO
+fn is_rejected(body: &str) -> bool {
+    body.lines().next().map(str::trim) == Some("KO")
+}
+
+// DuckDNS verbose=true 时的响应：
+// OK
+// [IPv4]
+// [IPv6]
+// UPDATED 或 NOCHANGE
+#[derive(Debug, PartialEq)]
+struct DuckDnsResponse {
+    ipv4: String,
+    ipv6: String,
+    updated: bool,
+}
+
+impl DuckDnsResponse {
+    fn parse(body: &str) -> Result<Self, BoxError> {
+        let mut lines = body.lines().map(str::trim);
+
+        if is_rejected(body) {
+            return Err(REJECTED_ERROR.into());
+        }
+        if lines.next() != Some("OK") {
+            return Err(format!("Unexpected DuckDNS response: {}", body).into());
+        }
+
+        let ipv4 = lines.next().unwrap_or_default().to_string();
+        let ipv6 = lines.next().unwrap_or_default().to_string();
+        let updated = lines.next() == Some("UPDATED");
+
+        Ok(Self { ipv4, ipv6, updated })
+    }
+}
+
+// 按地址值比较，忽略压缩写法等格式差异
+fn same_ipv6(a: &str, b: &str) -> bool {
+    match (a.parse::<std::net::Ipv6Addr>(), b.parse::<std::net::Ipv6Addr>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[async_trait]
+impl Provider for DuckDns {
+    fn name(&self) -> &str {
+        &self.label
+    }
+
+    fn hostname(&self) -> Option<String> {
+        if self.domain.ends_with(".duckdns.org") {
+            Some(self.domain.clone())
+        } else {
+            Some(format!("{}.duckdns.org", self.domain))
+        }
+    }
+
+    fn update_url(&self, ipv6: &str) -> Result<Option<String>, BoxError> {
+        self.url("***", ipv6).map(Some)
+    }
+
+    async fn update(&self, client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+        self.update_duckdns(client, ipv6).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config_splits_domains() {
+        let config = Config {
+            duckdns_domain: Some("home, nas,,".to_string()),
+            ..crate::tests::test_config()
+        };
+        let providers = DuckDns::from_config(&config).unwrap();
+        let names: Vec<&str> = providers.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["duckdns:home", "duckdns:nas"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_sends_query_params() {
+        let (url, request) = crate::tests::serve_once(crate::tests::http_response(
+            "200 OK",
+            "OK\n\n2001:db8::1\nUPDATED",
+        ))
+        .await;
+        let config = Config {
+            duckdns_domain: Some("home".to_string()),
+            duckdns_token: Some("secret".to_string()),
+            duckdns_base_url: Some(format!("{}/", url)),
+            ..crate::tests::test_config()
+        };
+        let providers = DuckDns::from_config(&config).unwrap();
+
+        let update = providers[0].update(&Client::new(), "2001:db8::1").await.unwrap();
+        assert!(update.changed);
+
+        let request = request.await.unwrap();
+        let request_line = request.lines().next().unwrap();
+        assert_eq!(
+            request_line,
+            "GET /update?domains=home&token=secret&ipv6=2001:db8::1&verbose=true HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn test_update_url_redacts_token() {
+        let config = Config {
+            duckdns_domain: Some("home".to_string()),
+            duckdns_token: Some("secret".to_string()),
+            ..crate::tests::test_config()
+        };
+        let providers = DuckDns::from_config(&config).unwrap();
+        assert_eq!(
+            providers[0].update_url("2001:db8::1").unwrap().as_deref(),
+            Some("https://www.duckdns.org/update?domains=home&token=***&ipv6=2001:db8::1&verbose=true")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_rejects_oversized_response() {
+        let body = format!("OK\n\n2001:db8::1\nUPDATED{}", " ".repeat(crate::DEFAULT_MAX_RESPONSE_BYTES));
+        let (url, _) = crate::tests::serve_once(crate::tests::http_response("200 OK", &body)).await;
+        let config = Config {
+            duckdns_domain: Some("home".to_string()),
+            duckdns_base_url: Some(url),
+            ..crate::tests::test_config()
+        };
+        let providers = DuckDns::from_config(&config).unwrap();
+
+        let Err(err) = providers[0].update(&Client::new(), "2001:db8::1").await else {
+            panic!("update should fail when the response is too large");
+        };
+        assert!(err.to_string().contains("too large"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_update_rotates_tokens() {
+        use std::sync::Arc;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // 只接受 new 令牌的本地服务，记录收到的请求数
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = vec![0u8; 8192];
+                let len = stream.read(&mut buf).await.unwrap();
+                let body = if String::from_utf8_lossy(&buf[..len]).contains("token=new&") {
+                    "OK\n\n2001:db8::1\nUPDATED"
+                } else {
+                    "KO"
+                };
+                let response = crate::tests::http_response("200 OK", body);
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+        });
+
+        let config = Config {
+            duckdns_domain: Some("home".to_string()),
+            duckdns_token: Some("old, new".to_string()),
+            duckdns_base_url: Some(url),
+            ..crate::tests::test_config()
+        };
+        let providers = DuckDns::from_config(&config).unwrap();
+        let client = Client::new();
+
+        assert!(providers[0].update(&client, "2001:db8::1").await.unwrap().changed);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // 之后直接使用被接受的令牌
+        assert!(providers[0].update(&client, "2001:db8::1").await.is_ok());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_update_fails_when_every_token_rejected() {
+        let (url, _) = crate::tests::serve_once(crate::tests::http_response("200 OK", "KO")).await;
+        let config = Config {
+            duckdns_domain: Some("home".to_string()),
+            duckdns_base_url: Some(url),
+            ..crate::tests::test_config()
+        };
+        let providers = DuckDns::from_config(&config).unwrap();
+
+        let Err(err) = providers[0].update(&Client::new(), "2001:db8::1").await else {
+            panic!("update should fail when the token is rejected");
+        };
+        assert_eq!(err.to_string(), REJECTED_ERROR);
+    }
+
+    #[test]
+    fn test_parse_verbose_response() {
+        let response = DuckDnsResponse::parse("OK\n1.2.3.4\n2001:db8::1\nUPDATED").unwrap();
+        assert_eq!(
+            response,
+            DuckDnsResponse {
+                ipv4: "1.2.3.4".to_string(),
+                ipv6: "2001:db8::1".to_string(),
+                updated: true,
+            }
+        );
+
+        let response = DuckDnsResponse::parse("OK\n\n2001:db8::1\nNOCHANGE").unwrap();
+        assert!(!response.updated);
+        assert!(response.ipv4.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rejected_response() {
+        assert!(DuckDnsResponse::parse("KO").is_err());
+        assert!(DuckDnsResponse::parse("<html>bad gateway</html>").is_err());
+    }
+
+    #[test]
+    fn test_same_ipv6_ignores_formatting() {
+        assert!(same_ipv6("2001:db8::1", "2001:0db8:0000:0000:0000:0000:0000:0001"));
+        assert!(!same_ipv6("2001:db8::1", "2001:db8::2"));
+        assert!(!same_ipv6("", "2001:db8::1"));
+    }
+}
@@ -0,0 +1,165 @@
+// DynDNS2 协议提供商（DNS-O-Matic、dyn.com、No-IP 等兼容服务）
+// GET /nic/update?hostname=...&myip=...，使用 Basic 认证，返回 good/nochg/badauth 等状态码
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use tracing::debug;
+
+use super::{log_egress, status_error, with_timeout, Provider, ProviderUpdate, Transient};
+use crate::{read_body_limited, BoxError, Config};
+
+pub struct DynDns2 {
+    label: String,
+    server: String,
+    username: String,
+    password: String,
+    hostname: String,
+    timeout: Option<Duration>,
+    // 响应体的最大字节数，正常响应每个主机名只有一行
+    max_response_bytes: usize,
+}
+
+impl DynDns2 {
+    pub fn from_config(config: &Config) -> Result<Vec<Box<dyn Provider>>, BoxError> {
+        let hostname = config.dyndns2_hostname.clone().ok_or("DYNDNS2_HOSTNAME must be set")?;
+        Ok(vec![Box::new(Self {
+            label: format!("dyndns2:{}", hostname),
+            server: config.dyndns2_server.clone().ok_or("DYNDNS2_SERVER must be set")?,
+            username: config.dyndns2_username.clone().ok_or("DYNDNS2_USERNAME must be set")?,
+            password: config.dyndns2_password.clone().ok_or("DYNDNS2_PASSWORD must be set")?,
+            hostname,
+            timeout: config.provider_timeout(),
+            max_response_bytes: config.max_response_bytes,
+        })])
+    }
+
+    // 拼接更新地址，server 只写主机时补上标准路径 /nic/update
+    fn update_url(&self, ip: &str) -> Result<Url, BoxError> {
+        let mut url = Url::parse(&self.server)?;
+        if url.path() == "/" {
+            url.set_path("/nic/update");
+        }
+        url.query_pairs_mut()
+            .append_pair("hostname", &self.hostname)
+            .append_pair("myip", ip);
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl Provider for DynDns2 {
+    fn name(&self) -> &str {
+        &self.label
+    }
+
+    fn hostname(&self) -> Option<String> {
+        Some(self.hostname.clone())
+    }
+
+    async fn update(&self, client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+        let url = self.update_url(ipv6)?;
+        debug!("Updating DynDNS2 host '{}' via {}", self.hostname, self.server);
+
+        let request = client
+            .get(url)
+            .basic_auth(&self.username, Some(&self.password))
+            .header("User-Agent", concat!("rs-refresh-ddns/", env!("CARGO_PKG_VERSION")));
+        let response = with_timeout(request, self.timeout).send().await?;
+        log_egress(self.name(), &response);
+
+        let status = response.status();
+        let body = read_body_limited(response, self.max_response_bytes)
+            .await
+            .map_err(|e| format!("DynDNS2 response rejected: {}", e))?;
+        debug!("DynDNS2 update response - Status: {}, Body: {}", status, body);
+
+        let changed = parse_response(&body).map_err(|e| status_error(status, e))?;
+        Ok(ProviderUpdate {
+            changed,
+            response: body,
+        })
+    }
+}
+
+// 解析响应，多个主机名时每行一个结果；返回是否有记录发生变化
+fn parse_response(body: &str) -> Result<bool, BoxError> {
+    let mut changed = false;
+    let mut lines = body.lines().map(str::trim).filter(|line| !line.is_empty()).peekable();
+    if lines.peek().is_none() {
+        return Err("Empty DynDNS2 response".into());
+    }
+
+    for line in lines {
+        let code = line.split_whitespace().next().unwrap_or_default();
+        match code {
+            "good" => changed = true,
+            "nochg" => {}
+            "badauth" => return Err("DynDNS2 authentication failed (badauth)".into()),
+            "notfqdn" => return Err("DynDNS2 hostname is not a fully-qualified domain name (notfqdn)".into()),
+            "nohost" => return Err("DynDNS2 hostname does not exist in this account (nohost)".into()),
+            "numhost" => return Err("DynDNS2 too many hosts in one request (numhost)".into()),
+            "abuse" => return Err("DynDNS2 hostname is blocked for abuse (abuse)".into()),
+            "badagent" => return Err("DynDNS2 rejected the user agent (badagent)".into()),
+            "!donator" => return Err("DynDNS2 feature requires a paid account (!donator)".into()),
+            "dnserr" | "911" => return Err(Box::new(Transient(format!("DynDNS2 server error, retry later ({})", code)))),
+            _ => return Err(format!("Unexpected DynDNS2 response: {}", line).into()),
+        }
+    }
+
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::is_retryable;
+
+    #[test]
+    fn test_parse_response() {
+        assert!(parse_response("good 2001:db8::1").unwrap());
+        assert!(!parse_response("nochg 2001:db8::1\n").unwrap());
+        assert!(parse_response("nochg 2001:db8::1\ngood 2001:db8::1").unwrap());
+        assert!(parse_response("badauth").is_err());
+        assert!(parse_response("good 2001:db8::1\nnohost").is_err());
+        assert!(parse_response("").is_err());
+        assert!(parse_response("<html>").is_err());
+
+        // 只有服务器端的暂时性错误值得重试
+        assert!(is_retryable(&parse_response("911").unwrap_err()));
+        assert!(!is_retryable(&parse_response("badauth").unwrap_err()));
+        assert!(is_retryable(&status_error(reqwest::StatusCode::SERVICE_UNAVAILABLE, "Unexpected DynDNS2 response: <html>")));
+        assert!(!is_retryable(&status_error(reqwest::StatusCode::OK, "Unexpected DynDNS2 response: <html>")));
+    }
+
+    fn provider(server: &str) -> DynDns2 {
+        DynDns2 {
+            label: String::new(),
+            server: server.to_string(),
+            username: "user".to_string(),
+            password: "pass".to_string(),
+            hostname: "home.example.com".to_string(),
+            timeout: None,
+            max_response_bytes: crate::DEFAULT_MAX_RESPONSE_BYTES,
+        }
+    }
+
+    #[test]
+    fn test_update_url() {
+        let provider = provider("https://updates.dnsomatic.com");
+        assert_eq!(
+            provider.update_url("2001:db8::1").unwrap().as_str(),
+            "https://updates.dnsomatic.com/nic/update?hostname=home.example.com&myip=2001%3Adb8%3A%3A1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_rejects_oversized_response() {
+        use crate::tests::{http_response, serve_once};
+
+        let body = format!("good 2001:db8::1{}", " ".repeat(crate::DEFAULT_MAX_RESPONSE_BYTES));
+        let (url, _) = serve_once(http_response("200 OK", &body)).await;
+        let err = provider(&url).update(&Client::new(), "2001:db8::1").await.err().unwrap();
+        assert!(err.to_string().contains("DynDNS2 response rejected"), "{}", err);
+    }
+}
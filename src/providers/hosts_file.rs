@@ -0,0 +1,140 @@
+// hosts 文件提供商，通常作为 local_providers 用于内网 DNS（分离解析）
+// 在 hosts 格式的文件中把 hosts_file_hostname 指向新地址：删除该主机名原有的IPv6行，追加一行新的，
+// 适用于 Pi-hole 的 /etc/pihole/custom.list、dnsmasq 的 addn-hosts 以及 /etc/hosts
+use std::net::Ipv6Addr;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use tracing::debug;
+
+use super::{Provider, ProviderUpdate};
+use crate::{BoxError, Config};
+
+pub struct HostsFile {
+    label: String,
+    path: String,
+    hostnames: Vec<String>,
+}
+
+impl HostsFile {
+    pub fn from_config(config: &Config) -> Result<Vec<Box<dyn Provider>>, BoxError> {
+        let path = config.hosts_file.clone().ok_or("HOSTS_FILE must be set")?;
+        let hostnames: Vec<String> = config
+            .hosts_file_hostname
+            .as_deref()
+            .ok_or("HOSTS_FILE_HOSTNAME must be set")?
+            .split([',', ' '])
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        if hostnames.is_empty() {
+            return Err("HOSTS_FILE_HOSTNAME must contain at least one hostname".into());
+        }
+
+        Ok(vec![Box::new(Self {
+            label: format!("hosts_file:{}", path),
+            path,
+            hostnames,
+        })])
+    }
+}
+
+#[async_trait]
+impl Provider for HostsFile {
+    fn name(&self) -> &str {
+        &self.label
+    }
+
+    fn hostname(&self) -> Option<String> {
+        self.hostnames.first().cloned()
+    }
+
+    async fn update(&self, _client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+        // 文件不存在时新建
+        let current = match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("Failed to read hosts file '{}': {}", self.path, e).into()),
+        };
+
+        let updated = rewrite(&current, &self.hostnames, ipv6);
+        if updated == current {
+            return Ok(ProviderUpdate {
+                changed: false,
+                response: format!("{} already points to {}", self.hostnames.join(" "), ipv6),
+            });
+        }
+
+        // 先写临时文件再改名，读取方（如 dnsmasq）不会看到写了一半的文件
+        let tmp_path = format!("{}.tmp", self.path);
+        tokio::fs::write(&tmp_path, &updated)
+            .await
+            .map_err(|e| format!("Failed to write '{}': {}", tmp_path, e))?;
+        tokio::fs::rename(&tmp_path, &self.path)
+            .await
+            .map_err(|e| format!("Failed to replace hosts file '{}': {}", self.path, e))?;
+        debug!("Wrote {} {} to {}", ipv6, self.hostnames.join(" "), self.path);
+
+        Ok(ProviderUpdate {
+            changed: true,
+            response: format!("{} now points to {}", self.hostnames.join(" "), ipv6),
+        })
+    }
+}
+
+// 删除只包含这些主机名的IPv6行，其他行（注释、IPv4、其他主机名）原样保留，然后追加新行
+fn rewrite(contents: &str, hostnames: &[String], ipv6: &str) -> String {
+    let mut output = String::with_capacity(contents.len() + 64);
+    for line in contents.lines() {
+        let mut fields = line.split('#').next().unwrap_or_default().split_whitespace();
+        let is_ipv6 = fields.next().is_some_and(|address| address.parse::<Ipv6Addr>().is_ok());
+        let names: Vec<&str> = fields.collect();
+        if is_ipv6 && !names.is_empty() && names.iter().all(|name| hostnames.iter().any(|hostname| hostname == name)) {
+            continue;
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    output.push_str(&format!("{} {}\n", ipv6, hostnames.join(" ")));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite() {
+        let hostnames = vec!["nas.lan".to_string()];
+        let contents = "# local\n192.168.1.2 nas.lan\n2001:db8::1 nas.lan\n2001:db8::9 printer.lan\n";
+        assert_eq!(
+            rewrite(contents, &hostnames, "2001:db8::2"),
+            "# local\n192.168.1.2 nas.lan\n2001:db8::9 printer.lan\n2001:db8::2 nas.lan\n"
+        );
+
+        // 已经是最新时内容不变
+        let updated = rewrite(contents, &hostnames, "2001:db8::2");
+        assert_eq!(rewrite(&updated, &hostnames, "2001:db8::2"), updated);
+
+        assert_eq!(rewrite("", &hostnames, "2001:db8::2"), "2001:db8::2 nas.lan\n");
+    }
+
+    #[tokio::test]
+    async fn test_update_writes_file() {
+        let path = std::env::temp_dir().join(format!("rs-refresh-ddns-hosts-{}", std::process::id()));
+        let provider = HostsFile {
+            label: String::new(),
+            path: path.to_string_lossy().to_string(),
+            hostnames: vec!["nas.lan".to_string()],
+        };
+
+        let update = provider.update(&Client::new(), "2001:db8::1").await.unwrap();
+        assert!(update.changed);
+        let update = provider.update(&Client::new(), "2001:db8::1").await.unwrap();
+        assert!(!update.changed);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "2001:db8::1 nas.lan\n");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,245 @@
+// INWX 提供商
+// 通过 XML-RPC 接口先 account.login 取得会话 Cookie，再用 nameserver.updateRecord 按记录ID修改内容，最后 account.logout
+// 响应中 code 为 1xxx 表示成功，其余（以及 XML-RPC fault）都视为失败
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::header::{COOKIE, SET_COOKIE};
+use reqwest::Client;
+use tracing::{debug, warn};
+
+use super::{log_egress, status_error, with_timeout, xml_escape, xml_tag, Provider, ProviderUpdate};
+use crate::{read_body_limited, BoxError, Config};
+
+const INWX_ENDPOINT: &str = "https://api.domrobot.com/xmlrpc/";
+// 登录后返回的会话 Cookie 名
+const SESSION_COOKIE: &str = "domrobot";
+
+pub struct Inwx {
+    label: String,
+    endpoint: String,
+    user: String,
+    pass: String,
+    record_id: u64,
+    timeout: Option<Duration>,
+    // 响应体的最大字节数
+    max_response_bytes: usize,
+}
+
+// XML-RPC 结构体成员的值
+enum Value<'a> {
+    Int(u64),
+    Str(&'a str),
+}
+
+impl Inwx {
+    pub fn from_config(config: &Config) -> Result<Vec<Box<dyn Provider>>, BoxError> {
+        let record_id = config.inwx_record_id.ok_or("INWX_RECORD_ID must be set")?;
+        Ok(vec![Box::new(Self {
+            label: format!("inwx:{}", record_id),
+            endpoint: INWX_ENDPOINT.to_string(),
+            user: config.inwx_user.clone().ok_or("INWX_USER must be set")?,
+            pass: config.inwx_pass.clone().ok_or("INWX_PASS must be set")?,
+            record_id,
+            timeout: config.provider_timeout(),
+            max_response_bytes: config.max_response_bytes,
+        })])
+    }
+
+    // 登录并返回会话 Cookie
+    async fn login(&self, client: &Client) -> Result<String, BoxError> {
+        let body = method_call("account.login", &[("user", Value::Str(&self.user)), ("pass", Value::Str(&self.pass))]);
+        let response = with_timeout(client.post(&self.endpoint).body(body), self.timeout).send().await?;
+        log_egress(self.name(), &response);
+        let cookie = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| value.split(';').next())
+            .find(|pair| pair.trim_start().starts_with(&format!("{}=", SESSION_COOKIE)))
+            .map(|pair| pair.trim().to_string());
+
+        let status = response.status();
+        let text = read_body_limited(response, self.max_response_bytes)
+            .await
+            .map_err(|e| format!("INWX login response rejected: {}", e))?;
+        debug!("INWX login response: {}", text);
+        parse_response(&text).map_err(|e| status_error(status, format!("INWX login failed: {}", e)))?;
+        // 开启了两步验证的账号还需要 account.unlock，这里不支持
+        if member(&text, "tfa").is_some_and(|tfa| !matches!(tfa, "" | "0")) {
+            return Err("INWX account has two-factor authentication enabled, which is not supported".into());
+        }
+        cookie.ok_or_else(|| "INWX login succeeded but returned no session cookie".into())
+    }
+
+    // 在已登录的会话中调用方法，返回成功响应的消息
+    async fn call(&self, client: &Client, cookie: &str, method: &str, params: &[(&str, Value<'_>)]) -> Result<String, BoxError> {
+        let request = client.post(&self.endpoint).header(COOKIE, cookie).body(method_call(method, params));
+        let response = with_timeout(request, self.timeout).send().await?;
+        let status = response.status();
+        let text = read_body_limited(response, self.max_response_bytes)
+            .await
+            .map_err(|e| format!("INWX {} response rejected: {}", method, e))?;
+        debug!("INWX {} response: {}", method, text);
+        parse_response(&text).map_err(|e| status_error(status, format!("INWX {} failed: {}", method, e)))
+    }
+
+    // 登出失败不影响结果，会话会自动过期
+    async fn logout(&self, client: &Client, cookie: &str) {
+        if let Err(e) = self.call(client, cookie, "account.logout", &[]).await {
+            warn!("{}", e);
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Inwx {
+    fn name(&self) -> &str {
+        &self.label
+    }
+
+    async fn update(&self, client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+        debug!("Updating INWX record {}", self.record_id);
+        let cookie = self.login(client).await?;
+        let result = self
+            .call(
+                client,
+                &cookie,
+                "nameserver.updateRecord",
+                &[("id", Value::Int(self.record_id)), ("content", Value::Str(ipv6))],
+            )
+            .await;
+        self.logout(client, &cookie).await;
+
+        // updateRecord 不报告内容是否真的变化，成功即视为已更新
+        Ok(ProviderUpdate {
+            changed: true,
+            response: result?,
+        })
+    }
+
+    // 只读取记录，验证凭证和记录ID，不修改记录
+    async fn check(&self, client: &Client, _ipv6: &str) -> Result<String, BoxError> {
+        let cookie = self.login(client).await?;
+        let result = self
+            .call(client, &cookie, "nameserver.info", &[("recordId", Value::Int(self.record_id))])
+            .await;
+        self.logout(client, &cookie).await;
+        result?;
+        Ok(format!("record {} readable", self.record_id))
+    }
+}
+
+// 生成以一个结构体为参数的 XML-RPC 请求
+fn method_call(method: &str, params: &[(&str, Value<'_>)]) -> String {
+    let members: String = params
+        .iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Value::Int(n) => format!("<int>{}</int>", n),
+                Value::Str(s) => format!("<string>{}</string>", xml_escape(s)),
+            };
+            format!("<member><name>{}</name><value>{}</value></member>", name, value)
+        })
+        .collect();
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            "<methodCall><methodName>{}</methodName><params><param><value><struct>{}</struct></value></param></params></methodCall>"
+        ),
+        method, members
+    )
+}
+
+// 检查响应的 code，成功时返回 msg；fault 和 2xxx 错误码转换为带原因的错误
+fn parse_response(xml: &str) -> Result<String, BoxError> {
+    if xml.contains("<fault>") {
+        let code = member(xml, "faultCode").unwrap_or("?");
+        let message = member(xml, "faultString").unwrap_or("unknown fault");
+        return Err(format!("XML-RPC fault {}: {}", code, message).into());
+    }
+
+    let code = member(xml, "code").ok_or_else(|| format!("Unexpected INWX response: {}", xml.trim()))?;
+    let message = member(xml, "msg").unwrap_or_default();
+    if !code.starts_with('1') {
+        return Err(match member(xml, "reason") {
+            Some(reason) => format!("code {}: {} ({})", code, message, reason),
+            None => format!("code {}: {}", code, message),
+        }
+        .into());
+    }
+    Ok(message.to_string())
+}
+
+// 取结构体中第一个名为 name 的成员的值，去掉 <int>、<string> 等类型标签
+fn member<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<name>{}</name>", name))?;
+    let value = xml_tag(&xml[start..], "value")?;
+    let value = match value.strip_prefix('<') {
+        Some(tagged) => {
+            let open_end = tagged.find('>')?;
+            let close_start = tagged.rfind("</")?;
+            tagged.get(open_end + 1..close_start)?
+        }
+        None => value,
+    };
+    Some(value.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(members: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\"?><methodResponse><params><param><value><struct>{}</struct></value></param></params></methodResponse>",
+            members
+        )
+    }
+
+    #[test]
+    fn test_method_call() {
+        let body = method_call(
+            "nameserver.updateRecord",
+            &[("id", Value::Int(42)), ("content", Value::Str("a<b&c"))],
+        );
+        assert!(body.contains("<methodName>nameserver.updateRecord</methodName>"));
+        assert!(body.contains("<member><name>id</name><value><int>42</int></value></member>"));
+        assert!(body.contains("<member><name>content</name><value><string>a&lt;b&amp;c</string></value></member>"));
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let ok = response(
+            "<member><name>code</name><value><int>1000</int></value></member>\
+             <member><name>msg</name><value><string>Command completed successfully</string></value></member>",
+        );
+        assert_eq!(parse_response(&ok).unwrap(), "Command completed successfully");
+
+        let failed = response(
+            "<member><name>code</name><value><int>2303</int></value></member>\
+             <member><name>msg</name><value><string>Object does not exist</string></value></member>\
+             <member><name>reason</name><value><string>record not found</string></value></member>",
+        );
+        assert_eq!(
+            parse_response(&failed).unwrap_err().to_string(),
+            "code 2303: Object does not exist (record not found)"
+        );
+
+        let fault = "<methodResponse><fault><value><struct>\
+             <member><name>faultCode</name><value><int>2400</int></value></member>\
+             <member><name>faultString</name><value><string>Command failed</string></value></member>\
+             </struct></value></fault></methodResponse>";
+        assert_eq!(parse_response(fault).unwrap_err().to_string(), "XML-RPC fault 2400: Command failed");
+
+        assert!(parse_response("<html>").is_err());
+    }
+
+    #[test]
+    fn test_member_without_type_tag() {
+        let xml = response("<member><name>tfa</name><value>0</value></member>");
+        assert_eq!(member(&xml, "tfa"), Some("0"));
+        assert_eq!(member(&xml, "code"), None);
+    }
+}
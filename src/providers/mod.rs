@@ -0,0 +1,541 @@
+// DNS 服务提供商
+// 每个提供商由独立的 cargo feature 控制是否编译，保持二进制精简
+use std::fmt;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, ClientBuilder, RequestBuilder, Response, StatusCode};
+use tracing::{debug, warn};
+
+use crate::{http_client_builder, proxy, BoxError, Config};
+
+#[cfg(feature = "duckdns")]
+mod duckdns;
+#[cfg(feature = "dyndns2")]
+mod dyndns2;
+#[cfg(feature = "cloudflare")]
+mod cloudflare;
+#[cfg(feature = "hosts_file")]
+mod hosts_file;
+#[cfg(feature = "inwx")]
+mod inwx;
+#[cfg(feature = "porkbun")]
+mod porkbun;
+#[cfg(feature = "route53")]
+mod route53;
+#[cfg(feature = "template")]
+mod template;
+
+// 所有已知的提供商名称（无论本次构建是否启用）
+const KNOWN_PROVIDERS: &[&str] = &["duckdns", "dyndns2", "cloudflare", "template", "route53", "inwx", "porkbun", "hosts_file"];
+
+// 一次提供商更新的结果
+pub struct ProviderUpdate {
+    // 提供商记录是否发生了变化（false 表示地址未变）
+    pub changed: bool,
+    // 提供商返回的原始响应
+    pub response: String,
+}
+
+#[async_trait]
+pub trait Provider: Send + Sync {
+    // 提供商名称，用于日志
+    fn name(&self) -> &str;
+
+    // 提供商更新的完整域名，用于 verify_via_dns 查询当前记录；无法确定时返回 None
+    fn hostname(&self) -> Option<String> {
+        None
+    }
+
+    // 将IPv6地址发布到提供商
+    async fn update(&self, client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError>;
+
+    // --show-url：本次更新将请求的地址，其中的令牌等密钥替换为 "***"；不是通过单个请求地址更新的提供商返回 None
+    fn update_url(&self, _ipv6: &str) -> Result<Option<String>, BoxError> {
+        Ok(None)
+    }
+
+    // --test-providers：检查凭证和配置是否可用，不应改变记录，成功时返回说明
+    // 默认用当前地址更新一次，对没有只读接口的提供商（如 DuckDNS）相当于空操作
+    async fn check(&self, client: &Client, ipv6: &str) -> Result<String, BoxError> {
+        let update = self.update(client, ipv6).await?;
+        Ok(if update.changed {
+            "ok (record was out of date and has been updated)".to_string()
+        } else {
+            "ok".to_string()
+        })
+    }
+}
+
+// 暂时性的失败（HTTP 5xx、429，或提供商要求稍后再试），provider_retries 只重试这类错误和网络错误；
+// 其他错误（如认证失败、域名不存在）重试也不会成功，反而会消耗提供商的限流额度
+#[derive(Debug)]
+#[cfg_attr(
+    not(any(
+        feature = "duckdns",
+        feature = "dyndns2",
+        feature = "cloudflare",
+        feature = "template",
+        feature = "route53",
+        feature = "inwx",
+        feature = "porkbun"
+    )),
+    allow(dead_code)
+)]
+pub struct Transient(pub String);
+
+impl fmt::Display for Transient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Transient {}
+
+// 提供商更新失败后是否值得重试：连接失败、超时等网络错误，以及标记为暂时性的失败
+pub fn is_retryable(error: &BoxError) -> bool {
+    if error.is::<Transient>() {
+        return true;
+    }
+    match error.downcast_ref::<reqwest::Error>() {
+        Some(e) => match e.status() {
+            Some(status) => is_transient_status(status),
+            None => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+        },
+        None => false,
+    }
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+// 请求失败时按 HTTP 状态标记能否重试：5xx 和 429 为暂时性的失败，其他状态保持原来的错误
+#[cfg_attr(
+    not(any(
+        feature = "duckdns",
+        feature = "dyndns2",
+        feature = "cloudflare",
+        feature = "template",
+        feature = "route53",
+        feature = "inwx",
+        feature = "porkbun"
+    )),
+    allow(dead_code)
+)]
+fn status_error(status: StatusCode, error: impl Into<BoxError>) -> BoxError {
+    let error = error.into();
+    if is_transient_status(status) {
+        Box::new(Transient(error.to_string()))
+    } else {
+        error
+    }
+}
+
+// 给错误换一个说明，保留能否重试
+#[cfg(feature = "route53")]
+fn map_message(error: BoxError, message: impl FnOnce(&BoxError) -> String) -> BoxError {
+    let text = message(&error);
+    if is_retryable(&error) {
+        Box::new(Transient(text))
+    } else {
+        text.into()
+    }
+}
+
+// 演练模式：只记录将要发布的地址，不调用提供商
+struct DryRun(Box<dyn Provider>);
+
+#[async_trait]
+impl Provider for DryRun {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn hostname(&self) -> Option<String> {
+        self.0.hostname()
+    }
+
+    fn update_url(&self, ipv6: &str) -> Result<Option<String>, BoxError> {
+        self.0.update_url(ipv6)
+    }
+
+    async fn update(&self, _client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+        warn!("Dry run: not publishing {} to '{}'", ipv6, self.0.name());
+        Ok(ProviderUpdate {
+            changed: false,
+            response: format!("dry run, would publish {}", ipv6),
+        })
+    }
+}
+
+// 配置了 provider_proxy 时：忽略传入的客户端，改用经过代理的客户端
+struct Proxied {
+    inner: Box<dyn Provider>,
+    client: Client,
+}
+
+#[async_trait]
+impl Provider for Proxied {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn hostname(&self) -> Option<String> {
+        self.inner.hostname()
+    }
+
+    fn update_url(&self, ipv6: &str) -> Result<Option<String>, BoxError> {
+        self.inner.update_url(ipv6)
+    }
+
+    async fn update(&self, _client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+        self.inner.update(&self.client, ipv6).await
+    }
+
+    async fn check(&self, _client: &Client, ipv6: &str) -> Result<String, BoxError> {
+        self.inner.check(&self.client, ipv6).await
+    }
+}
+
+// 提供商自己创建客户端时使用，配置了 provider_proxy 时带上代理
+fn client_builder(config: &Config) -> Result<ClientBuilder, BoxError> {
+    let builder = http_client_builder(config)?;
+    match config.provider_proxy.as_deref() {
+        Some(spec) => proxy::apply(builder, spec),
+        None => Ok(builder),
+    }
+}
+
+// 更新某个提供商期间的日志 span，每行日志都带上提供商名称和域名（如果有），多域名时便于区分交错的日志
+// span 在低于其级别的日志级别下不生效，使用 error 级别使任何日志级别下都带有这些字段
+pub fn log_span(provider: &dyn Provider) -> tracing::Span {
+    match provider.hostname() {
+        Some(domain) => tracing::error_span!("provider", name = %provider.name(), domain = %domain),
+        None => tracing::error_span!("provider", name = %provider.name()),
+    }
+}
+
+// 把提供商包装为演练模式
+pub fn dry_run(providers: Vec<Box<dyn Provider>>) -> Vec<Box<dyn Provider>> {
+    providers
+        .into_iter()
+        .map(|provider| Box::new(DryRun(provider)) as Box<dyn Provider>)
+        .collect()
+}
+
+// 根据配置创建所有启用的提供商
+pub fn build_providers(config: &Config) -> Result<Vec<Box<dyn Provider>>, BoxError> {
+    if config.providers().is_empty() {
+        return Err("No provider configured".into());
+    }
+    if !matches!(config.provider_mode.as_str(), "all" | "failover") {
+        return Err(format!("Invalid PROVIDER_MODE: {}. Expected all or failover", config.provider_mode).into());
+    }
+
+    let mut providers = Vec::new();
+    for name in config.providers() {
+        providers.extend(build_provider(name, config)?);
+    }
+    if config.provider_proxy.is_some() {
+        let client = client_builder(config)?.build()?;
+        providers = providers
+            .into_iter()
+            .map(|inner| {
+                Box::new(Proxied {
+                    inner,
+                    client: client.clone(),
+                }) as Box<dyn Provider>
+            })
+            .collect();
+    }
+    Ok(providers)
+}
+
+// 与公网提供商一起更新的内网提供商（local_providers），可以为空
+pub fn build_local_providers(config: &Config) -> Result<Vec<Box<dyn Provider>>, BoxError> {
+    let mut providers = Vec::new();
+    for name in &config.local_providers {
+        providers.extend(build_provider(name, config)?);
+    }
+    Ok(providers)
+}
+
+// 一个提供商配置可能展开为多个更新目标（例如多个域名）；不启用任何提供商时 config 不会被使用
+#[cfg_attr(
+    not(any(
+        feature = "duckdns",
+        feature = "dyndns2",
+        feature = "cloudflare",
+        feature = "template",
+        feature = "route53",
+        feature = "inwx",
+        feature = "porkbun",
+        feature = "hosts_file"
+    )),
+    allow(unused_variables)
+)]
+fn build_provider(name: &str, config: &Config) -> Result<Vec<Box<dyn Provider>>, BoxError> {
+    match name {
+        #[cfg(feature = "duckdns")]
+        "duckdns" => duckdns::DuckDns::from_config(config),
+        #[cfg(feature = "dyndns2")]
+        "dyndns2" => dyndns2::DynDns2::from_config(config),
+        #[cfg(feature = "cloudflare")]
+        "cloudflare" => cloudflare::Cloudflare::from_config(config),
+        #[cfg(feature = "template")]
+        "template" => template::Template::from_config(config),
+        #[cfg(feature = "route53")]
+        "route53" => route53::Route53::from_config(config),
+        #[cfg(feature = "inwx")]
+        "inwx" => inwx::Inwx::from_config(config),
+        #[cfg(feature = "porkbun")]
+        "porkbun" => porkbun::Porkbun::from_config(config),
+        #[cfg(feature = "hosts_file")]
+        "hosts_file" => hosts_file::HostsFile::from_config(config),
+        _ if KNOWN_PROVIDERS.contains(&name) => {
+            Err(format!("provider '{}' not enabled in this build", name).into())
+        }
+        _ => Err(format!("unknown provider '{}'", name).into()),
+    }
+}
+
+// 将响应头格式化为一行，便于排查限流、CDN 等问题
+#[cfg_attr(not(feature = "duckdns"), allow(dead_code))]
+fn format_headers(headers: &HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, String::from_utf8_lossy(value.as_bytes())))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// debug 级别下记录请求到达的服务器地址，以及本机发出请求所用的源地址和接口，用于排查非对称路由
+#[cfg_attr(
+    not(any(
+        feature = "duckdns",
+        feature = "dyndns2",
+        feature = "cloudflare",
+        feature = "template",
+        feature = "route53",
+        feature = "inwx",
+        feature = "porkbun"
+    )),
+    allow(dead_code)
+)]
+fn log_egress(provider: &str, response: &Response) {
+    if !tracing::enabled!(tracing::Level::DEBUG) {
+        return;
+    }
+    let Some(remote) = response.remote_addr() else {
+        return;
+    };
+    match crate::local::egress_for(remote) {
+        Ok((source, interface)) => debug!(
+            "Provider '{}' reached {} from {} via interface {}",
+            provider,
+            remote,
+            source,
+            interface.as_deref().unwrap_or("(unknown)")
+        ),
+        Err(e) => debug!("Provider '{}' reached {}, failed to determine the source address: {}", provider, remote, e),
+    }
+}
+
+// provider_timeout_secs 或 http_timeout_secs 设置时覆盖客户端的超时
+#[cfg_attr(
+    not(any(
+        feature = "duckdns",
+        feature = "dyndns2",
+        feature = "cloudflare",
+        feature = "template",
+        feature = "route53",
+        feature = "inwx",
+        feature = "porkbun"
+    )),
+    allow(dead_code)
+)]
+fn with_timeout(request: RequestBuilder, timeout: Option<Duration>) -> RequestBuilder {
+    match timeout {
+        Some(timeout) => request.timeout(timeout),
+        None => request,
+    }
+}
+
+// 提供商记录的备注，record_comment 中的 {time} 替换为当前时间（UTC）
+#[cfg_attr(not(any(feature = "route53", feature = "cloudflare")), allow(dead_code))]
+fn render_comment(template: &str) -> Result<String, BoxError> {
+    let time = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    render_template(template, &[("time", &time)])
+}
+
+// 用户提供的文本（备注、密码等）放进XML前需要转义
+#[cfg(any(feature = "route53", feature = "inwx"))]
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// 取第一个 <tag>...</tag> 的内容
+#[cfg(any(feature = "route53", feature = "inwx"))]
+fn xml_tag<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = xml[start..].find(&format!("</{}>", tag))? + start;
+    Some(xml[start..end].trim())
+}
+
+// 展开结果的上限，防止写错的范围（如 {1..100000}）生成大量提供商
+const MAX_PATTERN_EXPANSION: usize = 1000;
+
+// 展开域名模式：{a,b,c} 为列表，{1..3} 为数字范围（起点带前导零时保持宽度），多组时取所有组合；
+// 括号外的逗号分隔多个模式。例如 "{a,b}.home,nas{1..2}" 展开为 a.home、b.home、nas1、nas2
+#[cfg_attr(not(feature = "duckdns"), allow(dead_code))]
+fn expand_pattern(pattern: &str) -> Result<Vec<String>, BoxError> {
+    let mut expanded = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in pattern.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                expanded.extend(expand_braces(pattern[start..i].trim(), pattern)?);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    expanded.extend(expand_braces(pattern[start..].trim(), pattern)?);
+    expanded.retain(|item| !item.is_empty());
+
+    if expanded.len() > MAX_PATTERN_EXPANSION {
+        return Err(format!("Pattern '{}' expands to more than {} names", pattern, MAX_PATTERN_EXPANSION).into());
+    }
+    Ok(expanded)
+}
+
+// 展开一个不含顶层逗号的模式中的所有括号组
+fn expand_braces(item: &str, pattern: &str) -> Result<Vec<String>, BoxError> {
+    let Some(open) = item.find('{') else {
+        if item.contains('}') {
+            return Err(format!("Unmatched '}}' in pattern '{}'", pattern).into());
+        }
+        return Ok(vec![item.to_string()]);
+    };
+    let close = item[open..]
+        .find('}')
+        .map(|end| open + end)
+        .ok_or_else(|| format!("Unterminated '{{' in pattern '{}'", pattern))?;
+    let group = &item[open + 1..close];
+    if group.contains('{') {
+        return Err(format!("Nested braces are not supported in pattern '{}'", pattern).into());
+    }
+
+    let alternatives: Vec<String> = match group.split_once("..") {
+        Some((from, to)) => {
+            let (first, last): (u64, u64) = match (from.trim().parse(), to.trim().parse()) {
+                (Ok(first), Ok(last)) if first <= last => (first, last),
+                _ => return Err(format!("Invalid range '{{{}}}' in pattern '{}'", group, pattern).into()),
+            };
+            if last - first >= MAX_PATTERN_EXPANSION as u64 {
+                return Err(format!("Pattern '{}' expands to more than {} names", pattern, MAX_PATTERN_EXPANSION).into());
+            }
+            let width = if from.trim().starts_with('0') { from.trim().len() } else { 0 };
+            (first..=last).map(|n| format!("{:0width$}", n, width = width)).collect()
+        }
+        None => group.split(',').map(|alternative| alternative.trim().to_string()).collect(),
+    };
+
+    let rest = expand_braces(&item[close + 1..], pattern)?;
+    let mut expanded = Vec::with_capacity(alternatives.len() * rest.len());
+    for alternative in &alternatives {
+        for suffix in &rest {
+            expanded.push(format!("{}{}{}", &item[..open], alternative, suffix));
+        }
+        if expanded.len() > MAX_PATTERN_EXPANSION {
+            return Err(format!("Pattern '{}' expands to more than {} names", pattern, MAX_PATTERN_EXPANSION).into());
+        }
+    }
+    Ok(expanded)
+}
+
+// 替换模板中的 {name} 占位符，遇到未知的占位符时报错，避免把错误的地址发给提供商
+pub(crate) fn render_template(template: &str, vars: &[(&str, &str)]) -> Result<String, BoxError> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unterminated placeholder in template '{}'", template))?;
+        let name = &rest[start + 1..start + end];
+        let (_, value) = vars
+            .iter()
+            .find(|(var, _)| *var == name)
+            .ok_or_else(|| format!("Unknown placeholder '{{{}}}' in template '{}'", name, template))?;
+        output.push_str(value);
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dry_run_does_not_call_provider() {
+        struct Failing;
+
+        #[async_trait]
+        impl Provider for Failing {
+            fn name(&self) -> &str {
+                "failing"
+            }
+
+            async fn update(&self, _client: &Client, _ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+                Err("should not be called".into())
+            }
+        }
+
+        let providers = dry_run(vec![Box::new(Failing)]);
+        let update = providers[0].update(&Client::new(), "2001:db8::1").await.unwrap();
+        assert!(!update.changed);
+        assert_eq!(providers[0].name(), "failing");
+    }
+
+    #[test]
+    fn test_render_comment() {
+        let comment = render_comment("updated by rs-refresh-ddns at {time}").unwrap();
+        assert!(comment.starts_with("updated by rs-refresh-ddns at 20"), "{}", comment);
+        assert!(comment.ends_with('Z'), "{}", comment);
+        assert!(render_comment("{unknown}").is_err());
+    }
+
+    #[test]
+    fn test_expand_pattern() {
+        assert_eq!(expand_pattern("{a,b,c}.example").unwrap(), vec!["a.example", "b.example", "c.example"]);
+        assert_eq!(expand_pattern("nas{1..3}").unwrap(), vec!["nas1", "nas2", "nas3"]);
+        assert_eq!(expand_pattern("n{08..10}").unwrap(), vec!["n08", "n09", "n10"]);
+        assert_eq!(expand_pattern("{a,b}{1..2}").unwrap(), vec!["a1", "a2", "b1", "b2"]);
+        assert_eq!(expand_pattern("home, {a,b}.lab").unwrap(), vec!["home", "a.lab", "b.lab"]);
+        assert!(expand_pattern("{a,b").is_err());
+        assert!(expand_pattern("a}").is_err());
+        assert!(expand_pattern("{3..1}").is_err());
+        assert!(expand_pattern("{1..100000}").is_err());
+        assert!(expand_pattern("{{a,b}}").is_err());
+    }
+
+    #[test]
+    fn test_render_template() {
+        let vars = [("domain", "home"), ("ip", "2001:db8::1")];
+        assert_eq!(
+            render_template("https://example.com/nic?host={domain}&ip={ip}", &vars).unwrap(),
+            "https://example.com/nic?host=home&ip=2001:db8::1"
+        );
+        assert_eq!(render_template("no placeholders", &vars).unwrap(), "no placeholders");
+        assert!(render_template("https://example.com/?key={token}", &vars).is_err());
+        assert!(render_template("https://example.com/?ip={ip", &vars).is_err());
+    }
+}
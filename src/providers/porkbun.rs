@@ -0,0 +1,223 @@
+// Porkbun 提供商
+// 通过 JSON API 先用 retrieveByNameType 读取当前的 AAAA 记录，地址不同时再用 editByNameType 修改；
+// 每个请求的 JSON 请求体中都带 apikey 和 secretapikey，响应中 status 为 SUCCESS 表示成功，否则 message 给出原因
+use std::net::Ipv6Addr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use tracing::debug;
+
+use super::{log_egress, status_error, with_timeout, Provider, ProviderUpdate};
+use crate::{read_body_limited, BoxError, Config};
+
+const PORKBUN_ENDPOINT: &str = "https://api.porkbun.com/api/json/v3";
+
+pub struct Porkbun {
+    label: String,
+    endpoint: String,
+    apikey: String,
+    secretkey: String,
+    domain: String,
+    // 为空时表示根域名本身
+    subdomain: String,
+    timeout: Option<Duration>,
+    // 响应体的最大字节数
+    max_response_bytes: usize,
+}
+
+impl Porkbun {
+    pub fn from_config(config: &Config) -> Result<Vec<Box<dyn Provider>>, BoxError> {
+        let domain = config.porkbun_domain.clone().ok_or("PORKBUN_DOMAIN must be set")?;
+        let subdomain = config.porkbun_subdomain.as_deref().unwrap_or_default().trim().to_string();
+        let provider = Self {
+            label: String::new(),
+            endpoint: PORKBUN_ENDPOINT.to_string(),
+            apikey: config.porkbun_apikey.clone().ok_or("PORKBUN_APIKEY must be set")?,
+            secretkey: config.porkbun_secretkey.clone().ok_or("PORKBUN_SECRETKEY must be set")?,
+            domain,
+            subdomain,
+            timeout: config.provider_timeout(),
+            max_response_bytes: config.max_response_bytes,
+        };
+        Ok(vec![Box::new(Self {
+            label: format!("porkbun:{}", provider.fqdn()),
+            ..provider
+        })])
+    }
+
+    fn fqdn(&self) -> String {
+        if self.subdomain.is_empty() {
+            self.domain.clone()
+        } else {
+            format!("{}.{}", self.subdomain, self.domain)
+        }
+    }
+
+    // 如 {endpoint}/dns/retrieveByNameType/example.com/AAAA/home，根域名时省略最后一段
+    fn url(&self, action: &str) -> String {
+        let mut url = format!("{}/dns/{}/{}/AAAA", self.endpoint, action, self.domain);
+        if !self.subdomain.is_empty() {
+            url.push('/');
+            url.push_str(&self.subdomain);
+        }
+        url
+    }
+
+    async fn call(&self, client: &Client, action: &str, mut body: Value) -> Result<Value, BoxError> {
+        body["apikey"] = json!(self.apikey);
+        body["secretapikey"] = json!(self.secretkey);
+        let request = client
+            .post(self.url(action))
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+        let response = with_timeout(request, self.timeout).send().await?;
+        log_egress(self.name(), &response);
+
+        let status = response.status();
+        let text = read_body_limited(response, self.max_response_bytes)
+            .await
+            .map_err(|e| format!("Porkbun {} response rejected: {}", action, e))?;
+        debug!("Porkbun {} response - Status: {}, Body: {}", action, status, text);
+        parse_response(&text).map_err(|e| status_error(status, format!("Porkbun {} failed (HTTP {}): {}", action, status, e)))
+    }
+
+    // 当前 AAAA 记录的内容，没有记录时报错（editByNameType 只修改已有记录）
+    async fn current_records(&self, client: &Client) -> Result<Vec<String>, BoxError> {
+        let response = self.call(client, "retrieveByNameType", json!({})).await?;
+        let records = record_contents(&response);
+        if records.is_empty() {
+            return Err(format!("Porkbun has no AAAA record for {}, create it first", self.fqdn()).into());
+        }
+        Ok(records)
+    }
+}
+
+#[async_trait]
+impl Provider for Porkbun {
+    fn name(&self) -> &str {
+        &self.label
+    }
+
+    fn hostname(&self) -> Option<String> {
+        Some(self.fqdn())
+    }
+
+    async fn update(&self, client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+        debug!("Updating Porkbun AAAA record {}", self.fqdn());
+        let records = self.current_records(client).await?;
+        if records.iter().all(|content| same_address(content, ipv6)) {
+            return Ok(ProviderUpdate {
+                changed: false,
+                response: format!("{} already points to {}", self.fqdn(), ipv6),
+            });
+        }
+
+        self.call(client, "editByNameType", json!({ "content": ipv6 })).await?;
+        Ok(ProviderUpdate {
+            changed: true,
+            response: format!("{} now points to {} (was {})", self.fqdn(), ipv6, records.join(", ")),
+        })
+    }
+
+    // 只读取记录，验证密钥、域名的 API Access 以及记录是否存在，不修改记录
+    async fn check(&self, client: &Client, _ipv6: &str) -> Result<String, BoxError> {
+        let records = self.current_records(client).await?;
+        Ok(format!("record {} readable (currently {})", self.fqdn(), records.join(", ")))
+    }
+}
+
+// status 不是 SUCCESS 时把 message 作为错误返回
+fn parse_response(text: &str) -> Result<Value, BoxError> {
+    let value: Value = serde_json::from_str(text).map_err(|_| format!("Unexpected Porkbun response: {}", text.trim()))?;
+    match value["status"].as_str() {
+        Some("SUCCESS") => Ok(value),
+        _ => Err(value["message"].as_str().unwrap_or("unknown error").to_string().into()),
+    }
+}
+
+fn record_contents(response: &Value) -> Vec<String> {
+    response["records"]
+        .as_array()
+        .map(|records| {
+            records
+                .iter()
+                .filter_map(|record| record["content"].as_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// 按地址比较，记录中的写法（如大小写、省略零）可能与检测到的不同
+fn same_address(content: &str, ipv6: &str) -> bool {
+    match (content.parse::<Ipv6Addr>(), ipv6.parse::<Ipv6Addr>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => content == ipv6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(endpoint: &str, subdomain: &str) -> Porkbun {
+        Porkbun {
+            label: String::new(),
+            endpoint: endpoint.to_string(),
+            apikey: "pk1_key".to_string(),
+            secretkey: "sk1_secret".to_string(),
+            domain: "example.com".to_string(),
+            subdomain: subdomain.to_string(),
+            timeout: None,
+            max_response_bytes: crate::DEFAULT_MAX_RESPONSE_BYTES,
+        }
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let ok = parse_response(r#"{"status":"SUCCESS","records":[{"type":"AAAA","content":"2001:db8::1"}]}"#).unwrap();
+        assert_eq!(record_contents(&ok), vec!["2001:db8::1"]);
+
+        let err = parse_response(r#"{"status":"ERROR","message":"Invalid API key. (002)"}"#).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid API key. (002)");
+        assert!(parse_response("<html>").is_err());
+    }
+
+    #[test]
+    fn test_url() {
+        assert_eq!(
+            provider(PORKBUN_ENDPOINT, "home").url("editByNameType"),
+            "https://api.porkbun.com/api/json/v3/dns/editByNameType/example.com/AAAA/home"
+        );
+        assert_eq!(
+            provider(PORKBUN_ENDPOINT, "").url("retrieveByNameType"),
+            "https://api.porkbun.com/api/json/v3/dns/retrieveByNameType/example.com/AAAA"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_unchanged_skips_edit() {
+        use crate::tests::{http_response, serve_once};
+
+        let body = r#"{"status":"SUCCESS","records":[{"type":"AAAA","content":"2001:DB8:0::1"}]}"#;
+        let (url, request) = serve_once(http_response("200 OK", body)).await;
+        let update = provider(&url, "home").update(&Client::new(), "2001:db8::1").await.unwrap();
+        assert!(!update.changed);
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST /dns/retrieveByNameType/example.com/AAAA/home "), "{}", request);
+        assert!(request.contains(r#""secretapikey":"sk1_secret""#), "{}", request);
+    }
+
+    #[tokio::test]
+    async fn test_update_rejects_oversized_response() {
+        use crate::tests::{http_response, serve_once};
+
+        let body = format!(r#"{{"status":"SUCCESS","records":[]{}}}"#, " ".repeat(crate::DEFAULT_MAX_RESPONSE_BYTES));
+        let (url, _) = serve_once(http_response("200 OK", &body)).await;
+        let err = provider(&url, "home").update(&Client::new(), "2001:db8::1").await.err().unwrap();
+        assert!(err.to_string().contains("Porkbun retrieveByNameType response rejected"), "{}", err);
+    }
+}
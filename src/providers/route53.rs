@@ -0,0 +1,369 @@
+// AWS Route 53 提供商
+// 直接调用 ChangeResourceRecordSets REST 接口 UPSERT AAAA 记录，请求使用 SigV4 签名，避免引入体积很大的 AWS SDK
+// 凭证依次从 AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY（可选 AWS_SESSION_TOKEN）环境变量和
+// 共享凭证文件（AWS_SHARED_CREDENTIALS_FILE 或 ~/.aws/credentials，profile 由 AWS_PROFILE 指定）读取
+use std::time::Duration;
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use super::{log_egress, map_message, render_comment, status_error, with_timeout, xml_escape, xml_tag, Provider, ProviderUpdate};
+use crate::{read_body_limited, BoxError, Config};
+
+const ROUTE53_ENDPOINT: &str = "https://route53.amazonaws.com";
+// Route 53 是全局服务，签名固定使用 us-east-1
+const ROUTE53_REGION: &str = "us-east-1";
+const DEFAULT_TTL: u32 = 300;
+// 未配置 record_comment 时的变更备注
+const DEFAULT_COMMENT: &str = "rs-refresh-ddns";
+
+pub struct Route53 {
+    label: String,
+    endpoint: String,
+    hosted_zone_id: String,
+    record_name: String,
+    ttl: u32,
+    // 变更批次的备注模板，可在 Route 53 的变更记录中看到
+    comment: String,
+    timeout: Option<Duration>,
+    // 响应体的最大字节数
+    max_response_bytes: usize,
+}
+
+impl Route53 {
+    pub fn from_config(config: &Config) -> Result<Vec<Box<dyn Provider>>, BoxError> {
+        let hosted_zone_id = config
+            .route53_hosted_zone_id
+            .as_deref()
+            .ok_or("ROUTE53_HOSTED_ZONE_ID must be set")?;
+        let record_name = config.route53_record_name.clone().ok_or("ROUTE53_RECORD_NAME must be set")?;
+        let comment = config.record_comment.clone().unwrap_or_else(|| DEFAULT_COMMENT.to_string());
+        // 启动时检查模板，避免到第一次更新时才发现占位符写错
+        render_comment(&comment)?;
+
+        Ok(vec![Box::new(Self {
+            label: format!("route53:{}", record_name),
+            endpoint: ROUTE53_ENDPOINT.to_string(),
+            // 控制台和 CLI 输出的 ID 可能带有 /hostedzone/ 前缀
+            hosted_zone_id: hosted_zone_id.trim_start_matches("/hostedzone/").to_string(),
+            record_name,
+            ttl: config.route53_ttl.unwrap_or(DEFAULT_TTL),
+            comment,
+            timeout: config.provider_timeout(),
+            max_response_bytes: config.max_response_bytes,
+        })])
+    }
+
+    fn change_batch(&self, ipv6: &str, comment: &str) -> String {
+        format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<ChangeResourceRecordSetsRequest xmlns="https://route53.amazonaws.com/doc/2013-04-01/">"#,
+                "<ChangeBatch><Comment>{}</Comment><Changes><Change><Action>UPSERT</Action>",
+                "<ResourceRecordSet><Name>{}</Name><Type>AAAA</Type><TTL>{}</TTL>",
+                "<ResourceRecords><ResourceRecord><Value>{}</Value></ResourceRecord></ResourceRecords>",
+                "</ResourceRecordSet></Change></Changes></ChangeBatch></ChangeResourceRecordSetsRequest>"
+            ),
+            xml_escape(comment), self.record_name, self.ttl, ipv6
+        )
+    }
+}
+
+#[async_trait]
+impl Provider for Route53 {
+    fn name(&self) -> &str {
+        &self.label
+    }
+
+    fn hostname(&self) -> Option<String> {
+        Some(self.record_name.trim_end_matches('.').to_string())
+    }
+
+    async fn update(&self, client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+        let path = format!("/2013-04-01/hostedzone/{}/rrset/", self.hosted_zone_id);
+        let body = self.change_batch(ipv6, &render_comment(&self.comment)?);
+
+        debug!("Upserting Route 53 AAAA record {} in zone {}", self.record_name, self.hosted_zone_id);
+        let text = self
+            .send_signed(client, Method::POST, &path, body)
+            .await
+            .map_err(|e| map_message(e, |e| format!("Route 53 update failed with {}", e)))?;
+
+        // UPSERT 不报告记录是否真的变化，提交成功即视为已更新
+        let change = format!(
+            "change {} {}",
+            xml_tag(&text, "Id").unwrap_or_default(),
+            xml_tag(&text, "Status").unwrap_or_default()
+        );
+        Ok(ProviderUpdate {
+            changed: true,
+            response: change,
+        })
+    }
+
+    // 只读取托管区域，验证凭证和区域ID，不提交变更
+    async fn check(&self, client: &Client, _ipv6: &str) -> Result<String, BoxError> {
+        let path = format!("/2013-04-01/hostedzone/{}", self.hosted_zone_id);
+        let text = self
+            .send_signed(client, Method::GET, &path, String::new())
+            .await
+            .map_err(|e| map_message(e, |e| format!("Reading Route 53 hosted zone failed with {}", e)))?;
+        Ok(format!("hosted zone {} readable", xml_tag(&text, "Name").unwrap_or(&self.hosted_zone_id)))
+    }
+}
+
+impl Route53 {
+    // 发送 SigV4 签名的请求，返回响应体；失败时错误信息为 "status <状态码>: <消息>"
+    async fn send_signed(&self, client: &Client, method: Method, path: &str, body: String) -> Result<String, BoxError> {
+        let credentials = Credentials::load()?;
+        let url = reqwest::Url::parse(&format!("{}{}", self.endpoint, path))?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut headers = vec![("host", host), ("x-amz-date", amz_date.clone())];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let authorization = sign_v4(
+            &credentials,
+            method.as_str(),
+            path,
+            "",
+            &headers,
+            body.as_bytes(),
+            &amz_date,
+            ROUTE53_REGION,
+            "route53",
+        );
+
+        let mut request = client
+            .request(method, url)
+            .header("Authorization", authorization)
+            .header("Content-Type", "application/xml");
+        for (name, value) in &headers {
+            if *name != "host" {
+                request = request.header(*name, value);
+            }
+        }
+        let response = with_timeout(request.body(body), self.timeout).send().await?;
+        log_egress(self.name(), &response);
+
+        let status = response.status();
+        let text = read_body_limited(response, self.max_response_bytes)
+            .await
+            .map_err(|e| format!("Route 53 response rejected: {}", e))?;
+        debug!("Route 53 response - Status: {}, Body: {}", status, text);
+
+        if !status.is_success() {
+            let message = xml_tag(&text, "Message").unwrap_or(text.trim());
+            return Err(status_error(status, format!("status {}: {}", status, message)));
+        }
+        Ok(text)
+    }
+}
+
+struct Credentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+impl Credentials {
+    fn load() -> Result<Self, BoxError> {
+        if let (Ok(access_key_id), Ok(secret_access_key)) =
+            (std::env::var("AWS_ACCESS_KEY_ID"), std::env::var("AWS_SECRET_ACCESS_KEY"))
+        {
+            return Ok(Self {
+                access_key_id,
+                secret_access_key,
+                session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+            });
+        }
+
+        let path = match std::env::var("AWS_SHARED_CREDENTIALS_FILE") {
+            Ok(path) => path,
+            Err(_) => format!("{}/.aws/credentials", std::env::var("HOME").unwrap_or_default()),
+        };
+        let profile = std::env::var("AWS_PROFILE").unwrap_or_else(|_| "default".to_string());
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("No AWS credentials in the environment and failed to read '{}': {}", path, e))?;
+        Self::from_ini(&contents, &profile).ok_or_else(|| format!("No AWS credentials for profile '{}' in '{}'", profile, path).into())
+    }
+
+    // 解析共享凭证文件中的 [profile] 段
+    fn from_ini(contents: &str, profile: &str) -> Option<Self> {
+        let mut in_profile = false;
+        let (mut access_key_id, mut secret_access_key, mut session_token) = (None, None, None);
+        for line in contents.lines().map(str::trim) {
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_profile = section.trim() == profile;
+                continue;
+            }
+            if !in_profile {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let value = Some(value.trim().to_string());
+                match key.trim() {
+                    "aws_access_key_id" => access_key_id = value,
+                    "aws_secret_access_key" => secret_access_key = value,
+                    "aws_session_token" => session_token = value,
+                    _ => {}
+                }
+            }
+        }
+        Some(Self {
+            access_key_id: access_key_id?,
+            secret_access_key: secret_access_key?,
+            session_token,
+        })
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// 计算 SigV4 Authorization 头；headers 的名称需为小写，且包含 host 和 x-amz-date
+#[allow(clippy::too_many_arguments)]
+fn sign_v4(
+    credentials: &Credentials,
+    method: &str,
+    path: &str,
+    query: &str,
+    headers: &[(&str, String)],
+    payload: &[u8],
+    amz_date: &str,
+    region: &str,
+    service: &str,
+) -> String {
+    let mut sorted: Vec<&(&str, String)> = headers.iter().collect();
+    sorted.sort_by_key(|(name, _)| *name);
+    let canonical_headers: String = sorted.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let signed_headers = sorted.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        path,
+        query,
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(payload))
+    );
+
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = hmac_sha256(format!("AWS4{}", credentials.secret_access_key).as_bytes(), date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    let key = hmac_sha256(&key, "aws4_request");
+    let signature = hex(&hmac_sha256(&key, &string_to_sign));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_credentials() -> Credentials {
+        Credentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        }
+    }
+
+    // AWS SigV4 测试套件中的 get-vanilla 用例
+    #[test]
+    fn test_sign_v4_matches_aws_test_suite() {
+        let headers = [
+            ("host", "example.amazonaws.com".to_string()),
+            ("x-amz-date", "20150830T123600Z".to_string()),
+        ];
+        let authorization = sign_v4(
+            &example_credentials(),
+            "GET",
+            "/",
+            "",
+            &headers,
+            b"",
+            "20150830T123600Z",
+            "us-east-1",
+            "service",
+        );
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn test_credentials_from_ini() {
+        let ini = "[default]\naws_access_key_id = AKID1\naws_secret_access_key = secret1\n\n\
+                   [ddns]\naws_access_key_id=AKID2\naws_secret_access_key=secret2\naws_session_token=token2\n";
+        let credentials = Credentials::from_ini(ini, "ddns").unwrap();
+        assert_eq!(credentials.access_key_id, "AKID2");
+        assert_eq!(credentials.secret_access_key, "secret2");
+        assert_eq!(credentials.session_token.as_deref(), Some("token2"));
+        assert!(Credentials::from_ini(ini, "default").unwrap().session_token.is_none());
+        assert!(Credentials::from_ini(ini, "missing").is_none());
+    }
+
+    #[test]
+    fn test_change_batch_and_response_parsing() {
+        let config = Config {
+            route53_hosted_zone_id: Some("/hostedzone/Z123".to_string()),
+            route53_record_name: Some("home.example.com.".to_string()),
+            ..crate::tests::test_config()
+        };
+        let providers = Route53::from_config(&config).unwrap();
+        assert_eq!(providers[0].name(), "route53:home.example.com.");
+        assert_eq!(providers[0].hostname().as_deref(), Some("home.example.com"));
+
+        let provider = Route53 {
+            label: String::new(),
+            endpoint: ROUTE53_ENDPOINT.to_string(),
+            hosted_zone_id: "Z123".to_string(),
+            record_name: "home.example.com.".to_string(),
+            ttl: 60,
+            comment: DEFAULT_COMMENT.to_string(),
+            timeout: None,
+            max_response_bytes: crate::DEFAULT_MAX_RESPONSE_BYTES,
+        };
+        let batch = provider.change_batch("2001:db8::1", "a <b> & c");
+        assert!(batch.contains("<Comment>a &lt;b&gt; &amp; c</Comment>"));
+        assert!(batch.contains("<Action>UPSERT</Action>"));
+        assert!(batch.contains("<Name>home.example.com.</Name><Type>AAAA</Type><TTL>60</TTL>"));
+        assert!(batch.contains("<Value>2001:db8::1</Value>"));
+
+        let response = "<ChangeInfo><Id>/change/C1</Id><Status>PENDING</Status></ChangeInfo>";
+        assert_eq!(xml_tag(response, "Status"), Some("PENDING"));
+        assert_eq!(xml_tag(response, "Message"), None);
+    }
+}
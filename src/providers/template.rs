@@ -0,0 +1,258 @@
+// 通用HTTP模板提供商，用于没有专门支持的服务
+// update_url_template 中的 {domain}、{ip}、{ipv6}、{ipv4}、{token} 会被替换后发送 GET 请求
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use tracing::debug;
+
+use super::{client_builder, log_egress, render_template, status_error, with_timeout, Provider, ProviderUpdate};
+use crate::{read_body_limited, BoxError, Config};
+
+pub struct Template {
+    label: String,
+    url_template: String,
+    domain: String,
+    token: String,
+    success_match: Option<String>,
+    nochange_match: Option<String>,
+    // 视为成功的状态码，为空时任何 2xx 都视为成功
+    success_status: Vec<StatusCode>,
+    // 成功状态码中包含 3xx 时使用不跟随重定向的客户端，否则看到的是重定向后的状态码
+    client: Option<Client>,
+    timeout: Option<Duration>,
+    // 响应体的最大字节数
+    max_response_bytes: usize,
+}
+
+impl Template {
+    // template_domain 和 duckdns_domain 一样可以用逗号分隔多个域名，每个域名单独请求一次
+    pub fn from_config(config: &Config) -> Result<Vec<Box<dyn Provider>>, BoxError> {
+        let url_template = config
+            .update_url_template
+            .clone()
+            .ok_or("UPDATE_URL_TEMPLATE must be set")?;
+        let token = config.template_token.clone().unwrap_or_default();
+
+        let domains: Vec<&str> = match config.template_domain.as_deref() {
+            Some(domains) => domains.split(',').map(str::trim).filter(|d| !d.is_empty()).collect(),
+            None => Vec::new(),
+        };
+        if domains.is_empty() && url_template.contains("{domain}") {
+            return Err("UPDATE_URL_TEMPLATE uses {domain} but TEMPLATE_DOMAIN is not set".into());
+        }
+        let domains = if domains.is_empty() { vec![""] } else { domains };
+
+        let success_status = config
+            .template_success_status
+            .iter()
+            .map(|&code| StatusCode::from_u16(code).map_err(|_| format!("Invalid status code in TEMPLATE_SUCCESS_STATUS: {}", code)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let client = if success_status.iter().any(StatusCode::is_redirection) {
+            Some(client_builder(config)?.redirect(reqwest::redirect::Policy::none()).build()?)
+        } else {
+            None
+        };
+
+        let providers = domains
+            .into_iter()
+            .map(|domain| {
+                let label = if domain.is_empty() {
+                    "template".to_string()
+                } else {
+                    format!("template:{}", domain)
+                };
+                let provider = Self {
+                    label,
+                    url_template: url_template.clone(),
+                    domain: domain.to_string(),
+                    token: token.clone(),
+                    success_match: config.template_success_match.clone(),
+                    nochange_match: config.template_nochange_match.clone(),
+                    success_status: success_status.clone(),
+                    client: client.clone(),
+                    timeout: config.provider_timeout(),
+                    max_response_bytes: config.max_response_bytes,
+                };
+                // 启动时检查模板，避免到第一次更新时才发现占位符写错
+                provider.update_url("::1")?;
+                Ok(Box::new(provider) as Box<dyn Provider>)
+            })
+            .collect::<Result<Vec<_>, BoxError>>()?;
+        Ok(providers)
+    }
+
+    // 本工具只发布IPv6地址，{ipv4} 替换为空字符串，便于兼容要求同时提供两个参数的服务
+    fn update_url(&self, ipv6: &str) -> Result<String, BoxError> {
+        render_template(
+            &self.url_template,
+            &[
+                ("domain", &self.domain),
+                ("ip", ipv6),
+                ("ipv6", ipv6),
+                ("ipv4", ""),
+                ("token", &self.token),
+            ],
+        )
+    }
+
+    fn is_success(&self, status: StatusCode) -> bool {
+        if self.success_status.is_empty() {
+            status.is_success()
+        } else {
+            self.success_status.contains(&status)
+        }
+    }
+
+    // 未配置 template_success_match 时任何成功状态码的响应都视为成功；
+    // 响应包含 template_nochange_match 时视为地址未变化，否则视为已更新
+    fn parse_response(&self, body: &str) -> Result<bool, BoxError> {
+        if let Some(pattern) = self.success_match.as_deref()
+            && !body.contains(pattern)
+        {
+            return Err(format!("Template update response does not contain '{}': {}", pattern, body.trim()).into());
+        }
+        let unchanged = self.nochange_match.as_deref().is_some_and(|pattern| body.contains(pattern));
+        Ok(!unchanged)
+    }
+}
+
+#[async_trait]
+impl Provider for Template {
+    fn name(&self) -> &str {
+        &self.label
+    }
+
+    fn hostname(&self) -> Option<String> {
+        self.domain.contains('.').then(|| self.domain.clone())
+    }
+
+    async fn update(&self, client: &Client, ipv6: &str) -> Result<ProviderUpdate, BoxError> {
+        let url = self.update_url(ipv6)?;
+        debug!("Updating '{}' via URL template", self.label);
+
+        let request = self
+            .client
+            .as_ref()
+            .unwrap_or(client)
+            .get(&url)
+            .header("User-Agent", concat!("rs-refresh-ddns/", env!("CARGO_PKG_VERSION")));
+        let response = with_timeout(request, self.timeout).send().await?;
+        log_egress(self.name(), &response);
+
+        let status = response.status();
+        let body = read_body_limited(response, self.max_response_bytes)
+            .await
+            .map_err(|e| format!("Template update response rejected: {}", e))?;
+        debug!("Template update response - Status: {}, Body: {}", status, body);
+
+        if !self.is_success(status) {
+            return Err(status_error(status, format!("Template update failed with status: {}", status)));
+        }
+
+        let changed = self.parse_response(&body)?;
+        Ok(ProviderUpdate {
+            changed,
+            response: body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template_config(template: &str, domain: Option<&str>) -> Config {
+        Config {
+            update_url_template: Some(template.to_string()),
+            template_domain: domain.map(str::to_string),
+            template_token: Some("secret".to_string()),
+            ..crate::tests::test_config()
+        }
+    }
+
+    #[test]
+    fn test_from_config_validates_template() {
+        let config = template_config("https://example.com/nic?host={domain}&ip={ip}", Some("a.example.com,b"));
+        let providers = Template::from_config(&config).unwrap();
+        let names: Vec<&str> = providers.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["template:a.example.com", "template:b"]);
+        assert_eq!(providers[0].hostname().as_deref(), Some("a.example.com"));
+        assert_eq!(providers[1].hostname(), None);
+
+        assert!(Template::from_config(&template_config("https://example.com/nic?host={domain}", None)).is_err());
+        assert!(Template::from_config(&template_config("https://example.com/nic?ip={address}", None)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_substitutes_placeholders() {
+        let (url, request) = crate::tests::serve_once(crate::tests::http_response("200 OK", "nochg")).await;
+        let template = format!("{}/nic?host={{domain}}&ip={{ipv6}}&v4={{ipv4}}&key={{token}}", url);
+        let config = Config {
+            template_nochange_match: Some("nochg".to_string()),
+            ..template_config(&template, Some("home"))
+        };
+        let providers = Template::from_config(&config).unwrap();
+
+        let update = providers[0].update(&Client::new(), "2001:db8::1").await.unwrap();
+        assert!(!update.changed);
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with("GET /nic?host=home&ip=2001:db8::1&v4=&key=secret HTTP/1.1"), "{}", request);
+    }
+
+    #[tokio::test]
+    async fn test_update_rejects_oversized_response() {
+        let body = "x".repeat(crate::DEFAULT_MAX_RESPONSE_BYTES + 1);
+        let (url, _) = crate::tests::serve_once(crate::tests::http_response("200 OK", &body)).await;
+        let providers = Template::from_config(&template_config(&format!("{}/nic?ip={{ip}}", url), None)).unwrap();
+
+        let err = providers[0].update(&Client::new(), "2001:db8::1").await.err().unwrap();
+        assert!(err.to_string().contains("Template update response rejected"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_response_matchers() {
+        let template = Template {
+            label: "template".to_string(),
+            url_template: "https://example.com/?ip={ip}".to_string(),
+            domain: String::new(),
+            token: String::new(),
+            success_match: Some("OK".to_string()),
+            nochange_match: Some("NOCHANGE".to_string()),
+            success_status: Vec::new(),
+            client: None,
+            timeout: None,
+            max_response_bytes: crate::DEFAULT_MAX_RESPONSE_BYTES,
+        };
+        assert!(template.parse_response("OK UPDATED").unwrap());
+        assert!(!template.parse_response("OK NOCHANGE").unwrap());
+        assert!(template.parse_response("ERROR").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_success_status() {
+        let (url, _) = crate::tests::serve_once(crate::tests::http_response("302 Found", "")).await;
+        let config = Config {
+            template_success_status: vec![200, 302],
+            ..template_config(&format!("{}/nic?ip={{ip}}", url), None)
+        };
+        let providers = Template::from_config(&config).unwrap();
+        assert!(providers[0].update(&Client::new(), "2001:db8::1").await.unwrap().changed);
+
+        // 配置后其他 2xx 不再视为成功
+        let (url, _) = crate::tests::serve_once(crate::tests::http_response("201 Created", "")).await;
+        let config = Config {
+            template_success_status: vec![200, 204],
+            ..template_config(&format!("{}/nic?ip={{ip}}", url), None)
+        };
+        let providers = Template::from_config(&config).unwrap();
+        assert!(providers[0].update(&Client::new(), "2001:db8::1").await.is_err());
+
+        let config = Config {
+            template_success_status: vec![42],
+            ..template_config("https://example.com/?ip={ip}", None)
+        };
+        assert!(Template::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+// 提供商请求的代理（provider_proxy），用于本身无法访问外网、但本机上的代理或代理程序可以访问的网络命名空间
+// http://host:port 为普通HTTP代理；unix:/path 为监听在 unix socket 上的HTTP代理，
+// reqwest 不能直接连接 unix socket，因此在 127.0.0.1 的随机端口上监听，把每个连接原样转发到该 socket，再把这个端口作为代理
+use reqwest::{ClientBuilder, Proxy};
+
+use crate::BoxError;
+
+pub fn apply(builder: ClientBuilder, spec: &str) -> Result<ClientBuilder, BoxError> {
+    let url = match spec.strip_prefix("unix:") {
+        Some(path) => format!("http://{}", unix_bridge(path)?),
+        None if spec.starts_with("http://") || spec.starts_with("https://") => spec.to_string(),
+        None => {
+            return Err(format!("Invalid PROVIDER_PROXY: {}. Expected http://host:port or unix:/path/to/socket", spec).into());
+        }
+    };
+    let proxy = Proxy::all(&url).map_err(|e| format!("Invalid PROVIDER_PROXY '{}': {}", spec, e))?;
+    Ok(builder.proxy(proxy))
+}
+
+#[cfg(unix)]
+fn unix_bridge(path: &str) -> Result<std::net::SocketAddr, BoxError> {
+    use tokio::net::{TcpListener, UnixStream};
+    use tracing::{debug, warn};
+
+    if path.is_empty() {
+        return Err("PROVIDER_PROXY unix socket path is empty".into());
+    }
+    // 在运行时中创建，启动时就能发现路径写错，而不是等到第一次更新
+    std::fs::metadata(path).map_err(|e| format!("PROVIDER_PROXY socket '{}' is not accessible: {}", path, e))?;
+    let handle = tokio::runtime::Handle::try_current().map_err(|_| "PROVIDER_PROXY unix sockets need a running runtime")?;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    listener.set_nonblocking(true)?;
+    let addr = listener.local_addr()?;
+    let path = path.to_string();
+    let _guard = handle.enter();
+    let listener = TcpListener::from_std(listener)?;
+    handle.spawn(async move {
+        loop {
+            let mut inbound = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Provider proxy bridge stopped accepting connections: {}", e);
+                    return;
+                }
+            };
+            let path = path.clone();
+            tokio::spawn(async move {
+                let result = match UnixStream::connect(&path).await {
+                    Ok(mut outbound) => tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await.map(|_| ()),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    debug!("Provider proxy connection via '{}' ended with an error: {}", path, e);
+                }
+            });
+        }
+    });
+    debug!("Forwarding provider requests from {} to unix socket proxy", addr);
+    Ok(addr)
+}
+
+#[cfg(not(unix))]
+fn unix_bridge(_path: &str) -> Result<std::net::SocketAddr, BoxError> {
+    Err("PROVIDER_PROXY unix sockets are only supported on Unix".into())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_unix_socket_proxy() {
+        let path = std::env::temp_dir().join(format!("rs-refresh-ddns-proxy-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        // 作为HTTP代理的一端只应答一次，并返回收到的请求
+        let agent = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 8192];
+            let len = stream.read(&mut buf).await.unwrap();
+            let body = "via agent";
+            let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf[..len]).to_string()
+        });
+
+        let spec = format!("unix:{}", path.display());
+        let client = apply(reqwest::Client::builder(), &spec).unwrap().build().unwrap();
+        let body = client.get("http://provider.invalid/update").send().await.unwrap().text().await.unwrap();
+        assert_eq!(body, "via agent");
+        // 经过代理时请求行使用完整地址
+        assert!(agent.await.unwrap().starts_with("GET http://provider.invalid/update HTTP/1.1"));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(apply(reqwest::Client::builder(), &spec).is_err());
+        assert!(apply(reqwest::Client::builder(), "socks5://127.0.0.1:1080").is_err());
+        assert!(apply(reqwest::Client::builder(), "http://127.0.0.1:3128").is_ok());
+    }
+}
@@ -0,0 +1,296 @@
+// 生成带注释的示例 config.toml
+// 示例由 ConfigFile 序列化得到，新增字段时需要同时补充 sample() 和 FIELD_DOCS（有测试保证同步）
+use crate::logging::DEFAULT_LOG_FILE_RETENTION;
+use crate::{
+    BoxError, ConfigFile, DEFAULT_CRON, DEFAULT_MAX_BACKOFF_SECS, DEFAULT_MAX_CONCURRENT_UPDATES, DEFAULT_MAX_RESPONSE_BYTES,
+    DEFAULT_IP_SERVICE_RETRY_DELAY_MS, DEFAULT_PROVIDER_RETRY_DELAY_MS, DEFAULT_SHUTDOWN_TIMEOUT_SECS,
+};
+
+// (字段名, 说明, 是否在示例中默认启用；未启用的字段以注释形式给出)
+const FIELD_DOCS: &[(&str, &str, bool)] = &[
+    ("cron", "Cron表达式（6段，包含秒），定义任务执行时间，下面是本构建的默认值", true),
+    ("ipv6_method", "IPv6获取方式，可选值：external, local, shell, upnp, route, env, external_then_local, local_then_external", true),
+    ("ip_service_url", "外部IPv6获取服务地址", true),
+    ("providers", "启用的DNS提供商，可选值：duckdns, dyndns2, cloudflare, template, route53, inwx, porkbun, hosts_file", true),
+    ("duckdns_domain", "DuckDNS域名（不包含.duckdns.org），多个域名用逗号分隔", true),
+    ("duckdns_token", "DuckDNS令牌，轮换时可以用逗号分隔多个令牌，按顺序尝试", true),
+    ("dyndns2_server", "DynDNS2 服务地址", false),
+    ("dyndns2_username", "DynDNS2 用户名", false),
+    ("dyndns2_password", "DynDNS2 密码", false),
+    ("dyndns2_hostname", "DynDNS2 主机名", false),
+    ("cloudflare_api_token", "cloudflare 提供商的 API 令牌，需要以 cloudflare feature 编译，令牌需要该区域的 DNS 编辑权限", false),
+    ("cloudflare_zone_id", "cloudflare 提供商的区域 ID，在域名概览页的右下角", false),
+    ("cloudflare_record_name", "cloudflare 提供商要更新的记录的完整域名，记录需要事先存在", false),
+    ("cloudflare_record_type", "cloudflare 提供商更新的记录类型，只修改该类型的记录；本工具只检测IPv6地址，目前只支持 AAAA，默认 AAAA", false),
+    ("cloudflare_proxied", "cloudflare 提供商更新后记录是否经过 Cloudflare 代理，不设置时保持记录当前的设置", false),
+    ("hosts_interface", "local 方式使用的网络接口，不设置则自动查找；可以是逗号分隔的候选列表，env:NAME 取环境变量的值", false),
+    ("shell_command", "shell 方式执行的命令及参数", false),
+    ("required_interface", "仅当该网络接口存在时才执行更新", false),
+    ("status_file", "每次运行后写入的状态文件路径", false),
+    ("max_concurrent_updates", "同时进行的提供商更新数量上限", false),
+    ("quiet_success", "地址未变化的成功运行只记录 debug 日志", false),
+    ("tls_min_version", "TLS最低版本，可选值：1.0, 1.1, 1.2, 1.3", false),
+    ("extra_ca_cert", "额外信任的CA证书（PEM格式）路径", false),
+    ("single_threaded", "使用单线程运行时以降低内存占用", false),
+    ("log_response_headers", "提供商更新失败时以 warn 级别记录响应头", false),
+    ("max_backoff_secs", "连续失败后暂停定时更新的最长时间（秒），0 关闭退避", false),
+    ("max_response_bytes", "外部服务和提供商响应体的最大字节数", false),
+    ("nochange_cooldown_secs", "提供商报告地址未变化后跳过定时运行的时间（秒），0 不跳过", false),
+    ("timezone", "解释 cron 表达式的时区：IANA 名称（如 Asia/Shanghai）或 local，默认 UTC", false),
+    ("error_log_window_secs", "相同的错误在此时间（秒）内只记录一次，之后定期汇总重复次数，0 每次都记录", false),
+    ("verify_via_dns", "更新前通过DNS查询域名当前的AAAA记录，已经是检测到的地址时跳过该提供商", false),
+    ("dns_resolver", "verify_via_dns 使用的DNS服务器（IP[:端口]），不设置时使用系统解析器", false),
+    ("mqtt_broker", "每次运行后把结果（JSON）发布到该 MQTT broker，需要以 mqtt feature 编译", false),
+    ("mqtt_topic", "发布结果的 MQTT 主题", false),
+    ("mqtt_username", "MQTT 用户名，不设置时匿名连接", false),
+    ("mqtt_password", "MQTT 密码", false),
+    ("post_update_command", "地址变化后执行的命令，新地址通过环境变量 DDNS_IP 和最后一个参数传入", false),
+    ("ip_service_urls", "多个外部IPv6获取服务，设置后代替 ip_service_url，按顺序尝试直到有一个成功", false),
+    ("ip_service_quorum", "大于 0 时并发查询所有服务，至少这么多个服务返回同一地址、且超过半数有效回答一致时才接受，0 不投票", false),
+    ("http_version", "HTTP协议版本：auto（自动协商）、http1（只用 HTTP/1.1）或 http2（直接使用 HTTP/2）", false),
+    ("log_level", "日志级别：error、warn、info、debug 或 trace，命令行的 -v/-q 优先", false),
+    ("update_url_template", "template 提供商的更新地址模板，支持 {domain}、{ip}、{ipv6}、{ipv4}、{token}", false),
+    ("template_domain", "替换 {domain} 的域名，多个用逗号分隔，每个域名单独请求", false),
+    ("template_token", "替换 {token} 的令牌", false),
+    ("template_success_match", "响应必须包含的文字，不设置时任何 2xx 响应都视为成功", false),
+    ("template_nochange_match", "响应包含该文字时视为地址未变化", false),
+    ("interface_priority", "local 方式下的接口优先级，靠前的接口优先，以 * 结尾时按前缀匹配", false),
+    ("pause_file", "该文件存在时跳过定时更新，删除后下一次触发恢复", false),
+    ("startup_delay_secs", "启动后等待多少秒再开始定时任务，给开机时的网络留出就绪时间", false),
+    ("notify_webhook_url", "地址变化或更新失败时 POST JSON 通知的地址，可用 --test-notify 测试", false),
+    ("cron_auto_seconds", "cron 只有 5 个字段（标准 crontab 写法）时自动在前面补上秒字段 \"0 \"，否则启动报错", false),
+    ("route53_hosted_zone_id", "route53 提供商的托管区域ID，需要以 route53 feature 编译", false),
+    ("route53_record_name", "route53 提供商更新的记录名", false),
+    ("route53_ttl", "route53 记录的TTL（秒），默认 300", false),
+    ("connectivity_check", "发布前从新地址向该 host:port 建立TCP连接，失败时本次不更新，下次触发时重试", false),
+    ("duckdns_base_url", "DuckDNS 接口地址，请求发送到 <地址>/update，默认 https://www.duckdns.org", false),
+    ("route_target", "ipv6_method = \"route\" 时用于选路的目标（IPv6地址或 host:port），默认 [2001:4860:4860::8888]:53；不会实际发送数据", false),
+    ("shutdown_timeout_secs", "收到 SIGTERM/Ctrl-C 后等待进行中的更新完成的最长时间（秒），超时后强制退出，默认 15", false),
+    ("allow_ci", "检测到 CI 环境（如 CI=true）时默认只演练不更新，设为 true 允许在 CI 中真正更新", false),
+    ("http_timeout_secs", "所有HTTP请求的超时（秒），不设置则不限制", false),
+    ("ip_lookup_timeout_secs", "查询外部IP服务的超时（秒），不设置时使用 http_timeout_secs", false),
+    ("provider_timeout_secs", "提供商更新请求的超时（秒），不设置时使用 http_timeout_secs", false),
+    ("local_cache_secs", "local 方式的检测结果缓存时间（秒），期间网络接口的链路状态变化时立即失效，默认 0 不缓存", false),
+    ("pid_file", "使用 --daemonize 转入后台时写入进程号的文件，退出时删除", false),
+    ("event_driven", "监听内核的IPv6地址变化事件（仅 Linux），全局地址变化后立即更新；cron 仍按计划运行作为保活", false),
+    ("record_comment", "更新时写入的备注，{time} 替换为更新时间（UTC）；cloudflare 写入记录本身的备注，不设置时不修改；route53 只写入变更批次的 Comment（不显示在记录上），默认 rs-refresh-ddns", false),
+    ("ip_service_retry_delay_ms", "按顺序尝试多个IP服务时，两次尝试之间的基础等待时间（毫秒），之后每次翻倍并加入随机抖动，0 表示不等待，默认 500", false),
+    ("log_file", "同时写入的日志文件，按天轮转，实际文件名为 <路径>.<日期>", false),
+    ("log_file_retention", "保留的日志文件数量，默认 7", false),
+    ("log_console", "是否同时输出日志到控制台，设置了 log_file 时可以设为 false 只写文件，默认 true", false),
+    ("inwx_user", "inwx 提供商的账号，需要以 inwx feature 编译", false),
+    ("inwx_pass", "inwx 提供商的密码，不支持开启了两步验证的账号", false),
+    ("inwx_record_id", "inwx 提供商要更新的 AAAA 记录ID", false),
+    ("local_confirm_ms", "local 方式读到地址后等待这段时间（毫秒）再读一次，两次相同才发布，用于接口刚出现或消失时；默认 0 不确认", false),
+    ("allow_non_global_ipv6", "local 方式默认只发布全局地址，设为 true 时没有全局地址也可以发布 ULA 等非全局地址（如只在 VPN 内访问），会记录警告", false),
+    ("schedule_file", "包含 cron 和可选 timezone 的调度文件，设置后覆盖上面两项；运行期间修改会重新调度，新内容无效时保留原调度", false),
+    ("local_providers", "与公网提供商同时更新的内网提供商（如 hosts_file），失败只记录警告，不影响公网更新", false),
+    ("hosts_file", "hosts_file 提供商写入的 hosts 格式文件，如 Pi-hole 的 /etc/pihole/custom.list", false),
+    ("hosts_file_hostname", "hosts_file 中指向新地址的主机名，多个用逗号分隔", false),
+    ("domain_pattern", "展开为多个 DuckDNS 域名的模式，{a,b} 为列表，{1..3} 为数字范围，追加在 duckdns_domain 之后", false),
+    ("include_interfaces", "local 方式自动查找时只考虑这些接口，以 * 结尾时按前缀匹配，为空时考虑所有接口", false),
+    ("exclude_interfaces", "local 方式自动查找时跳过的接口，以 * 结尾时按前缀匹配，优先于 include_interfaces", false),
+    ("metrics_file", "每次运行后写入的 Prometheus 指标文件，供 node_exporter 的 textfile collector 收集", false),
+    ("ipv6_env_var", "ipv6_method = \"env\" 时读取地址的环境变量名，由容器平台注入", false),
+    ("change_confirm_secs", "检测到地址变化后等待的秒数，再次检测仍是新地址才发布，变回原地址时取消，默认 0 不确认", false),
+    ("publish_stable_only", "检测到本机的临时地址（隐私扩展）时改为发布同一前缀下的稳定地址，仅 Linux", false),
+    ("template_success_status", "template 提供商视为成功的HTTP状态码，为空时任何 2xx 都视为成功；包含 3xx 时不跟随重定向", false),
+    ("provider_mode", "all 更新所有提供商；failover 按 providers 的顺序逐个尝试，第一个成功后停止", false),
+    ("post_update_verify_url", "发布新地址后请求的外部可达性检测地址，{ip}/{ipv6} 替换为新地址，非 2xx 响应时记录错误并发送 unreachable 通知", false),
+    ("skip_ipv6_startup_check", "跳过启动时的IPv6出口检查（向 connectivity_check 或 one.one.one.one:443 建立IPv6连接，失败时只记录警告）", false),
+    ("porkbun_apikey", "porkbun 提供商的 API Key，需要以 porkbun feature 编译，域名需要在后台开启 API Access", false),
+    ("porkbun_secretkey", "porkbun 提供商的 Secret API Key", false),
+    ("porkbun_domain", "porkbun 提供商的根域名", false),
+    ("porkbun_subdomain", "porkbun 提供商要更新的子域名，不设置时更新根域名本身的 AAAA 记录", false),
+    ("provider_proxy", "提供商请求经过的HTTP代理：http://host:port，或 unix:/path 表示监听在 unix socket 上的HTTP代理；只影响提供商请求", false),
+    ("pre_update_delay_secs", "检测到新地址后等待的秒数，再调用提供商，地址未变化时不等待，默认 0", false),
+    ("ip_service_family", "连接外部IP服务使用的协议族：auto（由系统决定）、ipv6 或 ipv4，双栈服务按连接所用的协议族返回地址", false),
+    ("notify_on", "何时发送 webhook 通知：change（地址变化）、failure（失败）、change_or_failure 或 always（每次运行，可作为心跳）", false),
+    ("allowed_ipv6_cidrs", "只发布这些前缀内的地址，检测到其他地址时本次运行失败；为空时不限制", false),
+    ("denied_ipv6_cidrs", "不发布这些前缀内的地址（如 VPN 分配的地址），优先于 allowed_ipv6_cidrs", false),
+    ("identity_probe_url", "发布新地址后的重复地址检查：请求该地址（{ip}/{ipv6} 替换为新地址），响应不包含 identity_probe_expect 时记录警告；设置了 dns_resolver 时还确认主机名解析到新地址", false),
+    ("identity_probe_expect", "identity_probe_url 的响应中应包含的本机标识，设置 identity_probe_url 时必须设置", false),
+    ("ip_lookup_retries", "IPv6地址检测失败后重试的次数（整个检测过程重新执行），等待时间按 ip_service_retry_delay_ms 计算，默认 0", false),
+    ("provider_retries", "单个提供商更新失败后重试的次数，只重试网络错误和 HTTP 5xx、429 等暂时性的失败，默认 0", false),
+    ("provider_retry_delay_ms", "提供商重试前的基础等待时间（毫秒），之后每次翻倍（最多60秒）并加入随机抖动，默认 2000", false),
+];
+
+// 所有字段都填入默认值或示例值
+fn sample() -> ConfigFile {
+    ConfigFile {
+        cron: Some(DEFAULT_CRON.to_string()),
+        ipv6_method: Some("external".to_string()),
+        ip_service_url: Some("https://6.ipw.cn".to_string()),
+        providers: Some(vec!["duckdns".to_string()]),
+        duckdns_domain: Some("your-domain".to_string()),
+        duckdns_token: Some("your-token".to_string()),
+        dyndns2_server: Some("https://updates.dnsomatic.com".to_string()),
+        dyndns2_username: Some("your-username".to_string()),
+        dyndns2_password: Some("your-password".to_string()),
+        dyndns2_hostname: Some("home.example.com".to_string()),
+        cloudflare_api_token: Some("cf_example_token".to_string()),
+        cloudflare_zone_id: Some("023e105f4ecef8ad9ca31a8372d0c353".to_string()),
+        cloudflare_record_name: Some("home.example.com".to_string()),
+        cloudflare_record_type: Some("AAAA".to_string()),
+        cloudflare_proxied: Some(false),
+        hosts_interface: Some("eth0".to_string()),
+        shell_command: Some(vec!["sh".to_string(), "-c".to_string(), "ip -6 addr show eth0".to_string()]),
+        required_interface: Some("wlan0".to_string()),
+        status_file: Some("/var/lib/rs-refresh-ddns/status.toml".to_string()),
+        max_concurrent_updates: Some(DEFAULT_MAX_CONCURRENT_UPDATES),
+        quiet_success: Some(false),
+        tls_min_version: Some("1.2".to_string()),
+        extra_ca_cert: Some("/etc/ssl/my-ca.pem".to_string()),
+        single_threaded: Some(false),
+        log_response_headers: Some(false),
+        max_backoff_secs: Some(DEFAULT_MAX_BACKOFF_SECS),
+        max_response_bytes: Some(DEFAULT_MAX_RESPONSE_BYTES),
+        nochange_cooldown_secs: Some(0),
+        timezone: Some("Asia/Shanghai".to_string()),
+        error_log_window_secs: Some(0),
+        verify_via_dns: Some(false),
+        dns_resolver: Some("1.1.1.1:53".to_string()),
+        mqtt_broker: Some("mqtt://192.168.1.2:1883".to_string()),
+        mqtt_topic: Some("home/ddns".to_string()),
+        mqtt_username: Some("ddns".to_string()),
+        mqtt_password: Some("your-password".to_string()),
+        post_update_command: Some(vec!["/usr/local/bin/update-firewall.sh".to_string()]),
+        ip_service_urls: Some(vec!["https://6.ipw.cn".to_string(), "https://api6.ipify.org".to_string(), "https://v6.ident.me".to_string()]),
+        ip_service_quorum: Some(0),
+        http_version: Some("auto".to_string()),
+        log_level: Some("info".to_string()),
+        update_url_template: Some("https://example.com/nic/update?host={domain}&ip={ip}&key={token}".to_string()),
+        template_domain: Some("home.example.com".to_string()),
+        template_token: Some("your-token".to_string()),
+        template_success_match: Some("good".to_string()),
+        template_nochange_match: Some("nochg".to_string()),
+        interface_priority: Some(vec!["eth0".to_string(), "ppp*".to_string()]),
+        pause_file: Some("/var/lib/rs-refresh-ddns/pause".to_string()),
+        startup_delay_secs: Some(0),
+        notify_webhook_url: Some("https://hooks.example.com/ddns".to_string()),
+        cron_auto_seconds: Some(false),
+        route53_hosted_zone_id: Some("Z0123456789ABCDEFGHIJ".to_string()),
+        route53_record_name: Some("home.example.com".to_string()),
+        route53_ttl: Some(300),
+        connectivity_check: Some("one.one.one.one:443".to_string()),
+        duckdns_base_url: Some("https://www.duckdns.org".to_string()),
+        route_target: Some("[2001:4860:4860::8888]:53".to_string()),
+        shutdown_timeout_secs: Some(DEFAULT_SHUTDOWN_TIMEOUT_SECS),
+        allow_ci: Some(false),
+        http_timeout_secs: Some(30),
+        ip_lookup_timeout_secs: Some(10),
+        provider_timeout_secs: Some(60),
+        local_cache_secs: Some(0),
+        pid_file: Some("/var/run/rs-refresh-ddns.pid".to_string()),
+        event_driven: Some(false),
+        record_comment: Some("updated by rs-refresh-ddns at {time}".to_string()),
+        ip_service_retry_delay_ms: Some(DEFAULT_IP_SERVICE_RETRY_DELAY_MS),
+        log_file: Some("/var/log/rs-refresh-ddns/ddns.log".to_string()),
+        log_file_retention: Some(DEFAULT_LOG_FILE_RETENTION),
+        log_console: Some(true),
+        inwx_user: Some("example-user".to_string()),
+        inwx_pass: Some("your-password".to_string()),
+        inwx_record_id: Some(123456789),
+        local_confirm_ms: Some(0),
+        allow_non_global_ipv6: Some(false),
+        schedule_file: Some("/etc/rs-refresh-ddns/schedule.toml".to_string()),
+        local_providers: Some(vec!["hosts_file".to_string()]),
+        hosts_file: Some("/etc/pihole/custom.list".to_string()),
+        hosts_file_hostname: Some("nas.lan".to_string()),
+        domain_pattern: Some("{a,b,c}.example".to_string()),
+        include_interfaces: Some(vec!["eth*".to_string(), "ppp*".to_string()]),
+        exclude_interfaces: Some(vec!["docker0".to_string(), "veth*".to_string(), "tailscale0".to_string()]),
+        metrics_file: Some("/var/lib/node_exporter/textfile_collector/ddns.prom".to_string()),
+        ipv6_env_var: Some("POD_IPV6".to_string()),
+        change_confirm_secs: Some(0),
+        publish_stable_only: Some(false),
+        template_success_status: Some(vec![200, 204]),
+        provider_mode: Some("all".to_string()),
+        post_update_verify_url: Some("https://checker.example.com/tcp?host={ip}&port=443".to_string()),
+        skip_ipv6_startup_check: Some(false),
+        porkbun_apikey: Some("pk1_example".to_string()),
+        porkbun_secretkey: Some("sk1_example".to_string()),
+        porkbun_domain: Some("example.com".to_string()),
+        porkbun_subdomain: Some("home".to_string()),
+        provider_proxy: Some("unix:/run/ddns-agent/proxy.sock".to_string()),
+        pre_update_delay_secs: Some(0),
+        ip_service_family: Some("auto".to_string()),
+        notify_on: Some("change_or_failure".to_string()),
+        allowed_ipv6_cidrs: Some(vec!["2001:db8::/32".to_string()]),
+        denied_ipv6_cidrs: Some(vec!["fd7a:115c:a1e0::/48".to_string()]),
+        identity_probe_url: Some("https://checker.example.com/fetch?url=http://[{ip}]:8080/ddns-id".to_string()),
+        identity_probe_expect: Some("nas-7f3a".to_string()),
+        ip_lookup_retries: Some(0),
+        provider_retries: Some(0),
+        provider_retry_delay_ms: Some(DEFAULT_PROVIDER_RETRY_DELAY_MS),
+    }
+}
+
+// 是否设置了任何与配置字段对应的环境变量（字段名的大写形式）
+#[cfg_attr(not(feature = "embedded-config"), allow(dead_code))]
+pub fn any_env_set(is_set: impl Fn(&str) -> bool) -> bool {
+    FIELD_DOCS.iter().any(|(name, _, _)| is_set(&name.to_uppercase()))
+}
+
+// 每个配置字段在示例配置中的值，示例中所有字段都有值，可用来判断字段的类型
+pub fn field_types() -> Result<toml::Table, BoxError> {
+    match toml::Value::try_from(sample())? {
+        toml::Value::Table(table) => Ok(table),
+        _ => Err("Sample config did not serialize to a table".into()),
+    }
+}
+
+pub fn generate() -> Result<String, BoxError> {
+    let serialized = toml::to_string(&sample())?;
+    let mut output = String::from("# rs-refresh-ddns 示例配置\n");
+
+    for line in serialized.lines() {
+        let key = line.split('=').next().unwrap_or_default().trim();
+        let (_, doc, active) = FIELD_DOCS
+            .iter()
+            .find(|(name, _, _)| *name == key)
+            .ok_or_else(|| format!("Missing documentation for config field '{}'", key))?;
+
+        output.push('\n');
+        output.push_str(&format!("# {}\n", doc));
+        if *active {
+            output.push_str(&format!("{}\n", line));
+        } else {
+            output.push_str(&format!("# {}\n", line));
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_env_set() {
+        assert!(!any_env_set(|_| false));
+        assert!(any_env_set(|name| name == "DUCKDNS_TOKEN"));
+        assert!(!any_env_set(|name| name == "HOME"));
+    }
+
+    #[test]
+    fn test_sample_covers_every_field() {
+        let serialized = toml::to_string(&sample()).unwrap();
+        let keys: Vec<&str> = serialized
+            .lines()
+            .map(|line| line.split('=').next().unwrap().trim())
+            .collect();
+        let documented: Vec<&str> = FIELD_DOCS.iter().map(|(name, _, _)| *name).collect();
+        assert_eq!(keys, documented);
+    }
+
+    #[test]
+    fn test_generated_sample_parses() {
+        let generated = generate().unwrap();
+        let parsed: ConfigFile = toml::from_str(&generated).unwrap();
+        assert_eq!(parsed.cron.as_deref(), Some(DEFAULT_CRON));
+        assert_eq!(parsed.duckdns_domain.as_deref(), Some("your-domain"));
+        assert!(parsed.required_interface.is_none());
+    }
+}
@@ -0,0 +1,73 @@
+// 可热加载的调度文件（schedule_file）
+// 文件中的 cron 和 timezone 覆盖主配置，运行期间修改文件后重新调度，新内容无效时保留原来的调度
+use std::path::{Path, PathBuf};
+
+use ::notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use crate::BoxError;
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Schedule {
+    pub cron: String,
+    pub timezone: Option<String>,
+}
+
+pub fn load(path: &str) -> Result<Schedule, BoxError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read schedule file '{}': {}", path, e))?;
+    toml::from_str(&contents).map_err(|e| format!("Invalid schedule file '{}': {}", path, e).into())
+}
+
+// 监视调度文件，文件被修改、替换或新建时向通道发送通知
+// 监视的是所在目录，编辑器保存时常常写入临时文件再改名，直接监视文件会在第一次保存后失效
+pub fn watch(path: &str) -> Result<(RecommendedWatcher, mpsc::UnboundedReceiver<()>), BoxError> {
+    let path = std::path::absolute(path)?;
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("."));
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let target = path.clone();
+    let mut watcher = ::notify::recommended_watcher(move |event: ::notify::Result<::notify::Event>| match event {
+        Ok(event) if is_change(&event.kind) && event.paths.contains(&target) => {
+            let _ = tx.send(());
+        }
+        Ok(_) => {}
+        Err(e) => debug!("Schedule file watch error: {}", e),
+    })?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch '{}': {}", dir.display(), e))?;
+    Ok((watcher, rx))
+}
+
+fn is_change(kind: &EventKind) -> bool {
+    matches!(kind, EventKind::Create(_) | EventKind::Modify(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!("rs-refresh-ddns-schedule-{}.toml", std::process::id()));
+        let path_str = path.to_string_lossy().to_string();
+
+        std::fs::write(&path, "cron = \"0 */10 * * * *\"\ntimezone = \"Asia/Shanghai\"\n").unwrap();
+        assert_eq!(
+            load(&path_str).unwrap(),
+            Schedule {
+                cron: "0 */10 * * * *".to_string(),
+                timezone: Some("Asia/Shanghai".to_string()),
+            }
+        );
+
+        std::fs::write(&path, "crn = \"0 */10 * * * *\"\n").unwrap();
+        assert!(load(&path_str).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(load(&path_str).is_err());
+    }
+}
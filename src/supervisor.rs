@@ -0,0 +1,858 @@
+// 调度与运行状态管理
+// Supervisor 持有配置、共享的HTTP客户端、提供商以及运行状态（上次地址、连续失败次数等），
+// 定时任务和单次运行都通过它执行更新
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::TimeZone;
+use reqwest::Client;
+use tokio::time;
+use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
+use uuid::Uuid;
+use tracing::{debug, error, info, warn, Instrument};
+
+use crate::cidr;
+use crate::connectivity;
+use crate::hook;
+use crate::notify::{self, NotifyOn};
+use crate::schedule;
+use crate::local::LocalCache;
+use crate::log_throttle::{Decision, ErrorThrottle};
+use crate::metrics::{self, Metrics};
+use crate::providers::{self, Provider};
+use crate::{
+    build_http_client, ci_dry_run, outcome_of, should_run, update_ddns, BoxError, Config, ProviderResult, UpdateOutcome,
+};
+
+// 连续失败后的退避基数
+const BACKOFF_BASE: Duration = Duration::from_secs(60);
+
+// 收到地址事件后等待一小段时间再更新，合并同一次变化产生的多个事件（如新地址加入、旧地址废弃）
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const EVENT_DEBOUNCE: Duration = Duration::from_secs(2);
+
+// 调度文件变化后等待的时间，合并一次保存产生的多个文件事件
+const SCHEDULE_RELOAD_DEBOUNCE: Duration = Duration::from_millis(500);
+
+pub struct Supervisor {
+    config: Config,
+    client: Client,
+    providers: Vec<Box<dyn Provider>>,
+    // 内网提供商，失败只记录日志
+    local_providers: Vec<Box<dyn Provider>>,
+    // 本地检测的缓存，每个配置档案各自一份
+    local_cache: LocalCache,
+    cron: String,
+    timezone: ScheduleTimezone,
+    notify_on: NotifyOn,
+    state: Mutex<SupervisorState>,
+    error_throttle: Mutex<ErrorThrottle>,
+    // 定时更新运行期间持有读锁，停止时获取写锁以等待进行中的更新结束
+    in_flight: tokio::sync::RwLock<()>,
+    // 第一个提供商失败时放弃其余更新（--once --fail-fast）
+    fail_fast: bool,
+}
+
+// 解释 cron 表达式使用的时区
+#[derive(Debug, PartialEq)]
+enum ScheduleTimezone {
+    Utc,
+    Local,
+    Named(chrono_tz::Tz),
+}
+
+impl ScheduleTimezone {
+    // 未配置时沿用 UTC，"local" 表示系统本地时区，其余按 IANA 名称解析（如 Asia/Shanghai）
+    fn parse(name: Option<&str>) -> Result<Self, BoxError> {
+        match name {
+            None | Some("UTC") | Some("utc") => Ok(Self::Utc),
+            Some("local") => Ok(Self::Local),
+            Some(name) => name
+                .parse()
+                .map(Self::Named)
+                .map_err(|_| format!("Invalid timezone '{}', expected an IANA name like 'Asia/Shanghai'", name).into()),
+        }
+    }
+}
+
+impl std::fmt::Display for ScheduleTimezone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Utc => write!(f, "UTC"),
+            Self::Local => write!(f, "local ({})", chrono::Local::now().format("%:z")),
+            Self::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SupervisorState {
+    // 上次成功发布的地址
+    last_ip: Option<IpAddr>,
+    // 连续失败次数
+    consecutive_failures: u32,
+    // 退避结束时间，在此之前的定时运行会被跳过
+    backoff_until: Option<Instant>,
+    // 提供商报告地址未变化后的冷却结束时间，在此之前连检测也跳过
+    nochange_until: Option<Instant>,
+    // 最近一次成功运行和最近一次发布了新地址的时间，写入 metrics_file
+    last_success: Option<SystemTime>,
+    last_change: Option<SystemTime>,
+    // 上次写入失败的状态文件，重复的失败不再警告
+    unwritable_files: HashSet<String>,
+}
+
+impl Supervisor {
+    // 创建客户端和提供商，配置错误时直接失败
+    pub fn new(config: Config) -> Result<Self, BoxError> {
+        let client = build_http_client(&config)?;
+        let mut providers = providers::build_providers(&config)?;
+        let mut local_providers = providers::build_local_providers(&config)?;
+        if ci_dry_run(&config, &|name| std::env::var(name).ok()) {
+            warn!("!!! CI environment detected, running in dry-run mode: no DNS record will be changed. Set ALLOW_CI=true to update for real");
+            providers = providers::dry_run(providers);
+            local_providers = providers::dry_run(local_providers);
+        }
+        // 配置了调度文件时以文件中的 cron 和时区为准，启动时文件必须存在且有效
+        let (cron, timezone) = match config.schedule_file.as_deref() {
+            Some(path) => {
+                let schedule = schedule::load(path)?;
+                (schedule.cron, schedule.timezone)
+            }
+            None => (config.cron().to_string(), config.timezone().map(str::to_string)),
+        };
+        let timezone = ScheduleTimezone::parse(timezone.as_deref())?;
+        let cron = resolve_cron(&cron, config.cron_auto_seconds)?;
+        let error_log_window = config.error_log_window_secs;
+        let notify_on = NotifyOn::parse(&config.notify_on)?;
+        // 启动时检查模板，避免到第一次发布后才发现占位符写错
+        if let Some(template) = config.post_update_verify_url.as_deref() {
+            connectivity::verify_url(template, std::net::Ipv6Addr::LOCALHOST)?;
+        }
+        if let Some(template) = config.identity_probe_url.as_deref() {
+            connectivity::verify_url(template, std::net::Ipv6Addr::LOCALHOST)?;
+            if config.identity_probe_expect.as_deref().is_none_or(str::is_empty) {
+                return Err("IDENTITY_PROBE_EXPECT must be set when IDENTITY_PROBE_URL is set".into());
+            }
+        }
+        // 前缀写错时启动即失败，而不是每次检测到地址后才报错
+        cidr::parse_list(&config.allowed_ipv6_cidrs)?;
+        cidr::parse_list(&config.denied_ipv6_cidrs)?;
+        let state = SupervisorState {
+            last_change: config.metrics_file.as_deref().and_then(metrics::read_last_change),
+            ..Default::default()
+        };
+
+        #[cfg(not(feature = "mqtt"))]
+        if config.mqtt_broker.is_some() {
+            warn!("MQTT_BROKER is set but this build does not include the 'mqtt' feature, results will not be published");
+        }
+
+        Ok(Self {
+            config,
+            client,
+            providers,
+            local_providers,
+            local_cache: LocalCache::default(),
+            cron,
+            timezone,
+            notify_on,
+            state: Mutex::new(state),
+            error_throttle: Mutex::new(ErrorThrottle::new(Duration::from_secs(error_log_window))),
+            in_flight: tokio::sync::RwLock::new(()),
+            fail_fast: false,
+        })
+    }
+
+    pub fn fail_fast(mut self, enabled: bool) -> Self {
+        self.fail_fast = enabled;
+        self
+    }
+
+    // 多个配置档案在同一进程中运行时，每行日志都带上档案名称
+    pub(crate) fn log_span(&self) -> tracing::Span {
+        match self.config.profile() {
+            Some(name) => tracing::error_span!("profile", name = %name),
+            None => tracing::Span::none(),
+        }
+    }
+
+    // 执行一次完整的检测和更新，记录状态并写入状态文件
+    pub async fn run_once(&self) -> Result<UpdateOutcome, BoxError> {
+        let last_ip = self.state.lock().unwrap().last_ip;
+        let result = update_ddns(
+            &self.config,
+            &self.client,
+            &self.local_cache,
+            &self.providers,
+            &self.local_providers,
+            last_ip,
+            self.fail_fast,
+        )
+        .await;
+
+        self.log_result(&result);
+        self.record_result(&result);
+
+        // 记录变化后执行用户命令，失败只记录日志；部分提供商失败时已更新的记录同样算作变化
+        if let Some(outcome) = outcome_of(&result)
+            && outcome.changed
+            && let (Some(command), Some(ip)) = (self.config.post_update_command.as_deref(), outcome.ip)
+            && let Err(e) = hook::run_post_update(command, &ip.to_string()).await
+        {
+            error!("{}", e);
+        }
+
+        let status = RunStatus::from_result(&result);
+
+        // 发布新地址后请外部服务确认可以访问，失败时记录错误并发送 unreachable 通知，不影响本次运行的结果
+        if let Some(outcome) = outcome_of(&result)
+            && outcome.changed
+            && let (Some(template), Some(IpAddr::V6(ip))) = (self.config.post_update_verify_url.as_deref(), outcome.ip)
+            && let Err(e) = connectivity::verify_external(&self.client, template, ip).await
+        {
+            error!("External reachability check failed: {}", e);
+            if let Some(url) = self.config.notify_webhook_url.as_deref()
+                && self.notify_on.reports_failures()
+                && let Err(e) = notify::send(&self.client, url, "unreachable", &status).await
+            {
+                warn!("Failed to send unreachable notification: {}", e);
+            }
+        }
+
+        // 重复地址检查，不一致时只记录警告
+        if let Some(outcome) = outcome_of(&result)
+            && outcome.changed
+            && let (Some(template), Some(IpAddr::V6(ip))) = (self.config.identity_probe_url.as_deref(), outcome.ip)
+        {
+            self.check_duplicate_address(template, ip).await;
+        }
+
+        // 写入运行状态文件，供外部监控使用
+        if let Some(path) = self.config.status_file.as_deref() {
+            self.report_write("status", path, status.write_to(path));
+        }
+
+        if let Some(path) = self.config.metrics_file.as_deref() {
+            self.report_write("metrics", path, self.metrics().write_to(path));
+        }
+
+        // 按 notify_on 发送 webhook 通知，失败只记录日志
+        if let Some(url) = self.config.notify_webhook_url.as_deref()
+            && let Some(event) = self.notify_on.event(&result)
+            && let Err(e) = notify::send(&self.client, url, event, &status).await
+        {
+            warn!("Failed to send {} notification: {}", event, e);
+        }
+
+        // 发布到 MQTT，失败不影响本次运行的结果
+        #[cfg(feature = "mqtt")]
+        if self.config.mqtt_broker.is_some() {
+            let published = match serde_json::to_string(&status) {
+                Ok(payload) => crate::mqtt::publish(&self.config, payload).await,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = published {
+                warn!("Failed to publish update result to MQTT: {}", e);
+            }
+        }
+
+        result
+    }
+
+    // 立即执行一次检测和更新，不受 cron、退避和"未变化"冷却的限制，供嵌入方在自己的事件（如 webhook）中调用
+    // 丢弃本地地址缓存以确保重新检测；与定时更新一样，停止时会等待它结束
+    pub async fn force_update(&self) -> Result<UpdateOutcome, BoxError> {
+        self.local_cache.invalidate();
+        {
+            let mut state = self.state.lock().unwrap();
+            state.backoff_until = None;
+            state.nochange_until = None;
+        }
+
+        let _in_flight = self.in_flight.read().await;
+        self.run_once().await
+    }
+
+    // 记录运行结果，相同的错误按 error_log_window_secs 节流
+    fn log_result(&self, result: &Result<UpdateOutcome, BoxError>) {
+        let mut throttle = self.error_throttle.lock().unwrap();
+        if result.is_ok() {
+            let suppressed = throttle.reset();
+            if suppressed > 0 {
+                info!("DDNS update recovered, {} repeated errors were not logged", suppressed);
+            }
+        }
+
+        match result {
+            // 静默模式下，地址未变化的成功运行只记录 debug 日志
+            Ok(outcome) if !outcome.changed && self.config.quiet_success => {
+                debug!("DDNS update completed successfully: {} (no change)", outcome.ip_string())
+            }
+            Ok(outcome) => info!("DDNS update completed successfully: {}", outcome.ip_string()),
+            Err(e) => match throttle.check(&e.to_string(), Instant::now()) {
+                Decision::Log => error!("Failed to update DDNS: {}", e),
+                Decision::Suppress => debug!("Failed to update DDNS (repeated): {}", e),
+                Decision::Summary(count) => error!("Failed to update DDNS, still failing ({} times): {}", count, e),
+            },
+        }
+    }
+
+    // 按 cron 表达式定时运行，收到停止信号后返回
+    pub async fn run_scheduled(self: Arc<Self>) -> Result<(), BoxError> {
+        // 开机时网络可能还没就绪，等待一段固定时间后再启动定时任务
+        if self.config.startup_delay_secs > 0 {
+            info!("Waiting {}s before starting the scheduler", self.config.startup_delay_secs);
+            time::sleep(Duration::from_secs(self.config.startup_delay_secs)).await;
+        }
+
+        // 使用本地接口的方式指定的接口不存在时直接失败，放在启动延迟之后，给开机时较晚出现的接口留出时间
+        if matches!(self.config.ipv6_method(), "local" | "route" | "external_then_local" | "local_then_external")
+            && let Some(names) = self.config.hosts_interfaces()
+        {
+            if names.is_empty() {
+                return Err(crate::no_interface_resolved().into());
+            }
+            crate::local::check_interface_exists(&names)?;
+        }
+
+        // 本机没有可用的IPv6出口时检测和更新多半会失败，提前给出明确的提示，但不阻止启动
+        if !self.config.skip_ipv6_startup_check {
+            let target = self.config.connectivity_check.as_deref().unwrap_or(connectivity::DEFAULT_STARTUP_CHECK_TARGET);
+            if let Err(e) = connectivity::check_egress(target).await {
+                warn!(
+                    "IPv6 does not appear to be working on this host, address detection and updates may fail: {} \
+                     (set skip_ipv6_startup_check to disable this check)",
+                    e
+                );
+            }
+        }
+
+        let mut scheduler = JobScheduler::new().await?;
+
+        info!("Scheduling '{}' in timezone {}", self.cron, self.timezone);
+        let job = Self::scheduled_job(self.clone(), &self.cron, &self.timezone)?;
+
+        let mut job_id = scheduler.add(job).await?;
+        scheduler.start().await?;
+
+        let events = if self.config.event_driven {
+            self.clone().spawn_event_listener()
+        } else {
+            None
+        };
+
+        // 监视器需要在整个运行期间保持存活
+        let mut schedule_watch = match self.config.schedule_file.as_deref() {
+            Some(path) => Some(schedule::watch(path)?),
+            None => None,
+        };
+
+        // 保持运行直到收到停止信号，停止后不再触发新的更新；期间调度文件变化时重新调度
+        let shutdown = shutdown_signal();
+        tokio::pin!(shutdown);
+        let signal = loop {
+            let changed = async {
+                match schedule_watch.as_mut() {
+                    Some((_, changes)) => changes.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                signal = &mut shutdown => break signal?,
+                Some(()) = changed => {
+                    // 保存文件通常产生多个事件，稍等片刻合并后再读取
+                    time::sleep(SCHEDULE_RELOAD_DEBOUNCE).await;
+                    if let Some((_, changes)) = schedule_watch.as_mut() {
+                        while changes.try_recv().is_ok() {}
+                    }
+                    match self.reload_schedule(&scheduler, &job_id).await {
+                        Ok(new_id) => job_id = new_id,
+                        Err(e) => error!("Keeping the current schedule: {}", e),
+                    }
+                }
+            }
+        };
+        info!("Received {}, shutting down", signal);
+        scheduler.shutdown().await?;
+        if let Some(events) = events {
+            events.abort();
+        }
+
+        let grace = Duration::from_secs(self.config.shutdown_timeout_secs);
+        if !self.wait_for_in_flight(grace).await {
+            warn!("Update still running after {}s, exiting anyway", grace.as_secs());
+        }
+        Ok(())
+    }
+
+    // 地址变化时立即更新，cron 继续作为保活；订阅失败时只记录错误，仍按 cron 运行
+    #[cfg(target_os = "linux")]
+    fn spawn_event_listener(self: Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let mut events = match crate::netlink::watch_ipv6_addresses() {
+            Ok(events) => events,
+            Err(e) => {
+                error!("{}, falling back to the cron schedule only", e);
+                return None;
+            }
+        };
+        info!("Listening for IPv6 address changes");
+
+        let span = self.log_span();
+        Some(tokio::spawn(async move {
+            while events.recv().await.is_some() {
+                time::sleep(EVENT_DEBOUNCE).await;
+                while events.try_recv().is_ok() {}
+
+                info!("IPv6 address changed, updating now");
+                self.local_cache.invalidate();
+                // 地址确实变了，不受"未变化"冷却的限制
+                self.state.lock().unwrap().nochange_until = None;
+                self.tick().await;
+            }
+        }.instrument(span)))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn spawn_event_listener(self: Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        warn!("EVENT_DRIVEN is only supported on Linux, using the cron schedule only");
+        None
+    }
+
+    // 等待进行中的更新结束，超时返回 false
+    async fn wait_for_in_flight(&self, grace: Duration) -> bool {
+        time::timeout(grace, self.in_flight.write()).await.is_ok()
+    }
+
+    // 重新读取调度文件，新的调度添加成功后才移除旧的，任何一步失败都保留原来的调度
+    async fn reload_schedule(self: &Arc<Self>, scheduler: &JobScheduler, current: &Uuid) -> Result<Uuid, BoxError> {
+        let Some(path) = self.config.schedule_file.as_deref() else {
+            return Ok(*current);
+        };
+        let schedule = schedule::load(path)?;
+        let cron = resolve_cron(&schedule.cron, self.config.cron_auto_seconds)?;
+        let timezone = ScheduleTimezone::parse(schedule.timezone.as_deref())?;
+        let job = Self::scheduled_job(self.clone(), &cron, &timezone)
+            .map_err(|e| format!("Invalid CRON '{}' in '{}': {}", cron, path, e))?;
+
+        let new_id = scheduler.add(job).await?;
+        scheduler.remove(current).await?;
+        info!("Schedule file '{}' changed, now scheduling '{}' in timezone {}", path, cron, timezone);
+        Ok(new_id)
+    }
+
+    fn scheduled_job(supervisor: Arc<Self>, cron: &str, timezone: &ScheduleTimezone) -> Result<Job, JobSchedulerError> {
+        match timezone {
+            ScheduleTimezone::Utc => Self::scheduled_job_tz(supervisor, cron, chrono::Utc),
+            ScheduleTimezone::Local => Self::scheduled_job_tz(supervisor, cron, chrono::Local),
+            ScheduleTimezone::Named(tz) => Self::scheduled_job_tz(supervisor, cron, *tz),
+        }
+    }
+
+    fn scheduled_job_tz<TZ: TimeZone>(supervisor: Arc<Self>, cron: &str, timezone: TZ) -> Result<Job, JobSchedulerError> {
+        Job::new_async_tz(cron, timezone, move |_uuid, _l| {
+            let supervisor = supervisor.clone();
+            let span = supervisor.log_span();
+            Box::pin(
+                async move {
+                    supervisor.tick().await;
+                }
+                .instrument(span),
+            )
+        })
+    }
+
+    // 定时任务的一次触发
+    async fn tick(&self) {
+        // 不满足运行条件（例如不在指定网络中）时跳过本次更新
+        if !should_run(&self.config) {
+            return;
+        }
+
+        // 维护期间创建暂停文件即可停止更新，删除后下一次触发恢复
+        if let Some(path) = self.config.pause_file.as_deref()
+            && std::path::Path::new(path).exists()
+        {
+            info!("Pause file '{}' exists, skipping this run", path);
+            return;
+        }
+
+        let (backoff_until, nochange_until) = {
+            let state = self.state.lock().unwrap();
+            (state.backoff_until, state.nochange_until)
+        };
+        let now = Instant::now();
+        if backoff_until.is_some_and(|until| now < until) {
+            debug!("Backing off after repeated failures, skipping this run");
+            return;
+        }
+        if nochange_until.is_some_and(|until| now < until) {
+            debug!("Provider reported no change recently, skipping this run");
+            return;
+        }
+
+        let _in_flight = self.in_flight.read().await;
+        let _ = self.run_once().await;
+    }
+
+    fn metrics(&self) -> Metrics {
+        let state = self.state.lock().unwrap();
+        Metrics {
+            last_success: state.last_success,
+            last_change: state.last_change,
+            consecutive_failures: state.consecutive_failures,
+        }
+    }
+
+    fn record_result(&self, result: &Result<UpdateOutcome, BoxError>) {
+        let mut state = self.state.lock().unwrap();
+        // 部分提供商失败时已更新的记录同样算作变化
+        if outcome_of(result).is_some_and(|outcome| outcome.changed) {
+            state.last_change = Some(SystemTime::now());
+        }
+        match result {
+            Ok(outcome) => {
+                state.last_ip = outcome.ip.or(state.last_ip);
+                state.consecutive_failures = 0;
+                state.backoff_until = None;
+                state.last_success = Some(SystemTime::now());
+
+                let cooldown = Duration::from_secs(self.config.nochange_cooldown_secs);
+                state.nochange_until = (!outcome.changed && !cooldown.is_zero()).then(|| Instant::now() + cooldown);
+            }
+            Err(_) => {
+                state.nochange_until = None;
+                state.consecutive_failures += 1;
+                let max = Duration::from_secs(self.config.max_backoff_secs);
+                let delay = backoff_delay(state.consecutive_failures, max);
+                if !delay.is_zero() {
+                    warn!(
+                        "{} consecutive failures, pausing scheduled updates for {}s",
+                        state.consecutive_failures,
+                        delay.as_secs()
+                    );
+                    state.backoff_until = Some(Instant::now() + delay);
+                }
+            }
+        }
+    }
+}
+
+// 等待 Ctrl-C，Unix 上还包括容器停止时发送的 SIGTERM，返回信号名用于日志
+async fn shutdown_signal() -> Result<&'static str, BoxError> {
+    #[cfg(unix)]
+    {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.map(|_| "SIGINT").map_err(Into::into),
+            _ = sigterm.recv() => Ok("SIGTERM"),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await?;
+        Ok("Ctrl-C")
+    }
+}
+
+// 调度器要求 6 个字段（第一个是秒），从标准 crontab 复制来的 5 字段表达式会被拒绝或被误解，
+// 检测到时给出明确的提示，cron_auto_seconds 开启时自动在前面补上 "0 "
+fn resolve_cron(cron: &str, auto_seconds: bool) -> Result<String, BoxError> {
+    let fields = cron.split_whitespace().count();
+    if fields != 5 || cron.trim_start().starts_with('@') {
+        return Ok(cron.to_string());
+    }
+
+    let with_seconds = format!("0 {}", cron.trim());
+    if auto_seconds {
+        warn!(
+            "CRON '{}' has 5 fields but this scheduler expects 6 (seconds first), using '{}'",
+            cron, with_seconds
+        );
+        Ok(with_seconds)
+    } else {
+        Err(format!(
+            "CRON '{}' has 5 fields but this scheduler expects 6 with seconds first, e.g. '{}'; \
+             set CRON_AUTO_SECONDS=true to prepend '0 ' automatically",
+            cron, with_seconds
+        )
+        .into())
+    }
+}
+
+// 第 n 次连续失败后的退避时间：首次失败不退避，之后从 BACKOFF_BASE 起按指数增长，不超过 max
+fn backoff_delay(failures: u32, max: Duration) -> Duration {
+    if failures <= 1 {
+        return Duration::ZERO;
+    }
+    let factor = 1u32 << (failures - 2).min(16);
+    (BACKOFF_BASE * factor).min(max)
+}
+
+impl Supervisor {
+    // 发布后确认 identity_probe_url 回答的是本机，发现不一致时记录警告；
+    // 配置了 dns_resolver（应指向权威服务器）时还确认各主机名解析回新地址，系统解析器通常还缓存着旧记录，不做这项检查
+    async fn check_duplicate_address(&self, template: &str, ip: std::net::Ipv6Addr) {
+        if let Some(resolver) = self.config.dns_resolver.as_deref() {
+            for hostname in self.providers.iter().filter_map(|provider| provider.hostname()) {
+                if let Err(e) = connectivity::verify_resolves_to(&hostname, Some(resolver), ip).await {
+                    warn!("Published address check: {}", e);
+                }
+            }
+        }
+        let expect = self.config.identity_probe_expect.as_deref().unwrap_or_default();
+        if let Err(e) = connectivity::verify_identity(&self.client, template, expect, ip).await {
+            warn!("Duplicate address check: {}", e);
+        }
+    }
+
+    // 状态文件只供监控使用，写入失败（例如加固容器中目录只读）不影响检测和更新：
+    // 第一次失败时记录警告，之后重复的失败只记录 debug，恢复写入后提示一次
+    fn report_write(&self, kind: &str, path: &str, result: Result<(), BoxError>) {
+        let mut state = self.state.lock().unwrap();
+        match result {
+            Ok(()) => {
+                if state.unwritable_files.remove(path) {
+                    info!("The {} file '{}' is writable again", kind, path);
+                }
+            }
+            Err(e) if state.unwritable_files.insert(path.to_string()) => {
+                warn!("Failed to write {} file '{}', continuing without it: {}", kind, path, e);
+            }
+            Err(e) => debug!("Failed to write {} file '{}' (repeated): {}", kind, path, e),
+        }
+    }
+}
+
+// 最近一次运行的状态，写入 status_file
+#[derive(serde::Serialize)]
+pub(crate) struct RunStatus {
+    timestamp: String,
+    success: bool,
+    changed: bool,
+    ip: Option<String>,
+    provider_response: Option<String>,
+    error: Option<String>,
+    // 每个提供商的结果，放在最后以便序列化为 TOML 表数组
+    providers: Vec<ProviderResult>,
+}
+
+impl RunStatus {
+    pub(crate) fn from_result(result: &Result<UpdateOutcome, BoxError>) -> Self {
+        let outcome = outcome_of(result);
+
+        Self {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            success: result.is_ok(),
+            changed: outcome.is_some_and(|outcome| outcome.changed),
+            ip: outcome.and_then(|outcome| outcome.ip).map(|ip| ip.to_string()),
+            provider_response: outcome.map(|outcome| outcome.provider_response.clone()),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            providers: outcome.map(|outcome| outcome.providers.clone()).unwrap_or_default(),
+        }
+    }
+
+    // 先写临时文件再重命名，避免监控脚本读到写了一半的文件
+    fn write_to(&self, path: &str) -> Result<(), BoxError> {
+        let contents = toml::to_string(self)?;
+        let tmp_path = format!("{}.tmp", path);
+        std::fs::write(&tmp_path, contents)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(ScheduleTimezone::parse(None).unwrap(), ScheduleTimezone::Utc);
+        assert_eq!(ScheduleTimezone::parse(Some("local")).unwrap(), ScheduleTimezone::Local);
+        assert_eq!(
+            ScheduleTimezone::parse(Some("Asia/Shanghai")).unwrap(),
+            ScheduleTimezone::Named(chrono_tz::Asia::Shanghai)
+        );
+        assert!(ScheduleTimezone::parse(Some("Mars/Olympus")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_cron() {
+        assert_eq!(resolve_cron("0 */5 * * * *", false).unwrap(), "0 */5 * * * *");
+        assert_eq!(resolve_cron("*/5 * * * *", true).unwrap(), "0 */5 * * * *");
+        let err = resolve_cron("*/5 * * * *", false).unwrap_err();
+        assert!(err.to_string().contains("'0 */5 * * * *'"), "{}", err);
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let max = Duration::from_secs(3600);
+        assert_eq!(backoff_delay(0, max), Duration::ZERO);
+        assert_eq!(backoff_delay(1, max), Duration::ZERO);
+        assert_eq!(backoff_delay(2, max), Duration::from_secs(60));
+        assert_eq!(backoff_delay(3, max), Duration::from_secs(120));
+        assert_eq!(backoff_delay(4, max), Duration::from_secs(240));
+        assert_eq!(backoff_delay(100, max), max);
+        assert_eq!(backoff_delay(5, Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_record_result_tracks_failures() {
+        let supervisor = Supervisor::new(crate::tests::test_config()).unwrap();
+
+        supervisor.record_result(&Err("boom".into()));
+        supervisor.record_result(&Err("boom".into()));
+        {
+            let state = supervisor.state.lock().unwrap();
+            assert_eq!(state.consecutive_failures, 2);
+            assert!(state.backoff_until.is_some());
+        }
+
+        let outcome = UpdateOutcome {
+            changed: true,
+            ip: Some("2001:db8::1".parse().unwrap()),
+            provider_response: String::new(),
+            providers: Vec::new(),
+        };
+        supervisor.record_result(&Ok(outcome));
+        let state = supervisor.state.lock().unwrap();
+        assert_eq!(state.consecutive_failures, 0);
+        assert!(state.backoff_until.is_none());
+        assert_eq!(state.last_ip, Some("2001:db8::1".parse().unwrap()));
+        assert!(state.last_success.is_some());
+        assert!(state.last_change.is_some());
+    }
+
+    #[test]
+    fn test_record_result_nochange_cooldown() {
+        let config = Config {
+            nochange_cooldown_secs: 600,
+            ..crate::tests::test_config()
+        };
+        let supervisor = Supervisor::new(config).unwrap();
+
+        let outcome = |changed| UpdateOutcome {
+            changed,
+            ip: Some("2001:db8::1".parse().unwrap()),
+            provider_response: String::new(),
+            providers: Vec::new(),
+        };
+
+        supervisor.record_result(&Ok(outcome(false)));
+        assert!(supervisor.state.lock().unwrap().nochange_until.is_some());
+
+        supervisor.record_result(&Ok(outcome(true)));
+        assert!(supervisor.state.lock().unwrap().nochange_until.is_none());
+    }
+
+    #[test]
+    fn test_report_write_failure_is_not_fatal() {
+        let supervisor = Supervisor::new(crate::tests::test_config()).unwrap();
+        let dir = std::env::temp_dir().join(format!("rs-refresh-ddns-readonly-{}", std::process::id()));
+        let path = dir.join("metrics.prom").to_string_lossy().to_string();
+
+        // 目录不存在，写入失败只记录下来
+        let metrics = supervisor.metrics();
+        supervisor.report_write("metrics", &path, metrics.write_to(&path));
+        supervisor.report_write("metrics", &path, metrics.write_to(&path));
+        assert!(supervisor.state.lock().unwrap().unwritable_files.contains(&path));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        supervisor.report_write("metrics", &path, metrics.write_to(&path));
+        assert!(supervisor.state.lock().unwrap().unwritable_files.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_status_keeps_partial_results() {
+        let outcome = UpdateOutcome {
+            changed: true,
+            ip: Some("2001:db8::1".parse().unwrap()),
+            provider_response: "duckdns:a: OK".to_string(),
+            providers: vec![
+                ProviderResult {
+                    provider: "duckdns:a".to_string(),
+                    status: "updated",
+                    detail: "OK".to_string(),
+                },
+                ProviderResult {
+                    provider: "duckdns:b".to_string(),
+                    status: "failed",
+                    detail: "KO".to_string(),
+                },
+            ],
+        };
+        let result: Result<UpdateOutcome, BoxError> = Err(Box::new(crate::PartialFailure(outcome)));
+
+        let status = RunStatus::from_result(&result);
+        assert!(!status.success);
+        assert!(status.changed);
+        assert_eq!(status.ip.as_deref(), Some("2001:db8::1"));
+        assert_eq!(status.providers.len(), 2);
+        assert_eq!(status.error.as_deref(), Some("provider updates failed: 1 updated, 1 failed (duckdns:b: KO)"));
+        assert!(toml::to_string(&status).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tick_skips_while_paused() {
+        let pause_file = std::env::temp_dir().join(format!("rs-refresh-ddns-pause-{}", std::process::id()));
+        std::fs::write(&pause_file, "").unwrap();
+        let config = Config {
+            pause_file: Some(pause_file.to_string_lossy().to_string()),
+            ip_service_url: "http://127.0.0.1:1".to_string(),
+            ..crate::tests::test_config()
+        };
+        let supervisor = Supervisor::new(config).unwrap();
+
+        supervisor.tick().await;
+        assert_eq!(supervisor.state.lock().unwrap().consecutive_failures, 0);
+
+        std::fs::remove_file(&pause_file).unwrap();
+        supervisor.tick().await;
+        assert_eq!(supervisor.state.lock().unwrap().consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_force_update_ignores_backoff() {
+        let config = Config {
+            ip_service_url: "http://127.0.0.1:1".to_string(),
+            ..crate::tests::test_config()
+        };
+        let supervisor = Supervisor::new(config).unwrap();
+        supervisor.state.lock().unwrap().backoff_until = Some(Instant::now() + Duration::from_secs(600));
+
+        // 定时触发在退避期间被跳过，立即更新照常执行
+        supervisor.tick().await;
+        assert_eq!(supervisor.state.lock().unwrap().consecutive_failures, 0);
+        assert!(supervisor.force_update().await.is_err());
+        assert_eq!(supervisor.state.lock().unwrap().consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_in_flight() {
+        let supervisor = Supervisor::new(crate::tests::test_config()).unwrap();
+
+        let running = supervisor.in_flight.read().await;
+        assert!(!supervisor.wait_for_in_flight(Duration::from_millis(10)).await);
+
+        drop(running);
+        assert!(supervisor.wait_for_in_flight(Duration::from_millis(10)).await);
+    }
+
+    #[test]
+    fn test_run_status_records_failure() {
+        let result: Result<UpdateOutcome, BoxError> = Err("boom".into());
+        let status = RunStatus::from_result(&result);
+        assert!(!status.success);
+        assert_eq!(status.ip, None);
+        assert_eq!(status.error.as_deref(), Some("boom"));
+
+        let contents = toml::to_string(&status).unwrap();
+        assert!(contents.contains("success = false"));
+        assert!(contents.contains("error = \"boom\""));
+    }
+}
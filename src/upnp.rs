@@ -0,0 +1,175 @@
+// 通过 UPnP IGD 向路由器查询外部IPv6地址
+// 适用于可路由前缀由路由器持有、主机只通过前缀委派获得地址的家庭网络
+use std::time::Duration;
+
+use reqwest::{Client, Url};
+use tokio::net::UdpSocket;
+use tokio::time;
+use tracing::debug;
+
+use crate::BoxError;
+
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+// 依次尝试的服务类型
+const SEARCH_TARGETS: &[&str] = &[
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+// 依次尝试的 SOAP 动作及其返回字段
+// 标准 IGD 只定义了 GetExternalIPAddress（通常返回IPv4），部分路由器（如 FRITZ!Box）提供IPv6扩展
+const ACTIONS: &[(&str, &str)] = &[
+    ("X_AVM_DE_GetExternalIPv6Address", "NewExternalIPv6Address"),
+    ("GetExternalIPAddress", "NewExternalIPAddress"),
+];
+
+// 查询路由器的外部IPv6地址
+pub async fn get_ipv6_from_upnp(client: &Client) -> Result<String, BoxError> {
+    let location = discover_gateway().await?;
+    debug!("Found UPnP gateway at {}", location);
+
+    let description = client.get(location.clone()).send().await?.text().await?;
+    let (service_type, control_url) = find_wan_service(&description)
+        .ok_or("UPnP gateway does not expose a WANIPConnection/WANPPPConnection service")?;
+    let control_url = location.join(&control_url)?;
+
+    for (action, field) in ACTIONS {
+        match soap_call(client, &control_url, &service_type, action, field).await {
+            Ok(ip) if ip.contains(':') => {
+                debug!("Got IPv6 from UPnP action {}: {}", action, ip);
+                return Ok(ip);
+            }
+            Ok(ip) => debug!("UPnP action {} returned non-IPv6 address '{}'", action, ip),
+            Err(e) => debug!("UPnP action {} failed: {}", action, e),
+        }
+    }
+
+    Err("UPnP gateway did not report an external IPv6 address".into())
+}
+
+// 通过 SSDP 发现网关，返回设备描述文件地址
+async fn discover_gateway() -> Result<Url, BoxError> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    for target in SEARCH_TARGETS {
+        let request = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+            SSDP_ADDR, target
+        );
+        socket.send_to(request.as_bytes(), SSDP_ADDR).await?;
+    }
+
+    let mut buf = [0u8; 2048];
+    let deadline = time::Instant::now() + DISCOVERY_TIMEOUT;
+    loop {
+        let (len, _) = time::timeout_at(deadline, socket.recv_from(&mut buf))
+            .await
+            .map_err(|_| format!("No UPnP gateway responded within {}s", DISCOVERY_TIMEOUT.as_secs()))??;
+
+        if let Some(location) = parse_location(&String::from_utf8_lossy(&buf[..len])) {
+            return Ok(Url::parse(&location)?);
+        }
+    }
+}
+
+async fn soap_call(
+    client: &Client,
+    control_url: &Url,
+    service_type: &str,
+    action: &str,
+    field: &str,
+) -> Result<String, BoxError> {
+    let body = format!(
+        "<?xml version=\"1.0\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{action} xmlns:u=\"{service_type}\"></u:{action}></s:Body></s:Envelope>"
+    );
+
+    let response = client
+        .post(control_url.clone())
+        .header("Content-Type", "text/xml; charset=\"utf-8\"")
+        .header("SOAPAction", format!("\"{}#{}\"", service_type, action))
+        .body(body)
+        .send()
+        .await?;
+
+    let status = response.status();
+    let text = response.text().await?;
+    if !status.is_success() {
+        return Err(format!("SOAP request failed with status: {}", status).into());
+    }
+
+    xml_tag_text(&text, field)
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty())
+        .ok_or_else(|| format!("SOAP response has no {}", field).into())
+}
+
+// 从 SSDP 响应头中提取 LOCATION
+fn parse_location(response: &str) -> Option<String> {
+    response.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("location")
+            .then(|| value.trim().to_string())
+    })
+}
+
+// 在设备描述中查找 WAN 连接服务，返回 (serviceType, controlURL)
+fn find_wan_service(description: &str) -> Option<(String, String)> {
+    description.split("<service>").skip(1).find_map(|service| {
+        let service_type = xml_tag_text(service, "serviceType")?.trim();
+        if !service_type.contains("WANIPConnection") && !service_type.contains("WANPPPConnection") {
+            return None;
+        }
+        let control_url = xml_tag_text(service, "controlURL")?.trim();
+        Some((service_type.to_string(), control_url.to_string()))
+    })
+}
+
+// 取出第一个 <tag>...</tag> 中的文本
+fn xml_tag_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    Some(&xml[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_location() {
+        let response = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\nLocation: http://192.168.1.1:49000/igddesc.xml\r\n\r\n";
+        assert_eq!(parse_location(response).as_deref(), Some("http://192.168.1.1:49000/igddesc.xml"));
+        assert_eq!(parse_location("HTTP/1.1 200 OK\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn test_find_wan_service() {
+        let description = "<root><device><serviceList>\
+            <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType><controlURL>/l3f</controlURL></service>\
+            <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType><controlURL>/igdupnp/control/WANIPConn1</controlURL></service>\
+            </serviceList></device></root>";
+        assert_eq!(
+            find_wan_service(description),
+            Some((
+                "urn:schemas-upnp-org:service:WANIPConnection:1".to_string(),
+                "/igdupnp/control/WANIPConn1".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_xml_tag_text() {
+        let response = "<s:Envelope><s:Body><u:X_AVM_DE_GetExternalIPv6AddressResponse>\
+            <NewExternalIPv6Address>2001:db8::1</NewExternalIPv6Address>\
+            </u:X_AVM_DE_GetExternalIPv6AddressResponse></s:Body></s:Envelope>";
+        assert_eq!(xml_tag_text(response, "NewExternalIPv6Address"), Some("2001:db8::1"));
+        assert_eq!(xml_tag_text(response, "NewExternalIPAddress"), None);
+    }
+}